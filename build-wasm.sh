@@ -0,0 +1,10 @@
+#!/bin/bash
+set -e
+
+## Build the engine for the browser, needs `rustup target add wasm32-unknown-unknown` and `cargo install wasm-bindgen-cli`
+## wasm-bindgen needs a cdylib, it's only asked for here so every other build stays a plain rlib
+cargo rustc -p minesweeper-backend --lib --release --target wasm32-unknown-unknown --no-default-features --crate-type cdylib
+wasm-bindgen --target web --out-dir minesweeper-backend/web/pkg target/wasm32-unknown-unknown/release/minesweeper_backend.wasm
+
+## Build the UI next to it, then serve minesweeper-backend/web as static files
+(cd minesweeper-ui && elm make src/Main.elm --output=../minesweeper-backend/web/elm.js)
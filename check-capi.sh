@@ -0,0 +1,19 @@
+#!/bin/bash
+set -e
+
+## Check the C API, needs `cargo install cbindgen` and a C compiler
+cd minesweeper-capi
+
+## The header has to be exactly what cbindgen makes from src/lib.rs
+header=$(mktemp)
+trap 'rm -f "$header"' EXIT
+cbindgen --config cbindgen.toml --output "$header" --quiet
+if ! diff -u include/minesweeper.h "$header"; then
+    echo "include/minesweeper.h is out of date, regenerate it with: cd minesweeper-capi && cbindgen --config cbindgen.toml --output include/minesweeper.h"
+    exit 1
+fi
+
+## Then play a game through it from C, against the static library so nothing has to be found at runtime
+cargo build -p minesweeper-capi
+${CC:-cc} -std=c99 -Wall -Wextra -Werror -Iinclude tests/smoke.c ../target/debug/libminesweeper.a -lpthread -ldl -lm -o ../target/debug/capi-smoke
+../target/debug/capi-smoke
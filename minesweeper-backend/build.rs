@@ -5,7 +5,12 @@ use std::env;
 
 fn main()
 {
-    build_elm();
+    /* Only the desktop binaries embed the UI, the wasm build's page gets its own copy from build-wasm.sh */
+    let is_wasm = env::var("CARGO_CFG_TARGET_ARCH").map(|arch| arch == "wasm32").unwrap_or(false);
+    if env::var_os("CARGO_FEATURE_DESKTOP").is_some() && !is_wasm
+    {
+        build_elm();
+    }
 }
 
 fn build_elm()
@@ -23,7 +28,7 @@ fn build_elm()
 
     if Command::new("elm")
         .current_dir(ui_dir)
-        .args(&["make", elm_path, &format!("--output={}", elm_output)])
+        .args(["make", elm_path, &format!("--output={}", elm_output)])
         .status()
         .unwrap()
         .success()
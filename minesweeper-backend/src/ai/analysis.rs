@@ -0,0 +1,154 @@
+use ::common::{Horizontal, Vertical};
+use ::common::bitset::BitSet;
+use ::common::vec2d::Vec2d;
+use ::engine::packed::PackedTile;
+
+
+/// What can be worked out about a field from the tiles a player can see.
+/// Every shown number is trusted, even on a tile that was only revealed as a hint and might be a bomb itself,
+/// and flags are assumed to be right
+#[derive(Debug)]
+pub struct Analysis
+{
+    /// Tiles that aren't clicked or flagged and must be bombs
+    pub mines: Vec<(Horizontal, Vertical)>,
+    /// Tiles that aren't clicked or flagged and can't be bombs
+    pub safe: Vec<(Horizontal, Vertical)>,
+    /// Estimated chance of a bomb under every tile that isn't clicked or flagged, `None` for the rest
+    pub probabilities: Vec2d<Option<f64>>,
+}
+
+impl Analysis
+{
+    /// Every tile that isn't clicked or flagged, as x, y
+    pub fn unknown(&self) -> Vec<(Horizontal, Vertical)>
+    {
+        self.probabilities.enumerate()
+            .filter(|&(_, _, probability)| probability.is_some())
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+}
+
+fn is_unknown(tile: &PackedTile) -> bool
+{
+    !tile.was_clicked() && !tile.has_flag()
+}
+
+/// Applies the two single tile rules until nothing changes: a number with as many flags around it as it shows
+/// makes the rest of its neighbors safe, and a number with exactly as many unknown neighbors as it's missing makes them all bombs
+pub fn analyze(tiles: &Vec2d<PackedTile>, num_bombs: usize) -> Analysis
+{
+    let width = tiles.width().0;
+    let index = |x: Horizontal, y: Vertical| y.0 * width + x.0;
+
+    let mut mines = BitSet::new(tiles.len());
+    let mut safe = BitSet::new(tiles.len());
+
+    let mut changed = true;
+    while changed
+    {
+        changed = false;
+
+        for (x, y, tile) in tiles.enumerate()
+        {
+            if !tile.is_shown()
+            {
+                continue;
+            }
+
+            let mut num_mines = 0;
+            let mut undecided = Vec::new();
+            for (neighbor_x, neighbor_y) in tiles.neighbors_of(&x, &y)
+            {
+                let neighbor = &tiles[(neighbor_x, neighbor_y)];
+                let neighbor_index = index(neighbor_x, neighbor_y);
+
+                if neighbor.has_flag() || mines.contains(neighbor_index)
+                {
+                    num_mines += 1;
+                } else if is_unknown(neighbor) && !safe.contains(neighbor_index) {
+                    undecided.push(neighbor_index);
+                }
+            }
+
+            if undecided.is_empty()
+            {
+                continue;
+            }
+
+            if num_mines >= tile.num_bombs_around()
+            {
+                for neighbor_index in undecided
+                {
+                    changed |= safe.insert(neighbor_index);
+                }
+            } else if tile.num_bombs_around() - num_mines == undecided.len() {
+                for neighbor_index in undecided
+                {
+                    changed |= mines.insert(neighbor_index);
+                }
+            }
+        }
+    }
+
+    /* Tiles with no shown number around them only have the bombs that are left over to go by */
+    let num_flags = tiles.iter().filter(|tile| tile.has_flag()).count();
+    let num_open = tiles.enumerate()
+        .filter(|&(x, y, tile)| is_unknown(tile) && !mines.contains(index(x, y)) && !safe.contains(index(x, y)))
+        .count();
+    let num_mines = tiles.enumerate().filter(|&(x, y, _)| mines.contains(index(x, y))).count();
+    let num_left = num_bombs.saturating_sub(num_flags + num_mines);
+    let density = if num_open == 0 { 0.0 } else { (num_left as f64 / num_open as f64).min(1.0) };
+
+    let mut probabilities = tiles.map(|tile| if is_unknown(tile) { Some(density) } else { None });
+
+    /* Each shown number spreads what it's missing evenly over its undecided neighbors, the most alarming estimate wins */
+    let mut constrained = BitSet::new(tiles.len());
+    for (x, y, tile) in tiles.enumerate()
+    {
+        if !tile.is_shown() || tile.num_bombs_around() == 0
+        {
+            continue;
+        }
+
+        let neighbors: Vec<(Horizontal, Vertical)> = tiles.neighbors_of(&x, &y).collect();
+        let num_mines = neighbors.iter()
+            .filter(|&&(neighbor_x, neighbor_y)| tiles[(neighbor_x, neighbor_y)].has_flag() || mines.contains(index(neighbor_x, neighbor_y)))
+            .count();
+        let undecided: Vec<(Horizontal, Vertical)> = neighbors.into_iter()
+            .filter(|&(neighbor_x, neighbor_y)| {
+                let neighbor_index = index(neighbor_x, neighbor_y);
+                is_unknown(&tiles[(neighbor_x, neighbor_y)]) && !mines.contains(neighbor_index) && !safe.contains(neighbor_index)
+            })
+            .collect();
+
+        let probability = tile.num_bombs_around().saturating_sub(num_mines) as f64 / undecided.len().max(1) as f64;
+        for (neighbor_x, neighbor_y) in undecided
+        {
+            let estimate = if constrained.insert(index(neighbor_x, neighbor_y))
+            {
+                probability
+            } else {
+                probabilities[(neighbor_x, neighbor_y)].unwrap_or(0.0).max(probability)
+            };
+            probabilities[(neighbor_x, neighbor_y)] = Some(estimate);
+        }
+    }
+
+    for (x, y) in tiles.positions()
+    {
+        if mines.contains(index(x, y))
+        {
+            probabilities[(x, y)] = Some(1.0);
+        } else if safe.contains(index(x, y)) {
+            probabilities[(x, y)] = Some(0.0);
+        }
+    }
+
+    Analysis {
+        mines: tiles.positions().filter(|&(x, y)| mines.contains(index(x, y))).collect(),
+        safe: tiles.positions().filter(|&(x, y)| safe.contains(index(x, y))).collect(),
+        probabilities,
+    }
+}
@@ -0,0 +1,401 @@
+use ::errors::Result;
+
+use ::common::{Horizontal, Vertical};
+use ::engine::minesweeper::{Action, FirstClickPolicy, Minesweeper, State};
+
+
+/// Not shown yet, or only revealed as a hint
+pub const CHANNEL_HIDDEN: usize = 0;
+pub const CHANNEL_FLAG: usize = 1;
+/// Shown and clicked, so certainly not a bomb
+pub const CHANNEL_CLICKED: usize = 2;
+/// One channel for each number from 0 to 8 on every shown tile, hints included
+pub const CHANNEL_NUMBER: usize = 3;
+pub const NUM_CHANNELS: usize = CHANNEL_NUMBER + 9;
+
+/// The actions an agent picks from, in the order `Environment::action_from_index` numbers them
+pub const NUM_ACTION_KINDS: usize = 4;
+
+/// Reward shaping, every reward is added up over the step
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Rewards
+{
+    pub win: f64,
+    pub loss: f64,
+    /// For every tile a step clicks, not counting the ones winning clicks
+    pub per_tile: f64,
+    /// For a flag on a bomb, which tells the agent more than a player would know
+    pub correct_flag: f64,
+    pub wrong_flag: f64,
+    /// For an action the engine rejects, the game carries on as if it never happened
+    pub invalid: f64,
+    /// For every step, to discourage dawdling
+    pub per_step: f64,
+}
+
+impl Default for Rewards
+{
+    fn default() -> Rewards
+    {
+        Rewards {
+            win: 1.0,
+            loss: -1.0,
+            per_tile: 0.01,
+            correct_flag: 0.0,
+            wrong_flag: 0.0,
+            invalid: -0.1,
+            per_step: 0.0,
+        }
+    }
+}
+
+/// What the agent sees, as a flat tensor of `channels` x `height` x `width`, channel by channel then row by row
+#[derive(Serialize, Clone, Debug)]
+pub struct Observation
+{
+    pub channels: usize,
+    pub height: usize,
+    pub width: usize,
+    pub data: Vec<f32>,
+}
+
+impl Observation
+{
+    pub fn get(&self, channel: usize, x: usize, y: usize) -> f32
+    {
+        self.data[(channel * self.height + y) * self.width + x]
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StepInfo
+{
+    /// Why the engine rejected the action, if it did
+    pub error: Option<String>,
+    pub num_clicked: usize,
+    pub num_flags: usize,
+    pub won: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Step
+{
+    pub observation: Observation,
+    pub reward: f64,
+    /// Won or lost, `reset` before stepping again
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+/// Wraps a game in the reset and step loop reinforcement learning code expects
+#[derive(Debug)]
+pub struct Environment
+{
+    game: Minesweeper,
+    rewards: Rewards,
+}
+
+impl Environment
+{
+    pub fn new(width: Horizontal, height: Vertical, num_bombs: usize) -> Result<Environment>
+    {
+        Ok(Environment {
+            game: Minesweeper::new(width, height, num_bombs)?,
+            rewards: Rewards::default(),
+        })
+    }
+
+    pub fn set_rewards(&mut self, rewards: Rewards)
+    {
+        self.rewards = rewards;
+    }
+
+    pub fn get_rewards(&self) -> Rewards
+    {
+        self.rewards
+    }
+
+    /// Takes effect on the next `reset`
+    pub fn set_first_click_policy(&mut self, policy: FirstClickPolicy)
+    {
+        self.game.set_first_click_policy(policy);
+    }
+
+    /// Deals a new board, the same seed always deals the same board for the same first move
+    pub fn reset(&mut self, seed: Option<u64>) -> Result<Observation>
+    {
+        let (width, height, num_bombs) = (self.game.get_width(), self.game.get_height(), self.game.get_num_bombs());
+        self.game.resize(width, height, num_bombs)?;
+        self.game.set_seed(seed);
+
+        Ok(self.observe())
+    }
+
+    pub fn step(&mut self, action: Action) -> Step
+    {
+        let num_clicked = self.count_clicked();
+        /* Unflagging takes back what the flag earned, so flagging over and over again earns nothing */
+        let flagged = match action
+        {
+            Action::Flag { x_position, y_position } => Some((Horizontal(x_position), Vertical(y_position), 1.0)),
+            Action::Unflag { x_position, y_position } => Some((Horizontal(x_position), Vertical(y_position), -1.0)),
+            _ => None,
+        };
+
+        let mut reward = self.rewards.per_step;
+        let error = match self.game.handle_action(action)
+        {
+            Ok(_) => None,
+            Err(error) => Some(error.to_string()),
+        };
+
+        if error.is_some()
+        {
+            reward += self.rewards.invalid;
+        } else if let Some((x, y, sign)) = flagged {
+            reward += sign * if self.game.is_bomb_at(&x, &y) { self.rewards.correct_flag } else { self.rewards.wrong_flag };
+        }
+
+        match *self.game.get_state()
+        {
+            State::Won => reward += self.rewards.win,
+            State::Loss => reward += self.rewards.loss,
+            _ => reward += self.rewards.per_tile * (self.count_clicked() - num_clicked) as f64,
+        }
+
+        self.finish_step(reward, error)
+    }
+
+    /// Same as `step` with the action numbered by `action_from_index`, anything out of range counts as invalid
+    pub fn step_index(&mut self, index: usize) -> Step
+    {
+        match self.action_from_index(index)
+        {
+            Some(action) => self.step(action),
+            None =>
+            {
+                let error = format!("{} is past the last of {} actions", index, self.num_actions());
+                let reward = self.rewards.per_step + self.rewards.invalid;
+                self.finish_step(reward, Some(error))
+            },
+        }
+    }
+
+    pub fn observe(&self) -> Observation
+    {
+        let tiles = self.game.get_tiles();
+        let (width, height) = (self.game.get_width().0, self.game.get_height().0);
+        let plane = width * height;
+        let mut data = vec![0.0; NUM_CHANNELS * plane];
+
+        for (x, y, tile) in tiles.enumerate()
+        {
+            let position = y.0 * width + x.0;
+            if tile.has_flag()
+            {
+                data[CHANNEL_FLAG * plane + position] = 1.0;
+            }
+            if tile.was_clicked()
+            {
+                data[CHANNEL_CLICKED * plane + position] = 1.0;
+            } else {
+                data[CHANNEL_HIDDEN * plane + position] = 1.0;
+            }
+            if tile.is_shown()
+            {
+                data[(CHANNEL_NUMBER + tile.num_bombs_around()) * plane + position] = 1.0;
+            }
+        }
+
+        Observation { channels: NUM_CHANNELS, height, width, data }
+    }
+
+    /// Expose, flag, unflag and chord on every tile, `NUM_ACTION_KINDS` times the number of tiles
+    pub fn num_actions(&self) -> usize
+    {
+        NUM_ACTION_KINDS * self.game.get_width().0 * self.game.get_height().0
+    }
+
+    /// Actions numbered kind by kind, then row by row within each kind
+    pub fn action_from_index(&self, index: usize) -> Option<Action>
+    {
+        let width = self.game.get_width().0;
+        let plane = width * self.game.get_height().0;
+        let (x_position, y_position) = ((index % plane) % width, (index % plane) / width);
+
+        match index / plane
+        {
+            0 => Some(Action::Expose { x_position, y_position }),
+            1 => Some(Action::Flag { x_position, y_position }),
+            2 => Some(Action::Unflag { x_position, y_position }),
+            3 => Some(Action::Chord { x_position, y_position }),
+            _ => None,
+        }
+    }
+
+    pub fn get_game(&self) -> &Minesweeper
+    {
+        &self.game
+    }
+
+    fn finish_step(&self, reward: f64, error: Option<String>) -> Step
+    {
+        let state = self.game.get_state();
+
+        Step {
+            observation: self.observe(),
+            reward,
+            done: *state == State::Won || *state == State::Loss,
+            info: StepInfo {
+                error,
+                num_clicked: self.count_clicked(),
+                num_flags: self.game.get_summary().num_flags,
+                won: *state == State::Won,
+            },
+        }
+    }
+
+    fn count_clicked(&self) -> usize
+    {
+        self.game.get_tiles().iter().filter(|tile| tile.was_clicked()).count()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn environment(seed: u64) -> Environment
+    {
+        let mut environment = Environment::new(Horizontal(9), Vertical(9), 10).unwrap();
+        environment.set_first_click_policy(FirstClickPolicy::SafeOpening);
+        environment.reset(Some(seed)).unwrap();
+
+        environment
+    }
+
+    fn bombs(environment: &Environment) -> Vec<(Horizontal, Vertical)>
+    {
+        let game = environment.get_game();
+
+        game.get_tiles().positions().filter(|(x, y)| game.is_bomb_at(x, y)).collect()
+    }
+
+    #[test]
+    fn resetting_with_the_same_seed_deals_the_same_board()
+    {
+        let mut environment = environment(7);
+        let first = environment.step(Action::Expose { x_position: 4, y_position: 4 });
+        let first_bombs = bombs(&environment);
+        assert_eq!(first_bombs.len(), 10);
+
+        environment.reset(Some(8)).unwrap();
+        environment.step(Action::Expose { x_position: 4, y_position: 4 });
+        assert_ne!(bombs(&environment), first_bombs);
+
+        let observation = environment.reset(Some(7)).unwrap();
+        assert_eq!(environment.get_game().get_summary().num_shown, 0);
+        assert!((0..81).all(|position| observation.data[CHANNEL_HIDDEN * 81 + position] == 1.0));
+
+        let again = environment.step(Action::Expose { x_position: 4, y_position: 4 });
+        assert_eq!(bombs(&environment), first_bombs);
+        assert_eq!(again.observation.data, first.observation.data);
+        assert_eq!(again.reward, first.reward);
+    }
+
+    #[test]
+    fn every_tile_is_observed_in_the_right_channels()
+    {
+        let mut environment = environment(7);
+        environment.step(Action::Expose { x_position: 4, y_position: 4 });
+        let game = environment.get_game();
+        let (x_position, y_position) = game.get_tiles().enumerate()
+            .find(|&(_, _, tile)| !tile.is_shown())
+            .map(|(x, y, _)| (x.0, y.0))
+            .unwrap();
+        environment.step(Action::Flag { x_position, y_position });
+
+        let observation = environment.observe();
+        assert_eq!((observation.channels, observation.height, observation.width), (NUM_CHANNELS, 9, 9));
+        assert_eq!(observation.data.len(), NUM_CHANNELS * 81);
+
+        let mut num_hints = 0;
+        for (x, y, tile) in environment.get_game().get_tiles().enumerate()
+        {
+            let (x, y) = (x.0, y.0);
+            let numbers: Vec<usize> = (0..9).filter(|&number| observation.get(CHANNEL_NUMBER + number, x, y) == 1.0).collect();
+
+            assert_eq!(observation.get(CHANNEL_FLAG, x, y) == 1.0, tile.has_flag(), "flag at {},{}", x, y);
+            assert_eq!(observation.get(CHANNEL_CLICKED, x, y) == 1.0, tile.was_clicked(), "clicked at {},{}", x, y);
+            assert_eq!(observation.get(CHANNEL_HIDDEN, x, y) == 1.0, !tile.was_clicked(), "hidden at {},{}", x, y);
+            if tile.is_shown()
+            {
+                assert_eq!(numbers, vec![tile.num_bombs_around()], "number at {},{}", x, y);
+            } else {
+                assert!(numbers.is_empty(), "number on a hidden tile at {},{}", x, y);
+            }
+
+            /* A hint shows its number but the tile still counts as hidden */
+            if tile.is_shown() && !tile.was_clicked()
+            {
+                num_hints += 1;
+                assert_eq!(observation.get(CHANNEL_HIDDEN, x, y), 1.0);
+                assert!(tile.num_bombs_around() > 0);
+            }
+        }
+        assert!(num_hints > 0);
+        assert_eq!(observation.get(CHANNEL_FLAG, x_position, y_position), 1.0);
+    }
+
+    #[test]
+    fn unflagging_takes_back_what_the_flag_earned()
+    {
+        let mut environment = environment(7);
+        environment.set_rewards(Rewards { correct_flag: 0.5, wrong_flag: -0.3, ..Rewards::default() });
+        environment.step(Action::Expose { x_position: 4, y_position: 4 });
+
+        let bomb = bombs(&environment).into_iter().find(|&(x, y)| !environment.get_game().get_tiles()[(x, y)].is_shown()).unwrap();
+        let safe = environment.get_game().get_tiles().enumerate()
+            .find(|&(x, y, tile)| !environment.get_game().is_bomb_at(&x, &y) && !tile.is_shown())
+            .map(|(x, y, _)| (x, y))
+            .unwrap();
+
+        for &((x, y), earned) in &[(bomb, 0.5), (safe, -0.3)]
+        {
+            let (x_position, y_position) = (x.0, y.0);
+            let flag = environment.step(Action::Flag { x_position, y_position });
+            let unflag = environment.step(Action::Unflag { x_position, y_position });
+
+            assert_eq!(flag.reward, earned);
+            assert_eq!(flag.reward + unflag.reward, 0.0);
+            assert_eq!(unflag.info.num_flags, 0);
+        }
+    }
+
+    #[test]
+    fn an_index_past_the_last_action_is_invalid_and_changes_nothing()
+    {
+        let mut environment = environment(7);
+        environment.set_rewards(Rewards { per_step: -0.01, ..Rewards::default() });
+        environment.step(Action::Expose { x_position: 4, y_position: 4 });
+
+        let num_actions = environment.num_actions();
+        assert_eq!(num_actions, NUM_ACTION_KINDS * 81);
+        assert!(environment.action_from_index(num_actions - 1).is_some());
+
+        let tiles = environment.get_game().get_tiles().clone();
+        let observation = environment.observe();
+        for &index in &[num_actions, num_actions + 1, usize::MAX]
+        {
+            let step = environment.step_index(index);
+
+            assert_eq!(step.reward, -0.01 + Rewards::default().invalid);
+            assert!(step.info.error.is_some());
+            assert!(!step.done);
+            assert_eq!(step.observation.data, observation.data);
+            assert_eq!(environment.get_game().get_tiles(), &tiles);
+            assert_eq!(*environment.get_game().get_state(), State::InProgress);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use ::common::{self, Horizontal, Vertical};
+use ::engine::minesweeper::Minesweeper;
+use super::analysis;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Difficulty
+{
+    /// Clicks anywhere
+    Easy,
+    /// Takes the bombs it can prove, otherwise clicks anywhere it can't prove is safe
+    Medium,
+    /// Takes the bombs it can prove, otherwise the tile most likely to be a bomb
+    Hard,
+}
+
+impl Difficulty
+{
+    pub fn from_name(name: &str) -> Option<Difficulty>
+    {
+        match name.to_lowercase().as_str()
+        {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+}
+
+/// Plays Flags, where finding bombs is the point, so it goes looking for them instead of avoiding them
+#[derive(Debug)]
+pub struct FlagsOpponent
+{
+    difficulty: Difficulty,
+}
+
+impl FlagsOpponent
+{
+    pub fn new(difficulty: Difficulty) -> FlagsOpponent
+    {
+        FlagsOpponent { difficulty }
+    }
+
+    pub fn get_difficulty(&self) -> Difficulty
+    {
+        self.difficulty
+    }
+
+    /// The x, y to expose next, `None` once there's nothing left to click
+    pub fn choose_move(&self, game: &Minesweeper) -> Option<(usize, usize)>
+    {
+        let analysis = analysis::analyze(game.get_tiles(), game.get_num_bombs());
+        let unknown = analysis.unknown();
+        let mut rng = StdRng::seed_from_u64(common::random_seed());
+
+        let choice = match self.difficulty
+        {
+            Difficulty::Easy => unknown.choose(&mut rng).cloned(),
+            Difficulty::Medium =>
+            {
+                let unproven: Vec<(Horizontal, Vertical)> = unknown.iter()
+                    .filter(|position| !analysis.safe.contains(position))
+                    .cloned()
+                    .collect();
+
+                analysis.mines.choose(&mut rng)
+                    .or_else(|| unproven.choose(&mut rng))
+                    .or_else(|| unknown.choose(&mut rng))
+                    .cloned()
+            },
+            Difficulty::Hard =>
+            {
+                let probability = |position: &(Horizontal, Vertical)| analysis.probabilities[*position].unwrap_or(0.0);
+                let best = unknown.iter().map(&probability).fold(0.0, f64::max);
+                let likeliest: Vec<(Horizontal, Vertical)> = unknown.iter()
+                    .filter(|position| probability(position) >= best)
+                    .cloned()
+                    .collect();
+
+                likeliest.choose(&mut rng).cloned()
+            },
+        };
+
+        choice.map(|(x, y)| (x.0, y.0))
+    }
+}
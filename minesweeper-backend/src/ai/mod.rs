@@ -0,0 +1,9 @@
+//! Computer players, they only ever look at what a human player could see
+
+pub mod analysis;
+pub mod solver;
+pub mod flags;
+pub mod strategy;
+pub mod tournament;
+pub mod simulator;
+pub mod environment;
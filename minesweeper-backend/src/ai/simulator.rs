@@ -0,0 +1,203 @@
+use ::errors::Result;
+
+use ::common::{Horizontal, Vertical};
+use ::engine::minesweeper::{Action, FirstClickPolicy, Minesweeper, State};
+use super::analysis::{self, Analysis};
+use super::solver;
+
+
+/// The boards to deal, the same settings always deal the same boards
+#[derive(Clone, Debug)]
+pub struct SimulationSettings
+{
+    pub width: Horizontal,
+    pub height: Vertical,
+    pub num_bombs: usize,
+    pub first_click_policy: FirstClickPolicy,
+    pub boards: usize,
+    /// Board `n` is dealt from `seed + n`
+    pub seed: u64,
+}
+
+/// How hard boards dealt with some settings are, as played by `solver::solve`
+#[derive(Serialize, Debug)]
+pub struct SimulationReport
+{
+    pub width: usize,
+    pub height: usize,
+    pub num_bombs: usize,
+    pub first_click_policy: FirstClickPolicy,
+    pub boards: usize,
+    /// Boards a perfect player can't clear without guessing at least once
+    pub must_guess: usize,
+    pub must_guess_rate: f64,
+    /// Guesses a perfect player makes to clear a board when every guess goes their way
+    pub average_guesses: f64,
+    /// Boards won by guessing the tile least likely to be a bomb whenever stuck
+    pub won: usize,
+    pub win_rate: f64,
+}
+
+struct Playthrough
+{
+    won: bool,
+    guesses: usize,
+}
+
+/// Every board is played twice, once where every guess is lucky to count the guesses it takes, and once for real to see how often it's won
+pub fn simulate(settings: &SimulationSettings) -> Result<SimulationReport>
+{
+    let mut report = SimulationReport {
+        width: settings.width.0,
+        height: settings.height.0,
+        num_bombs: settings.num_bombs,
+        first_click_policy: settings.first_click_policy,
+        boards: settings.boards,
+        must_guess: 0,
+        must_guess_rate: 0.0,
+        average_guesses: 0.0,
+        won: 0,
+        win_rate: 0.0,
+    };
+
+    let mut guesses = 0;
+    for board in 0..settings.boards
+    {
+        let seed = settings.seed.wrapping_add(board as u64);
+
+        let lucky = play_board(settings, seed, true)?;
+        guesses += lucky.guesses;
+        if lucky.guesses > 0
+        {
+            report.must_guess += 1;
+        }
+
+        if play_board(settings, seed, false)?.won
+        {
+            report.won += 1;
+        }
+    }
+
+    if settings.boards > 0
+    {
+        let boards = settings.boards as f64;
+        report.must_guess_rate = report.must_guess as f64 / boards;
+        report.average_guesses = guesses as f64 / boards;
+        report.win_rate = report.won as f64 / boards;
+    }
+
+    info!("{}x{} with {} bombs needs a guess {:.1}% of the time and is won {:.1}% of the time",
+        report.width, report.height, report.num_bombs, report.must_guess_rate * 100.0, report.win_rate * 100.0);
+
+    Ok(report)
+}
+
+fn new_game(settings: &SimulationSettings, seed: u64) -> Result<Minesweeper>
+{
+    let mut game = Minesweeper::new(settings.width, settings.height, settings.num_bombs)?;
+    game.set_seed(Some(seed));
+    game.set_first_click_policy(settings.first_click_policy);
+    Ok(game)
+}
+
+/// The first move goes in the middle, where it's most likely to open an area
+fn first_move(settings: &SimulationSettings, seed: u64, lucky: bool) -> Result<(Horizontal, Vertical)>
+{
+    let middle = (Horizontal(settings.width.0 / 2), Vertical(settings.height.0 / 2));
+    if !lucky || settings.first_click_policy != FirstClickPolicy::Unprotected
+    {
+        return Ok(middle);
+    }
+
+    /* Unprotected bombs don't depend on where the first move is, so a throwaway game shows where they are */
+    let mut probe = new_game(settings, seed)?;
+    probe.handle_action(Action::Expose { x_position: middle.0 .0, y_position: middle.1 .0 })?;
+    let safe = probe.get_tiles().positions().find(|&(x, y)| !probe.is_bomb_at(&x, &y));
+
+    Ok(safe.unwrap_or(middle))
+}
+
+/// Plays proven moves for as long as there are any and guesses when there aren't.
+/// A `lucky` player always guesses a tile that turns out to be safe
+fn play_board(settings: &SimulationSettings, seed: u64, lucky: bool) -> Result<Playthrough>
+{
+    let mut game = new_game(settings, seed)?;
+
+    let (x, y) = first_move(settings, seed, lucky)?;
+    let mut guesses = if settings.first_click_policy == FirstClickPolicy::Unprotected { 1 } else { 0 };
+    game.handle_action(Action::Expose { x_position: x.0, y_position: y.0 })?;
+
+    while *game.get_state() == State::InProgress
+    {
+        /* The single tile rules are much cheaper and usually enough */
+        let mut analysis = analysis::analyze(game.get_tiles(), settings.num_bombs);
+        if analysis.mines.is_empty() && analysis.safe.is_empty()
+        {
+            analysis = solver::solve(game.get_tiles(), settings.num_bombs);
+        }
+
+        if !analysis.mines.is_empty() || !analysis.safe.is_empty()
+        {
+            play_proven(&mut game, &analysis)?;
+            continue;
+        }
+
+        let (x, y) = match choose_guess(&game, &analysis, lucky)
+        {
+            Some(position) => position,
+            None => break,
+        };
+
+        if analysis.probabilities[(x, y)].unwrap_or(0.0) >= 1.0
+        {
+            /* Everything left is a bomb, which isn't a guess */
+            game.handle_action(Action::Flag { x_position: x.0, y_position: y.0 })?;
+        } else {
+            guesses += 1;
+            game.handle_action(Action::Expose { x_position: x.0, y_position: y.0 })?;
+        }
+    }
+
+    Ok(Playthrough { won: *game.get_state() == State::Won, guesses })
+}
+
+fn play_proven(game: &mut Minesweeper, analysis: &Analysis) -> Result<()>
+{
+    let flags = analysis.mines.iter().map(|&(x, y)| Action::Flag { x_position: x.0, y_position: y.0 });
+    let exposes = analysis.safe.iter().map(|&(x, y)| Action::Expose { x_position: x.0, y_position: y.0 });
+
+    for action in flags.chain(exposes)
+    {
+        /* The last flag wins the game and exposing a safe tile can show ones later in the list */
+        if *game.get_state() != State::InProgress
+        {
+            break;
+        }
+        if game.validate_action(&action).is_ok()
+        {
+            game.handle_action(action)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The undecided tile least likely to be a bomb, ties going to the first one
+fn choose_guess(game: &Minesweeper, analysis: &Analysis, lucky: bool) -> Option<(Horizontal, Vertical)>
+{
+    let probability = |position: &(Horizontal, Vertical)| analysis.probabilities[*position].unwrap_or(1.0);
+    let unknown = analysis.unknown();
+
+    let candidates: Vec<(Horizontal, Vertical)> = if lucky && unknown.iter().any(|&(x, y)| !game.is_bomb_at(&x, &y))
+    {
+        unknown.into_iter().filter(|&(x, y)| !game.is_bomb_at(&x, &y)).collect()
+    } else {
+        unknown
+    };
+
+    candidates.into_iter().fold(None, |best, position| match best
+    {
+        Some(best) if probability(&best) <= probability(&position) => Some(best),
+        _ => Some(position),
+    })
+}
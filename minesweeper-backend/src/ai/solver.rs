@@ -0,0 +1,541 @@
+use ::common::{Horizontal, Vertical};
+use ::common::bitset::BitSet;
+use ::common::vec2d::Vec2d;
+use ::engine::packed::PackedTile;
+use super::analysis::{self, Analysis};
+
+
+/// Enumerating a group of tiles gives up after this many steps and the whole field falls back to `analysis::analyze`
+const MAX_STEPS: usize = 200_000;
+
+/// A shown number's undecided neighbors and how many of them are bombs
+struct Constraint
+{
+    variables: Vec<usize>,
+    num_bombs: usize,
+}
+
+/// Every way a group of tiles that share numbers can hold bombs, by how many bombs they hold
+struct Solutions
+{
+    /// `counts[k]` is how many ways there are to put `k` bombs in the group
+    counts: Vec<f64>,
+    /// `bombs[k][variable]` is how many of those ways have a bomb on `variable`
+    bombs: Vec<Vec<f64>>,
+}
+
+fn is_unknown(tile: &PackedTile) -> bool
+{
+    !tile.was_clicked() && !tile.has_flag()
+}
+
+/// What a perfect player could work out: every arrangement of bombs that fits the shown numbers and the bombs left over is counted,
+/// so tiles that are a bomb in all of them or in none are proven, and the rest get their exact odds.
+/// Falls back to `analysis::analyze` when the numbers tie too many tiles together to count
+pub fn solve(tiles: &Vec2d<PackedTile>, num_bombs: usize) -> Analysis
+{
+    let simple = analysis::analyze(tiles, num_bombs);
+    let width = tiles.width().0;
+    let index = |x: Horizontal, y: Vertical| y.0 * width + x.0;
+
+    let mut known_mines = BitSet::new(tiles.len());
+    let mut known_safe = BitSet::new(tiles.len());
+    for &(x, y) in simple.mines.iter()
+    {
+        known_mines.insert(index(x, y));
+    }
+    for &(x, y) in simple.safe.iter()
+    {
+        known_safe.insert(index(x, y));
+    }
+
+    /* Undecided tiles next to a shown number become variables, everything else undecided is only bound by the count */
+    let mut variable_of: Vec<Option<usize>> = vec![None; tiles.len()];
+    let mut positions: Vec<(Horizontal, Vertical)> = Vec::new();
+    let mut constraints: Vec<Constraint> = Vec::new();
+
+    for (x, y, tile) in tiles.enumerate()
+    {
+        if !tile.is_shown()
+        {
+            continue;
+        }
+
+        let mut num_known = 0;
+        let mut variables = Vec::new();
+        for (neighbor_x, neighbor_y) in tiles.neighbors_of(&x, &y)
+        {
+            let neighbor_index = index(neighbor_x, neighbor_y);
+            if tiles[(neighbor_x, neighbor_y)].has_flag() || known_mines.contains(neighbor_index)
+            {
+                num_known += 1;
+            } else if is_unknown(&tiles[(neighbor_x, neighbor_y)]) && !known_safe.contains(neighbor_index) {
+                let variable = *variable_of[neighbor_index].get_or_insert_with(|| {
+                    positions.push((neighbor_x, neighbor_y));
+                    positions.len() - 1
+                });
+                variables.push(variable);
+            }
+        }
+
+        if variables.is_empty()
+        {
+            continue;
+        }
+
+        match tile.num_bombs_around().checked_sub(num_known)
+        {
+            Some(num_bombs) => constraints.push(Constraint { variables, num_bombs }),
+            /* Only a wrong flag gets here and then nothing can be trusted */
+            None => return simple,
+        }
+    }
+
+    let num_flags = tiles.iter().filter(|tile| tile.has_flag()).count();
+    let num_left = match num_bombs.checked_sub(num_flags + simple.mines.len())
+    {
+        Some(num_left) => num_left,
+        None => return simple,
+    };
+    let unconstrained: Vec<(Horizontal, Vertical)> = tiles.enumerate()
+        .filter(|&(x, y, tile)| {
+            let tile_index = index(x, y);
+            is_unknown(tile) && !known_mines.contains(tile_index) && !known_safe.contains(tile_index) && variable_of[tile_index].is_none()
+        })
+        .map(|(x, y, _)| (x, y))
+        .collect();
+
+    let groups = group_variables(positions.len(), &constraints);
+    let mut solutions = Vec::with_capacity(groups.len());
+    for group in groups.iter()
+    {
+        match enumerate(group, &constraints)
+        {
+            Some(group_solutions) => solutions.push(group_solutions),
+            None =>
+            {
+                debug!("Too many arrangements among {} tiles, falling back to single tile rules", group.len());
+                return simple;
+            },
+        }
+    }
+
+    /* How likely each total of bombs in the groups is, from the ways to spread the rest over the unconstrained tiles */
+    let ln_factorials = ln_factorials(tiles.len());
+    let ln_choose = |n: usize, k: usize| ln_factorials[n] - ln_factorials[k] - ln_factorials[n - k];
+    let max_in_groups: usize = solutions.iter().map(|group| group.counts.len() - 1).sum();
+    let ln_weights: Vec<Option<f64>> = (0..max_in_groups + 1)
+        .map(|in_groups| match num_left.checked_sub(in_groups)
+        {
+            Some(outside) if outside <= unconstrained.len() => Some(ln_choose(unconstrained.len(), outside)),
+            _ => None,
+        })
+        .collect();
+    let max_ln_weight = match ln_weights.iter().filter_map(|weight| *weight).fold(None, |max: Option<f64>, weight| Some(max.map_or(weight, |max| max.max(weight))))
+    {
+        Some(max_ln_weight) => max_ln_weight,
+        /* More bombs are left than there's room for, or fewer than the numbers need */
+        None => return simple,
+    };
+    let weights: Vec<f64> = ln_weights.iter().map(|weight| weight.map_or(0.0, |weight| (weight - max_ln_weight).exp())).collect();
+
+    let mut mines = simple.mines.clone();
+    let mut safe = simple.safe.clone();
+    let mut probabilities = simple.probabilities.map(|probability| probability.map(|_| 0.0));
+    for &(x, y) in simple.mines.iter()
+    {
+        probabilities[(x, y)] = Some(1.0);
+    }
+
+    /* Each group's odds depend on everyone else's bombs only through how many of them there are */
+    let mut total = 0.0;
+    let mut group_weights = Vec::with_capacity(solutions.len());
+    for (group_index, group) in solutions.iter().enumerate()
+    {
+        let others = convolve(solutions.iter().enumerate().filter(|&(other, _)| other != group_index).map(|(_, other)| &other.counts));
+        let weight_by_count: Vec<f64> = (0..group.counts.len())
+            .map(|in_group| others.iter().enumerate().map(|(in_others, ways)| ways * weights[in_group + in_others]).sum())
+            .collect();
+
+        if group_index == 0
+        {
+            total = group.counts.iter().zip(weight_by_count.iter()).map(|(ways, weight)| ways * weight).sum();
+        }
+        group_weights.push(weight_by_count);
+    }
+
+    if solutions.is_empty()
+    {
+        total = weights[0];
+    }
+    if total <= 0.0
+    {
+        return simple;
+    }
+
+    for ((group, group_solutions), weight_by_count) in groups.iter().zip(solutions.iter()).zip(group_weights.iter())
+    {
+        for (local, &variable) in group.iter().enumerate()
+        {
+            let mut bomb_weight = 0.0;
+            let mut is_ever_bomb = false;
+            let mut is_ever_safe = false;
+
+            for (in_group, weight) in weight_by_count.iter().enumerate()
+            {
+                let ways = group_solutions.counts[in_group];
+                if ways == 0.0 || *weight <= 0.0
+                {
+                    continue;
+                }
+
+                let with_bomb = group_solutions.bombs[in_group][local];
+                bomb_weight += with_bomb * weight;
+                is_ever_bomb = is_ever_bomb || with_bomb > 0.0;
+                is_ever_safe = is_ever_safe || with_bomb < ways;
+            }
+
+            let position = positions[variable];
+            if !is_ever_safe
+            {
+                mines.push(position);
+                probabilities[position] = Some(1.0);
+            } else if !is_ever_bomb {
+                safe.push(position);
+                probabilities[position] = Some(0.0);
+            } else {
+                probabilities[position] = Some((bomb_weight / total).min(1.0));
+            }
+        }
+    }
+
+    /* The unconstrained tiles share whatever bombs the groups leave over evenly */
+    if !unconstrained.is_empty()
+    {
+        let in_groups = convolve(solutions.iter().map(|group| &group.counts));
+        let mut expected_outside = 0.0;
+        let mut is_ever_bomb = false;
+        let mut is_ever_safe = false;
+        for (count, ways) in in_groups.iter().enumerate()
+        {
+            if *ways == 0.0 || weights[count] <= 0.0
+            {
+                continue;
+            }
+
+            let outside = num_left - count;
+            expected_outside += ways * weights[count] * outside as f64;
+            is_ever_bomb = is_ever_bomb || outside > 0;
+            is_ever_safe = is_ever_safe || outside < unconstrained.len();
+        }
+
+        let probability = (expected_outside / total / unconstrained.len() as f64).min(1.0);
+        for &position in unconstrained.iter()
+        {
+            if !is_ever_safe
+            {
+                mines.push(position);
+                probabilities[position] = Some(1.0);
+            } else if !is_ever_bomb {
+                safe.push(position);
+                probabilities[position] = Some(0.0);
+            } else {
+                probabilities[position] = Some(probability);
+            }
+        }
+    }
+
+    Analysis { mines, safe, probabilities }
+}
+
+/// Splits the variables into groups that share no number, as lists of variables
+fn group_variables(num_variables: usize, constraints: &[Constraint]) -> Vec<Vec<usize>>
+{
+    let mut parents: Vec<usize> = (0..num_variables).collect();
+    fn root(parents: &mut [usize], mut variable: usize) -> usize
+    {
+        while parents[variable] != variable
+        {
+            parents[variable] = parents[parents[variable]];
+            variable = parents[variable];
+        }
+        variable
+    }
+
+    for constraint in constraints.iter()
+    {
+        let first = root(&mut parents, constraint.variables[0]);
+        for &variable in constraint.variables[1..].iter()
+        {
+            let other = root(&mut parents, variable);
+            parents[other] = first;
+        }
+    }
+
+    let mut group_of_root: Vec<Option<usize>> = vec![None; num_variables];
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for variable in 0..num_variables
+    {
+        let variable_root = root(&mut parents, variable);
+        let group = *group_of_root[variable_root].get_or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(variable);
+    }
+
+    groups
+}
+
+/// Backtracks through every arrangement of the group's variables, `None` if that takes more than `MAX_STEPS`
+fn enumerate(group: &[usize], constraints: &[Constraint]) -> Option<Solutions>
+{
+    /* Constraints and variables renumbered to only the ones in this group */
+    let mut local_of = ::std::collections::HashMap::new();
+    for (local, &variable) in group.iter().enumerate()
+    {
+        local_of.insert(variable, local);
+    }
+
+    let mut needed = Vec::new();
+    let mut unassigned = Vec::new();
+    let mut constraints_of: Vec<Vec<usize>> = vec![Vec::new(); group.len()];
+    for constraint in constraints.iter().filter(|constraint| local_of.contains_key(&constraint.variables[0]))
+    {
+        for variable in constraint.variables.iter()
+        {
+            constraints_of[local_of[variable]].push(needed.len());
+        }
+        needed.push(constraint.num_bombs);
+        unassigned.push(constraint.variables.len());
+    }
+
+    let mut search = Search {
+        constraints_of,
+        needed,
+        placed: vec![0; unassigned.len()],
+        unassigned,
+        assignment: vec![false; group.len()],
+        solutions: Solutions { counts: vec![0.0; group.len() + 1], bombs: vec![vec![0.0; group.len()]; group.len() + 1] },
+        steps: 0,
+    };
+
+    if search.run(0, 0)
+    {
+        Some(search.solutions)
+    } else {
+        None
+    }
+}
+
+struct Search
+{
+    constraints_of: Vec<Vec<usize>>,
+    needed: Vec<usize>,
+    placed: Vec<usize>,
+    unassigned: Vec<usize>,
+    assignment: Vec<bool>,
+    solutions: Solutions,
+    steps: usize,
+}
+
+impl Search
+{
+    /// Returns false once it's taken too many steps
+    fn run(&mut self, variable: usize, num_bombs: usize) -> bool
+    {
+        self.steps += 1;
+        if self.steps > MAX_STEPS
+        {
+            return false;
+        }
+
+        if variable == self.assignment.len()
+        {
+            self.solutions.counts[num_bombs] += 1.0;
+            for (local, &is_bomb) in self.assignment.iter().enumerate()
+            {
+                if is_bomb
+                {
+                    self.solutions.bombs[num_bombs][local] += 1.0;
+                }
+            }
+            return true;
+        }
+
+        for &is_bomb in [false, true].iter()
+        {
+            let finished = !self.assign(variable, is_bomb) || {
+                self.assignment[variable] = is_bomb;
+                self.run(variable + 1, num_bombs + is_bomb as usize)
+            };
+            self.unassign(variable, is_bomb);
+
+            if !finished
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Returns whether every number around the variable can still be satisfied
+    fn assign(&mut self, variable: usize, is_bomb: bool) -> bool
+    {
+        let mut is_possible = true;
+        for &constraint in self.constraints_of[variable].iter()
+        {
+            self.unassigned[constraint] -= 1;
+            if is_bomb
+            {
+                self.placed[constraint] += 1;
+            }
+
+            let placed = self.placed[constraint];
+            is_possible = is_possible && placed <= self.needed[constraint] && placed + self.unassigned[constraint] >= self.needed[constraint];
+        }
+        is_possible
+    }
+
+    fn unassign(&mut self, variable: usize, is_bomb: bool)
+    {
+        for &constraint in self.constraints_of[variable].iter()
+        {
+            self.unassigned[constraint] += 1;
+            if is_bomb
+            {
+                self.placed[constraint] -= 1;
+            }
+        }
+    }
+}
+
+/// Ways to get each total when picking one count from every group
+fn convolve<'a, I: Iterator<Item = &'a Vec<f64>>>(groups: I) -> Vec<f64>
+{
+    let mut totals = vec![1.0];
+    for counts in groups
+    {
+        let mut next = vec![0.0; totals.len() + counts.len() - 1];
+        for (total, ways) in totals.iter().enumerate()
+        {
+            for (count, group_ways) in counts.iter().enumerate()
+            {
+                next[total + count] += ways * group_ways;
+            }
+        }
+        totals = next;
+    }
+    totals
+}
+
+fn ln_factorials(max: usize) -> Vec<f64>
+{
+    let mut ln_factorials = Vec::with_capacity(max + 1);
+    let mut sum = 0.0;
+    ln_factorials.push(sum);
+    for n in 1..max + 1
+    {
+        sum += (n as f64).ln();
+        ln_factorials.push(sum);
+    }
+    ln_factorials
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// One string per row: a digit is a clicked number, `F` a flag and `.` a tile nobody has touched
+    fn field(rows: &[&str]) -> Vec2d<PackedTile>
+    {
+        let rows = rows.iter()
+            .map(|row| row.chars().map(|c| {
+                let mut tile = PackedTile::default();
+                match c
+                {
+                    'F' => tile.set_has_flag(true),
+                    '.' => {},
+                    _ =>
+                    {
+                        tile.set_num_bombs_around(c.to_digit(10).unwrap() as usize);
+                        tile.set_is_shown(true);
+                        tile.set_was_clicked(true);
+                    },
+                }
+                tile
+            }).collect())
+            .collect();
+
+        Vec2d::from_rows(rows).unwrap()
+    }
+
+    fn sorted(positions: &[(Horizontal, Vertical)]) -> Vec<(usize, usize)>
+    {
+        let mut positions: Vec<(usize, usize)> = positions.iter().map(|&(x, y)| (x.0, y.0)).collect();
+        positions.sort_by_key(|&(x, y)| (y, x));
+        positions
+    }
+
+    fn probability(analysis: &Analysis, x: usize, y: usize) -> f64
+    {
+        analysis.probabilities[(Horizontal(x), Vertical(y))].unwrap()
+    }
+
+    #[test]
+    fn one_two_one_proves_both_ends_are_mines()
+    {
+        let analysis = solve(&field(&["121", "..."]), 2);
+
+        assert_eq!(sorted(&analysis.mines), vec![(0, 1), (2, 1)]);
+        assert_eq!(sorted(&analysis.safe), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn a_flag_counts_towards_the_number()
+    {
+        /* With the flag the 2 is only missing one bomb and the 1 already needs it */
+        let analysis = solve(&field(&["F2.", "..1"]), 2);
+
+        assert!(analysis.mines.is_empty());
+        assert_eq!(sorted(&analysis.safe), vec![(0, 1)]);
+        assert_eq!(probability(&analysis, 2, 0), 0.5);
+        assert_eq!(probability(&analysis, 1, 1), 0.5);
+    }
+
+    #[test]
+    fn a_forced_guess_is_fifty_fifty()
+    {
+        let analysis = solve(&field(&["11", ".."]), 1);
+
+        assert!(analysis.mines.is_empty());
+        assert!(analysis.safe.is_empty());
+        assert_eq!(probability(&analysis, 0, 1), 0.5);
+        assert_eq!(probability(&analysis, 1, 1), 0.5);
+    }
+
+    #[test]
+    fn tiles_away_from_the_numbers_share_the_bombs_left_over()
+    {
+        /* One bomb among the corner's 3 neighbors and the other one among the 5 tiles further away: 3 * 5 ways */
+        let analysis = solve(&field(&["1..", "...", "..."]), 2);
+
+        assert!(analysis.mines.is_empty());
+        assert!(analysis.safe.is_empty());
+        assert!((probability(&analysis, 1, 1) - 1.0 / 3.0).abs() < 1e-9);
+        assert!((probability(&analysis, 2, 2) - 1.0 / 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn the_bomb_count_can_prove_tiles_safe()
+    {
+        /* The corner's bomb is the only one, so nothing further away can be a bomb */
+        let analysis = solve(&field(&["1..", "...", "..."]), 1);
+
+        assert!(analysis.mines.is_empty());
+        assert_eq!(sorted(&analysis.safe), vec![(2, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        assert!((probability(&analysis, 1, 0) - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,163 @@
+use ::common::{Horizontal, Vertical};
+use ::common::vec2d::Vec2d;
+use ::engine::minesweeper::{Action, FieldSummary};
+use ::engine::packed::PackedTile;
+use super::analysis;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+
+/// A bot that plays a single player game, one action at a time
+pub trait Strategy
+{
+    /// Tells strategies apart in reports, so it should stay the same between runs
+    fn name(&self) -> &str;
+
+    /// Called before every game with that game's seed, so strategies that guess can guess the same way every run
+    fn new_game(&mut self, _seed: u64) {}
+
+    /// The next action given only what a player could see, `None` when there's no tile left to act on
+    fn next_action(&mut self, tiles: &Vec2d<PackedTile>, summary: &FieldSummary) -> Option<Action>;
+}
+
+/// Every strategy that ships with the crate, by name
+pub fn builtin(name: &str) -> Option<Box<dyn Strategy>>
+{
+    match name
+    {
+        "random" => Some(Box::new(RandomClicker::new())),
+        "single-tile" => Some(Box::new(SingleTileSolver::new())),
+        _ => None,
+    }
+}
+
+pub const BUILTIN_NAMES: [&str; 2] = ["random", "single-tile"];
+
+/// Clicks anywhere it hasn't yet, flagging only once every tile that's left has to be a bomb.
+/// The baseline anything smarter should beat
+#[derive(Debug)]
+pub struct RandomClicker
+{
+    rng: StdRng,
+}
+
+impl RandomClicker
+{
+    pub fn new() -> RandomClicker
+    {
+        RandomClicker { rng: StdRng::seed_from_u64(0) }
+    }
+}
+
+impl Default for RandomClicker
+{
+    fn default() -> RandomClicker
+    {
+        RandomClicker::new()
+    }
+}
+
+impl Strategy for RandomClicker
+{
+    fn name(&self) -> &str
+    {
+        "random"
+    }
+
+    fn new_game(&mut self, seed: u64)
+    {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn next_action(&mut self, tiles: &Vec2d<PackedTile>, summary: &FieldSummary) -> Option<Action>
+    {
+        let unknown: Vec<(Horizontal, Vertical)> = tiles.enumerate()
+            .filter(|&(_, _, tile)| !tile.was_clicked() && !tile.has_flag())
+            .map(|(x, y, _)| (x, y))
+            .collect();
+
+        let (x, y) = *unknown.choose(&mut self.rng)?;
+        if unknown.len() + summary.num_flags == summary.num_bombs
+        {
+            Some(Action::Flag { x_position: x.0, y_position: y.0 })
+        } else {
+            Some(Action::Expose { x_position: x.0, y_position: y.0 })
+        }
+    }
+}
+
+/// Flags and clicks whatever `analysis` can prove, otherwise clicks the tile least likely to be a bomb
+#[derive(Default, Debug)]
+pub struct SingleTileSolver;
+
+impl SingleTileSolver
+{
+    pub fn new() -> SingleTileSolver
+    {
+        SingleTileSolver
+    }
+}
+
+impl Strategy for SingleTileSolver
+{
+    fn name(&self) -> &str
+    {
+        "single-tile"
+    }
+
+    fn next_action(&mut self, tiles: &Vec2d<PackedTile>, summary: &FieldSummary) -> Option<Action>
+    {
+        let analysis = analysis::analyze(tiles, summary.num_bombs);
+
+        if let Some(&(x, y)) = analysis.mines.first()
+        {
+            return Some(Action::Flag { x_position: x.0, y_position: y.0 });
+        }
+
+        /* Ties go to the first tile, which keeps the strategy deterministic */
+        let probability = |position: &(Horizontal, Vertical)| analysis.probabilities[*position].unwrap_or(1.0);
+        let (x, y) = analysis.safe.first().cloned()
+            .or_else(|| analysis.unknown().into_iter().fold(None, |best, position| match best
+            {
+                Some(best) if probability(&best) <= probability(&position) => Some(best),
+                _ => Some(position),
+            }))?;
+
+        /* Only bombs left, but too far from any number to be proven */
+        if probability(&(x, y)) >= 1.0
+        {
+            Some(Action::Flag { x_position: x.0, y_position: y.0 })
+        } else {
+            Some(Action::Expose { x_position: x.0, y_position: y.0 })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn no_action_once_every_tile_is_clicked_or_flagged()
+    {
+        let mut clicked = PackedTile::default();
+        clicked.set_is_shown(true);
+        clicked.set_was_clicked(true);
+        clicked.set_num_bombs_around(1);
+        let mut flagged = PackedTile::default();
+        flagged.set_has_flag(true);
+
+        let tiles = Vec2d::from_rows(vec![vec![clicked, flagged]]).unwrap();
+        let summary = FieldSummary { width: 2, height: 1, num_bombs: 1, num_flags: 1, num_shown: 1 };
+
+        for name in BUILTIN_NAMES.iter()
+        {
+            let mut strategy = builtin(name).unwrap();
+            strategy.new_game(0);
+            assert!(strategy.next_action(&tiles, &summary).is_none(), "{} still had a move", name);
+        }
+    }
+}
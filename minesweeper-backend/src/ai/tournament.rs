@@ -0,0 +1,186 @@
+use ::errors::Result;
+
+use ::engine::minesweeper::{Bbbv, FirstClickPolicy, Minesweeper, State};
+use ::engine::preset::Preset;
+use super::strategy::Strategy;
+
+use std::time::{Duration, Instant};
+
+
+/// The same settings always deal the same fields, so runs can be compared
+#[derive(Clone, Debug)]
+pub struct TournamentSettings
+{
+    /// `Preset::Custom` has no size and is skipped
+    pub presets: Vec<Preset>,
+    pub games_per_preset: usize,
+    /// Game `n` of every preset is dealt from `seed + n`, every strategy plays the same fields
+    pub seed: u64,
+    pub first_click_policy: FirstClickPolicy,
+}
+
+impl Default for TournamentSettings
+{
+    fn default() -> TournamentSettings
+    {
+        TournamentSettings {
+            presets: vec![Preset::Beginner, Preset::Intermediate, Preset::Expert],
+            games_per_preset: 1000,
+            seed: 0,
+            first_click_policy: FirstClickPolicy::SafeTile,
+        }
+    }
+}
+
+/// How one strategy did on one preset
+#[derive(Serialize, Debug)]
+pub struct StrategyReport
+{
+    pub strategy: String,
+    pub preset: Preset,
+    pub played: usize,
+    pub won: usize,
+    /// Games lost to an action the engine rejected or to running out of moves, they count as played and not won
+    pub forfeited: usize,
+    /// Over every game, won or not, including the strategy's thinking time
+    pub average_seconds: f64,
+    pub average_bbbv: f64,
+    pub average_bbbv_solved: f64,
+}
+
+impl StrategyReport
+{
+    pub fn win_rate(&self) -> f64
+    {
+        if self.played == 0 { 0.0 } else { self.won as f64 / self.played as f64 }
+    }
+}
+
+struct GameResult
+{
+    won: bool,
+    forfeited: bool,
+    duration: Duration,
+    bbbv: Bbbv,
+}
+
+/// Plays every strategy through every preset, reports come back strategy by strategy in the order given
+pub fn run(strategies: &mut [Box<dyn Strategy>], settings: &TournamentSettings) -> Result<Vec<StrategyReport>>
+{
+    let mut reports = Vec::new();
+
+    for strategy in strategies.iter_mut()
+    {
+        for preset in settings.presets.iter()
+        {
+            if preset.dimensions().is_some()
+            {
+                reports.push(run_preset(strategy.as_mut(), *preset, settings)?);
+            }
+        }
+    }
+
+    Ok(reports)
+}
+
+fn run_preset(strategy: &mut dyn Strategy, preset: Preset, settings: &TournamentSettings) -> Result<StrategyReport>
+{
+    let mut report = StrategyReport {
+        strategy: strategy.name().to_string(),
+        preset,
+        played: 0,
+        won: 0,
+        forfeited: 0,
+        average_seconds: 0.0,
+        average_bbbv: 0.0,
+        average_bbbv_solved: 0.0,
+    };
+
+    let mut seconds = 0.0;
+    let mut bbbv = 0;
+    let mut bbbv_solved = 0;
+
+    for game in 0..settings.games_per_preset
+    {
+        let result = play_game(strategy, preset, settings.seed.wrapping_add(game as u64), settings.first_click_policy)?;
+
+        report.played += 1;
+        if result.won
+        {
+            report.won += 1;
+        }
+        if result.forfeited
+        {
+            report.forfeited += 1;
+        }
+        seconds += result.duration.as_secs() as f64 + f64::from(result.duration.subsec_nanos()) / 1e9;
+        bbbv += result.bbbv.total;
+        bbbv_solved += result.bbbv.solved;
+    }
+
+    if report.played > 0
+    {
+        let played = report.played as f64;
+        report.average_seconds = seconds / played;
+        report.average_bbbv = bbbv as f64 / played;
+        report.average_bbbv_solved = bbbv_solved as f64 / played;
+    }
+
+    info!("{} won {} of {} {:?} games", report.strategy, report.won, report.played, preset);
+
+    Ok(report)
+}
+
+fn play_game(strategy: &mut dyn Strategy, preset: Preset, seed: u64, first_click_policy: FirstClickPolicy) -> Result<GameResult>
+{
+    let (width, height, num_bombs) = preset.dimensions().ok_or("Custom has no size to play")?;
+
+    let mut game = Minesweeper::new(width, height, num_bombs)?;
+    game.set_seed(Some(seed));
+    game.set_first_click_policy(first_click_policy);
+    strategy.new_game(seed);
+
+    /* Every useful action clicks or flags a tile, so anything past a few times the tiles is going around in circles */
+    let max_moves = width.0 * height.0 * 4;
+    let started = Instant::now();
+    let mut forfeited = false;
+
+    for _ in 0..max_moves
+    {
+        if *game.get_state() == State::Won || *game.get_state() == State::Loss
+        {
+            break;
+        }
+
+        let action = match strategy.next_action(game.get_tiles(), &game.get_summary())
+        {
+            Some(action) => action,
+            None =>
+            {
+                debug!("{} forfeits game {} with no move left to make", strategy.name(), seed);
+                forfeited = true;
+                break;
+            },
+        };
+        if let Err(error) = game.handle_action(action)
+        {
+            debug!("{} forfeits game {} because {}", strategy.name(), seed, error);
+            forfeited = true;
+            break;
+        }
+    }
+
+    let won = *game.get_state() == State::Won;
+    if !won && *game.get_state() != State::Loss && !forfeited
+    {
+        debug!("{} forfeits game {} after {} moves", strategy.name(), seed, max_moves);
+        forfeited = true;
+    }
+
+    Ok(GameResult {
+        won,
+        forfeited,
+        duration: started.elapsed(),
+        bbbv: game.get_bbbv().unwrap_or(Bbbv { total: 0, solved: 0 }),
+    })
+}
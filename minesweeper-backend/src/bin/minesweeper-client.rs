@@ -0,0 +1,86 @@
+//! Stand-in client for minesweeper-host, for trying out multiplayer without a real frontend.
+//!
+//! Lines typed on stdin go to the host as they are and everything the host sends is printed,
+//! so it speaks the same JSON lines as minesweeper-headless.
+//!
+//! Usage: minesweeper-client host:port [name], joins straight away when given a name
+
+extern crate minesweeper_backend;
+extern crate serde_json;
+
+use minesweeper_backend::engine::minesweeper::Action;
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process;
+use std::thread;
+
+
+fn main()
+{
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.len() > 2
+    {
+        eprintln!("Usage: minesweeper-client host:port [name]");
+        process::exit(2);
+    }
+
+    let mut stream = match TcpStream::connect(&args[0])
+    {
+        Ok(stream) => stream,
+        Err(error) =>
+        {
+            eprintln!("Unable to connect to {} because {}", args[0], error);
+            process::exit(1);
+        },
+    };
+
+    let reader = match stream.try_clone()
+    {
+        Ok(reader) => reader,
+        Err(error) =>
+        {
+            eprintln!("Unable to read from {} because {}", args[0], error);
+            process::exit(1);
+        },
+    };
+
+    /* The host closing the connection ends the client even while it's waiting on stdin */
+    thread::spawn(move || {
+        let stdout = io::stdout();
+        for line in BufReader::new(reader).lines()
+        {
+            match line
+            {
+                Ok(line) =>
+                {
+                    let mut out = stdout.lock();
+                    let _ = writeln!(out, "{}", line);
+                    let _ = out.flush();
+                },
+                Err(_) => break,
+            }
+        }
+        process::exit(0);
+    });
+
+    if let Some(name) = args.get(1)
+    {
+        let join = serde_json::to_string(&Action::Join { name: name.clone() }).unwrap();
+        if writeln!(stream, "{}", join).is_err()
+        {
+            process::exit(1);
+        }
+    }
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines()
+    {
+        match line
+        {
+            Ok(line) => if writeln!(stream, "{}", line).is_err() { break },
+            Err(_) => break,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Plays the engine over stdin & stdout for bots, without any window.
+//!
+//! Every line on stdin is one `Action` as JSON, the same ones the webview UI sends.
+//! Every line on stdout is one `ToUiCommand` as JSON, the same ones the webview UI receives.
+//!
+//! Usage: minesweeper-headless [width height num_bombs], defaults to the beginner preset
+
+extern crate minesweeper_backend;
+extern crate serde;
+extern crate serde_json;
+
+use minesweeper_backend::common::{Horizontal, Vertical};
+use minesweeper_backend::engine::minesweeper::{Action, Minesweeper};
+use minesweeper_backend::engine::preset::Preset;
+use minesweeper_backend::errors::{Error, ErrorKind};
+use minesweeper_backend::protocol::ToUiCommand;
+
+use serde::Serialize;
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+
+fn parse_dimensions() -> Result<(Horizontal, Vertical, usize), String>
+{
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.len()
+    {
+        0 => Ok(Preset::Beginner.dimensions().unwrap()),
+        3 =>
+        {
+            let numbers: Result<Vec<usize>, _> = args.iter().map(|arg| arg.parse::<usize>()).collect();
+            match numbers
+            {
+                Ok(numbers) => Ok((Horizontal(numbers[0]), Vertical(numbers[1]), numbers[2])),
+                Err(error) => Err(format!("width, height and num_bombs must be numbers: {}", error)),
+            }
+        },
+        _ => Err("Usage: minesweeper-headless [width height num_bombs]".to_string()),
+    }
+}
+
+/// Writes `command` as one line, a closed stdout means whoever was playing has gone away
+fn send<W: Write, S: Serialize>(out: &mut W, command: &S)
+{
+    let result = serde_json::to_writer(&mut *out, command)
+        .map_err(Error::from)
+        .and_then(|_| out.write_all(b"\n").map_err(Error::from))
+        .and_then(|_| out.flush().map_err(Error::from));
+
+    if let Err(error) = result
+    {
+        eprintln!("Unable to write to stdout because {}", error);
+        process::exit(1);
+    }
+}
+
+fn send_field<W: Write>(out: &mut W, game: &Minesweeper)
+{
+    send(out, &ToUiCommand::NewField {tiles: game.get_tiles()});
+    send(out, &ToUiCommand::from_state(game.get_state()));
+}
+
+fn main()
+{
+    let (width, height, num_bombs) = match parse_dimensions()
+    {
+        Ok(dimensions) => dimensions,
+        Err(message) =>
+        {
+            eprintln!("{}", message);
+            process::exit(2);
+        },
+    };
+
+    let mut game = match Minesweeper::new(width, height, num_bombs)
+    {
+        Ok(game) => game,
+        Err(error) =>
+        {
+            eprintln!("Unable to start a game of width:{} height:{} bombs:{} because {}", width.0, height.0, num_bombs, error);
+            process::exit(2);
+        },
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    send_field(&mut out, &game);
+
+    for line in stdin.lock().lines()
+    {
+        let line = match line
+        {
+            Ok(line) => line,
+            Err(error) =>
+            {
+                eprintln!("Unable to read from stdin because {}", error);
+                process::exit(1);
+            },
+        };
+
+        if line.trim().is_empty()
+        {
+            continue;
+        }
+
+        match serde_json::from_str(&line)
+        {
+            Ok(Action::Start{ width, height, num_bombs }) =>
+            {
+                match game.resize(Horizontal(width), Vertical(height), num_bombs)
+                {
+                    Ok(_) => send_field(&mut out, &game),
+                    Err(error) => send(&mut out, &ToUiCommand::from_error(&error)),
+                }
+            },
+            Ok(Action::RequestField) => send_field(&mut out, &game),
+            Ok(Action::Quit) => break,
+            Ok(action @ Action::Flag{ .. }) | Ok(action @ Action::Unflag{ .. }) |
+            Ok(action @ Action::Expose{ .. }) | Ok(action @ Action::Chord{ .. }) =>
+            {
+                if let Err(error) = game.handle_action(action)
+                {
+                    send(&mut out, &ToUiCommand::from_error(&error));
+                }
+
+                send(&mut out, &ToUiCommand::TilesChanged {changes: game.get_changed_tiles()});
+                send(&mut out, &ToUiCommand::Summary {summary: game.get_summary()});
+                send(&mut out, &ToUiCommand::from_state(game.get_state()));
+            },
+            Ok(action) => send(&mut out, &ToUiCommand::from_error(&ErrorKind::Unsupported(format!("{:?}", action)).into())),
+            Err(error) => send(&mut out, &ToUiCommand::from_error(&error.into())),
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! Hosts a multiplayer game on the local network, every player plays the same field at the same time.
+//!
+//! Speaks the same JSON lines as minesweeper-headless over TCP, one connection per player.
+//! A client sends `{"_type":"Join","name":"..."}` first, every move after that is attributed to it.
+//! Every client sees every player's moves as `PlayerTilesChanged` along with the scoreboard,
+//! or everyone's cursors and pending flags in co-op, everyone's progress in a race or whose turn it is in hot-seat.
+//!
+//! Usage: minesweeper-host [--coop | --eliminate | --hotseat | --race [--seed seed]] [port [width height num_bombs]]
+//! Games are competitive by default, hitting a mine freezes a player for a few seconds unless `--eliminate` is given.
+//! With `--coop` everyone solves the field together and a mine loses it for everyone.
+//! With `--hotseat` two to four players take turns and a mine knocks a player out, moves out of turn are refused with `not_your_turn`.
+//! With `--race` every racer gets their own copy of the same field, any racer sends `{"_type":"StartRace"}` to start.
+
+extern crate minesweeper_backend;
+extern crate rand;
+extern crate serde;
+extern crate serde_json;
+
+use minesweeper_backend::common::{Horizontal, Vertical};
+use minesweeper_backend::engine::minesweeper::{Action, Minesweeper};
+use minesweeper_backend::engine::preset::Preset;
+use minesweeper_backend::errors::{Error, ErrorKind};
+use minesweeper_backend::protocol::ToUiCommand;
+use minesweeper_backend::session::{PlayerId, Session};
+use minesweeper_backend::session::competitive::{CompetitiveSession, MinePenalty};
+use minesweeper_backend::session::coop::CoopSession;
+use minesweeper_backend::session::hotseat::HotSeatSession;
+use minesweeper_backend::session::race::RaceSession;
+
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::process;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+
+const DEFAULT_PORT: u16 = 7878;
+const FREEZE_SECONDS: u64 = 5;
+/// How often players are updated between moves in games with a clock
+const TICK: Duration = Duration::from_secs(1);
+/// How long a write may wait on a player that stopped reading before they're dropped, everyone else waits that long too
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+/// No action comes anywhere near this long, a player sending a longer line is dropped before it fills up the host's memory
+const MAX_LINE: u64 = 64 * 1024;
+
+/// Everything the connection threads tell the host, the host thread is the only one touching the session
+enum Event
+{
+    Connected(usize, TcpStream),
+    Line(usize, String),
+    Disconnected(usize),
+}
+
+struct Client
+{
+    stream: TcpStream,
+    player: Option<PlayerId>,
+    /// A broadcast couldn't reach it, it's removed once the host is done with the current event
+    gone: bool,
+}
+
+enum Mode
+{
+    Competitive(MinePenalty),
+    Coop,
+    HotSeat,
+    Race { seed: u64 },
+}
+
+struct Options
+{
+    port: u16,
+    mode: Mode,
+    dimensions: (Horizontal, Vertical, usize),
+}
+
+/// Removes `flag` from `args`, returns whether it was there
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool
+{
+    match args.iter().position(|arg| arg == flag)
+    {
+        Some(index) =>
+        {
+            args.remove(index);
+            true
+        },
+        None => false,
+    }
+}
+
+/// Removes `flag` and the value after it from `args`
+fn take_value(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, String>
+{
+    match args.iter().position(|arg| arg == flag)
+    {
+        Some(index) if index + 1 < args.len() =>
+        {
+            args.remove(index);
+            Ok(Some(args.remove(index)))
+        },
+        Some(_) => Err(format!("{} needs a value", flag)),
+        None => Ok(None),
+    }
+}
+
+fn parse_options() -> Result<Options, String>
+{
+    let usage = "Usage: minesweeper-host [--coop | --eliminate | --hotseat | --race [--seed seed]] [port [width height num_bombs]]";
+
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let seed = match take_value(&mut args, "--seed")?
+    {
+        Some(seed) => Some(seed.parse::<u64>().map_err(|error| format!("seed must be a number: {}\n{}", error, usage))?),
+        None => None,
+    };
+    let flags = (take_flag(&mut args, "--coop"), take_flag(&mut args, "--eliminate"), take_flag(&mut args, "--hotseat"), take_flag(&mut args, "--race"));
+    let mode = match (flags, seed)
+    {
+        ((false, false, false, false), None) => Mode::Competitive(MinePenalty::Freeze { seconds: FREEZE_SECONDS }),
+        ((false, true, false, false), None) => Mode::Competitive(MinePenalty::Eliminate),
+        ((true, false, false, false), None) => Mode::Coop,
+        ((false, false, true, false), None) => Mode::HotSeat,
+        ((false, false, false, true), seed) => Mode::Race { seed: seed.unwrap_or_else(rand::random) },
+        ((_, _, _, false), Some(_)) => return Err(format!("--seed only works with --race\n{}", usage)),
+        _ => return Err(format!("Pick only one of --coop, --eliminate, --hotseat or --race\n{}", usage)),
+    };
+
+    let numbers: Result<Vec<usize>, _> = args.iter().map(|arg| arg.parse::<usize>()).collect();
+    let numbers = numbers.map_err(|error| format!("port, width, height and num_bombs must be numbers: {}\n{}", error, usage))?;
+
+    let port = match numbers.first()
+    {
+        Some(&port) if port <= u16::MAX as usize => port as u16,
+        Some(_) => return Err(format!("port must be below 65536\n{}", usage)),
+        None => DEFAULT_PORT,
+    };
+
+    let dimensions = match numbers.len()
+    {
+        0 | 1 => Preset::Beginner.dimensions().unwrap(),
+        4 => (Horizontal(numbers[1]), Vertical(numbers[2]), numbers[3]),
+        _ => return Err(usage.to_string()),
+    };
+
+    Ok(Options { port, mode, dimensions })
+}
+
+/// Writes `command` as one line, returns false once the client has gone away
+fn send<S: Serialize>(stream: &mut TcpStream, command: &S) -> bool
+{
+    let result = serde_json::to_writer(&mut *stream, command)
+        .map_err(Error::from)
+        .and_then(|_| stream.write_all(b"\n").map_err(Error::from))
+        .and_then(|_| stream.flush().map_err(Error::from));
+
+    result.is_ok()
+}
+
+/// Sends to every client, marking the ones that have gone away for `remove_gone`
+fn broadcast<S: Serialize>(clients: &mut HashMap<usize, Client>, command: &S)
+{
+    for client in clients.values_mut().filter(|client| !client.gone)
+    {
+        if !send(&mut client.stream, command)
+        {
+            client.gone = true;
+        }
+    }
+}
+
+/// Players whose connection failed leave the session, telling everyone else about it can find more that have gone
+fn remove_gone(session: &mut dyn Session, clients: &mut HashMap<usize, Client>)
+{
+    loop
+    {
+        let gone: Vec<usize> = clients.iter().filter(|&(_, client)| client.gone).map(|(&id, _)| id).collect();
+        if gone.is_empty()
+        {
+            return;
+        }
+
+        for id in gone
+        {
+            if let Some(client) = clients.remove(&id)
+            {
+                /* Stops its reading thread too */
+                let _ = client.stream.shutdown(Shutdown::Both);
+                if let Some(player) = client.player
+                {
+                    leave(session, clients, id, player);
+                }
+            }
+        }
+    }
+}
+
+fn send_field(stream: &mut TcpStream, session: &dyn Session, player: Option<PlayerId>)
+{
+    let game = session.get_game(player);
+    send(stream, &ToUiCommand::NewField {tiles: game.get_tiles()});
+    send(stream, &ToUiCommand::from_state(game.get_state()));
+    send(stream, &session.get_players_update());
+}
+
+fn send_error(stream: &mut TcpStream, error: &Error)
+{
+    send(stream, &ToUiCommand::from_error(error));
+}
+
+/// Accepts players forever, each connection gets a thread that forwards its lines to the host
+fn accept_clients(listener: TcpListener, events: Sender<Event>)
+{
+    for (id, stream) in listener.incoming().enumerate()
+    {
+        let stream = match stream
+        {
+            Ok(stream) => stream,
+            Err(error) =>
+            {
+                eprintln!("Unable to accept a player because {}", error);
+                continue;
+            },
+        };
+
+        if let Err(error) = stream.set_write_timeout(Some(WRITE_TIMEOUT))
+        {
+            eprintln!("Unable to set a write timeout for a player because {}", error);
+            continue;
+        }
+
+        let reader = match stream.try_clone()
+        {
+            Ok(reader) => reader,
+            Err(error) =>
+            {
+                eprintln!("Unable to read from a player because {}", error);
+                continue;
+            },
+        };
+
+        if events.send(Event::Connected(id, stream)).is_err()
+        {
+            return;
+        }
+
+        let events = events.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            loop
+            {
+                /* One byte over the limit is enough to tell a line that's too long */
+                let mut line = String::new();
+                match reader.by_ref().take(MAX_LINE + 1).read_line(&mut line)
+                {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) =>
+                    {
+                        let line = line.trim_end_matches(&['\r', '\n'][..]);
+                        if line.len() as u64 > MAX_LINE
+                        {
+                            eprintln!("Dropping a player for sending a line longer than {} bytes", MAX_LINE);
+                            break;
+                        }
+                        if events.send(Event::Line(id, line.to_string())).is_err()
+                        {
+                            return;
+                        }
+                    },
+                }
+            }
+            let _ = events.send(Event::Disconnected(id));
+        });
+    }
+}
+
+fn handle_line(session: &mut dyn Session, clients: &mut HashMap<usize, Client>, id: usize, line: &str)
+{
+    let action = match serde_json::from_str(line)
+    {
+        Ok(action) => action,
+        Err(error) =>
+        {
+            if let Some(client) = clients.get_mut(&id)
+            {
+                send_error(&mut client.stream, &error.into());
+            }
+            return;
+        },
+    };
+
+    let player = match clients.get(&id)
+    {
+        Some(client) => client.player,
+        None => return,
+    };
+
+    match (action, player)
+    {
+        (Action::Join{ name }, None) =>
+        {
+            match session.join(name)
+            {
+                Ok(player) =>
+                {
+                    if let Some(client) = clients.get_mut(&id)
+                    {
+                        client.player = Some(player);
+                        send(&mut client.stream, &ToUiCommand::Joined {player});
+                        /* The player may have their own field */
+                        send_field(&mut client.stream, session, Some(player));
+                    }
+                    broadcast(clients, &session.get_players_update());
+                },
+                Err(error) =>
+                {
+                    if let Some(client) = clients.get_mut(&id)
+                    {
+                        send_error(&mut client.stream, &error);
+                    }
+                },
+            }
+        },
+        (Action::RequestField, _) =>
+        {
+            if let Some(client) = clients.get_mut(&id)
+            {
+                send_field(&mut client.stream, session, client.player);
+            }
+        },
+        (Action::Leave, Some(player)) | (Action::Quit, Some(player)) =>
+        {
+            leave(session, clients, id, player);
+        },
+        (action @ Action::Join{ .. }, Some(_)) =>
+        {
+            let error = ErrorKind::NotAllowed(format!("{:?}", action), "joined by this client".to_string()).into();
+            if let Some(client) = clients.get_mut(&id)
+            {
+                send_error(&mut client.stream, &error);
+            }
+        },
+        (action, None) =>
+        {
+            let error = ErrorKind::NotAllowed(format!("{:?}", action), "waiting for Join".to_string()).into();
+            if let Some(client) = clients.get_mut(&id)
+            {
+                send_error(&mut client.stream, &error);
+            }
+        },
+        (action, Some(player)) =>
+        {
+            let was_over = session.is_over();
+            let is_start = matches!(action, Action::StartRace);
+
+            match session.handle_action(player, action)
+            {
+                Ok(result) =>
+                {
+                    /* Cursors and pending flags only change the players */
+                    if !result.changes.is_empty()
+                    {
+                        let game = session.get_game(Some(player));
+                        let commands = [
+                            ToUiCommand::PlayerTilesChanged {player, changes: result.changes, points: result.points},
+                            ToUiCommand::Summary {summary: game.get_summary()},
+                            ToUiCommand::from_state(game.get_state()),
+                        ];
+
+                        for command in &commands
+                        {
+                            if session.is_shared()
+                            {
+                                broadcast(clients, command);
+                            } else if let Some(client) = clients.get_mut(&id) {
+                                send(&mut client.stream, command);
+                            }
+                        }
+                    }
+                    /* Starting a race opens every racer's field at once */
+                    if is_start
+                    {
+                        for client in clients.values_mut()
+                        {
+                            send_field(&mut client.stream, session, client.player);
+                        }
+                    }
+                    if let Some((x, y)) = result.mine_hit
+                    {
+                        broadcast(clients, &ToUiCommand::MineHit {player, x, y});
+                    }
+                    broadcast(clients, &session.get_players_update());
+                },
+                Err(error) =>
+                {
+                    if let Some(client) = clients.get_mut(&id)
+                    {
+                        send_error(&mut client.stream, &error);
+                    }
+                },
+            }
+
+            if !was_over && session.is_over()
+            {
+                broadcast(clients, &ToUiCommand::SessionOver {winners: session.get_winners()});
+            }
+        },
+    }
+}
+
+fn leave(session: &mut dyn Session, clients: &mut HashMap<usize, Client>, id: usize, player: PlayerId)
+{
+    let was_over = session.is_over();
+
+    if session.leave(player).is_ok()
+    {
+        if let Some(client) = clients.get_mut(&id)
+        {
+            client.player = None;
+        }
+        broadcast(clients, &session.get_players_update());
+    }
+
+    if !was_over && session.is_over()
+    {
+        broadcast(clients, &ToUiCommand::SessionOver {winners: session.get_winners()});
+    }
+}
+
+fn main()
+{
+    let options = match parse_options()
+    {
+        Ok(options) => options,
+        Err(message) =>
+        {
+            eprintln!("{}", message);
+            process::exit(2);
+        },
+    };
+
+    let (width, height, num_bombs) = options.dimensions;
+    let session: Result<Box<dyn Session>, Error> = match options.mode
+    {
+        Mode::Competitive(penalty) => Minesweeper::new(width, height, num_bombs)
+            .map(|game| Box::new(CompetitiveSession::new(game, penalty)) as Box<dyn Session>),
+        Mode::Coop => Minesweeper::new(width, height, num_bombs)
+            .map(|game| Box::new(CoopSession::new(game)) as Box<dyn Session>),
+        Mode::HotSeat => Minesweeper::new(width, height, num_bombs)
+            .map(|game| Box::new(HotSeatSession::new(game)) as Box<dyn Session>),
+        Mode::Race { seed } =>
+        {
+            eprintln!("Racing with seed {}", seed);
+            /* Everyone starts in the middle */
+            RaceSession::new(width, height, num_bombs, seed, (width.0 / 2, height.0 / 2))
+                .map(|race| Box::new(race) as Box<dyn Session>)
+        },
+    };
+    let mut session = match session
+    {
+        Ok(session) => session,
+        Err(error) =>
+        {
+            eprintln!("Unable to start a game of width:{} height:{} bombs:{} because {}", width.0, height.0, num_bombs, error);
+            process::exit(2);
+        },
+    };
+
+    let listener = match TcpListener::bind(("0.0.0.0", options.port))
+    {
+        Ok(listener) => listener,
+        Err(error) =>
+        {
+            eprintln!("Unable to listen on port {} because {}", options.port, error);
+            process::exit(1);
+        },
+    };
+    eprintln!("Hosting a {}x{} game with {} bombs on port {}", width.0, height.0, num_bombs, options.port);
+
+    let (events, received) = mpsc::channel();
+    thread::spawn(move || accept_clients(listener, events));
+
+    let mut clients: HashMap<usize, Client> = HashMap::new();
+    loop
+    {
+        let event = match received.recv_timeout(TICK)
+        {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) =>
+            {
+                if session.is_ticking()
+                {
+                    broadcast(&mut clients, &session.get_players_update());
+                    remove_gone(&mut *session, &mut clients);
+                }
+                continue;
+            },
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        match event
+        {
+            Event::Connected(id, mut stream) =>
+            {
+                send_field(&mut stream, &*session, None);
+                clients.insert(id, Client { stream, player: None, gone: false });
+            },
+            Event::Line(id, line) =>
+            {
+                if !line.trim().is_empty()
+                {
+                    handle_line(&mut *session, &mut clients, id, &line);
+                }
+            },
+            Event::Disconnected(id) =>
+            {
+                if let Some(player) = clients.get(&id).and_then(|client| client.player)
+                {
+                    leave(&mut *session, &mut clients, id, player);
+                }
+                clients.remove(&id);
+            },
+        }
+
+        remove_gone(&mut *session, &mut clients);
+    }
+}
@@ -0,0 +1,108 @@
+//! Deals many boards of one size and reports how often they need a guess and how often a good bot wins them.
+//!
+//! Usage: minesweeper-simulate [--boards N] [--seed N] [--policy unprotected|safe-tile|safe-opening] [--json] [width height num_bombs]
+//! simulates 1000 beginner boards with the default first click policy by default
+
+extern crate minesweeper_backend;
+extern crate serde_json;
+
+use minesweeper_backend::common::{Horizontal, Vertical};
+use minesweeper_backend::engine::minesweeper::FirstClickPolicy;
+use minesweeper_backend::engine::preset::Preset;
+use minesweeper_backend::ai::simulator::{self, SimulationSettings};
+
+use std::env;
+use std::process;
+
+
+const USAGE: &str = "Usage: minesweeper-simulate [--boards N] [--seed N] [--policy unprotected|safe-tile|safe-opening] [--json] [width height num_bombs]";
+
+fn parse_number(flag: &str, value: Option<String>) -> Result<u64, String>
+{
+    match value
+    {
+        Some(value) => value.parse::<u64>().map_err(|error| format!("{} must be a number: {}", flag, error)),
+        None => Err(format!("{} needs a value", flag)),
+    }
+}
+
+/// The settings and whether to print JSON
+fn parse_options() -> Result<(SimulationSettings, bool), String>
+{
+    let (width, height, num_bombs) = Preset::Beginner.dimensions().unwrap();
+    let mut settings = SimulationSettings {
+        width,
+        height,
+        num_bombs,
+        first_click_policy: FirstClickPolicy::SafeTile,
+        boards: 1000,
+        seed: 0,
+    };
+    let mut json = false;
+    let mut dimensions = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next()
+    {
+        match arg.as_str()
+        {
+            "--boards" => settings.boards = parse_number("--boards", args.next())? as usize,
+            "--seed" => settings.seed = parse_number("--seed", args.next())?,
+            "--policy" => settings.first_click_policy = match args.next()
+            {
+                Some(name) => FirstClickPolicy::from_name(&name).ok_or_else(|| format!("{} isn't a first click policy\n{}", name, USAGE))?,
+                None => return Err("--policy needs a value".to_string()),
+            },
+            "--json" => json = true,
+            number => dimensions.push(number.parse::<usize>().map_err(|error| format!("width, height and num_bombs must be numbers: {}", error))?),
+        }
+    }
+
+    match dimensions.len()
+    {
+        0 => {},
+        3 =>
+        {
+            settings.width = Horizontal(dimensions[0]);
+            settings.height = Vertical(dimensions[1]);
+            settings.num_bombs = dimensions[2];
+        },
+        _ => return Err(USAGE.to_string()),
+    }
+
+    Ok((settings, json))
+}
+
+fn main()
+{
+    let (settings, json) = match parse_options()
+    {
+        Ok(options) => options,
+        Err(message) =>
+        {
+            eprintln!("{}", message);
+            process::exit(2);
+        },
+    };
+
+    let report = match simulator::simulate(&settings)
+    {
+        Ok(report) => report,
+        Err(error) =>
+        {
+            eprintln!("Unable to simulate width:{} height:{} bombs:{} because {}", settings.width.0, settings.height.0, settings.num_bombs, error);
+            process::exit(2);
+        },
+    };
+
+    if json
+    {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        return;
+    }
+
+    println!("{}x{} with {} bombs, {:?}, {} boards", report.width, report.height, report.num_bombs, report.first_click_policy, report.boards);
+    println!("must guess      {:>6.2}%", report.must_guess_rate * 100.0);
+    println!("average guesses {:>7.2}", report.average_guesses);
+    println!("bot win rate    {:>6.2}%", report.win_rate * 100.0);
+}
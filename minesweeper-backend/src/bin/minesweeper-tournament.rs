@@ -0,0 +1,106 @@
+//! Plays bot strategies against each other on the same seeded fields and reports how they did.
+//!
+//! Usage: minesweeper-tournament [--games N] [--seed N] [--json] [strategy ...]
+//! plays every built-in strategy 1000 times on each classic preset by default
+
+extern crate minesweeper_backend;
+extern crate serde_json;
+
+use minesweeper_backend::ai::strategy::{self, Strategy, BUILTIN_NAMES};
+use minesweeper_backend::ai::tournament::{self, TournamentSettings};
+
+use std::env;
+use std::process;
+
+
+const USAGE: &str = "Usage: minesweeper-tournament [--games N] [--seed N] [--json] [strategy ...]";
+
+struct Options
+{
+    settings: TournamentSettings,
+    strategies: Vec<Box<dyn Strategy>>,
+    json: bool,
+}
+
+fn parse_number(flag: &str, value: Option<String>) -> Result<u64, String>
+{
+    match value
+    {
+        Some(value) => value.parse::<u64>().map_err(|error| format!("{} must be a number: {}", flag, error)),
+        None => Err(format!("{} needs a value", flag)),
+    }
+}
+
+fn parse_options() -> Result<Options, String>
+{
+    let mut options = Options {
+        settings: TournamentSettings::default(),
+        strategies: Vec::new(),
+        json: false,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next()
+    {
+        match arg.as_str()
+        {
+            "--games" => options.settings.games_per_preset = parse_number("--games", args.next())? as usize,
+            "--seed" => options.settings.seed = parse_number("--seed", args.next())?,
+            "--json" => options.json = true,
+            name => match strategy::builtin(name)
+            {
+                Some(strategy) => options.strategies.push(strategy),
+                None => return Err(format!("{} isn't a strategy, try one of {}\n{}", name, BUILTIN_NAMES.join(", "), USAGE)),
+            },
+        }
+    }
+
+    if options.strategies.is_empty()
+    {
+        options.strategies = BUILTIN_NAMES.iter().filter_map(|name| strategy::builtin(name)).collect();
+    }
+
+    Ok(options)
+}
+
+fn main()
+{
+    let mut options = match parse_options()
+    {
+        Ok(options) => options,
+        Err(message) =>
+        {
+            eprintln!("{}", message);
+            process::exit(2);
+        },
+    };
+
+    let reports = match tournament::run(&mut options.strategies, &options.settings)
+    {
+        Ok(reports) => reports,
+        Err(error) =>
+        {
+            eprintln!("Unable to run the tournament because {}", error);
+            process::exit(1);
+        },
+    };
+
+    if options.json
+    {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+        return;
+    }
+
+    println!("{:<14} {:<14} {:>7} {:>8} {:>10} {:>11} {:>10}", "strategy", "preset", "played", "win %", "avg ms", "avg 3BV", "solved 3BV");
+    for report in reports.iter()
+    {
+        println!("{:<14} {:<14} {:>7} {:>8.2} {:>10.3} {:>11.2} {:>10.2}",
+            report.strategy,
+            format!("{:?}", report.preset),
+            report.played,
+            report.win_rate() * 100.0,
+            report.average_seconds * 1000.0,
+            report.average_bbbv,
+            report.average_bbbv_solved);
+    }
+}
@@ -0,0 +1,566 @@
+//! Plays the same engine as the webview app but in a terminal, so it works over SSH.
+//!
+//! Usage: minesweeper-tui [--players name,name[,name,name] | --flags name[,name] [--computer easy|medium|hard]] [width height num_bombs]
+//! defaults to the beginner preset.
+//! `--players` plays hot-seat, everyone takes turns at the same keyboard.
+//! `--flags` plays Flags where two players race to find the bombs, against the computer when only one name is given
+
+#[macro_use]
+extern crate crossterm;
+extern crate minesweeper_backend;
+
+use minesweeper_backend::common::{Horizontal, Vertical};
+use minesweeper_backend::engine::minesweeper::{Action, Minesweeper, State};
+use minesweeper_backend::errors;
+use minesweeper_backend::engine::packed::PackedTile;
+use minesweeper_backend::engine::preset::Preset;
+use minesweeper_backend::session::{PlayerId, Session};
+use minesweeper_backend::session::hotseat::{HotSeatSession, SeatSummary};
+use minesweeper_backend::session::flags::FlagsSession;
+use minesweeper_backend::ai::flags::{Difficulty, FlagsOpponent};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+
+/// Lines below the field for the status and the keys
+const STATUS_LINES: u16 = 2;
+/// Every tile is drawn as its symbol followed by a space so the field isn't squished horizontally
+const TILE_COLUMNS: u16 = 2;
+
+const KEYS_HELP: &str = "arrows/hjkl move  space expose  f flag  c chord  n new game  q quit";
+/// Flags marks each bomb in the color of whoever found it
+const CLAIM_COLORS: [Color; 2] = [Color::Yellow, Color::Magenta];
+const COMPUTER_NAME: &str = "Computer";
+
+enum Mode
+{
+    Solo(Minesweeper),
+    HotSeat(HotSeatSession),
+    /// The computer always plays the second seat
+    Flags(FlagsSession, Option<FlagsOpponent>),
+}
+
+struct Tui
+{
+    mode: Mode,
+    cursor_x: usize,
+    cursor_y: usize,
+    /// Top left tile that is drawn, only moves when the field doesn't fit in the terminal
+    scroll_x: usize,
+    scroll_y: usize,
+    /// Why the last move was rejected, empty if it wasn't
+    message: String,
+}
+
+impl Tui
+{
+    fn new(mode: Mode) -> Tui
+    {
+        Tui {
+            mode,
+            cursor_x: 0,
+            cursor_y: 0,
+            scroll_x: 0,
+            scroll_y: 0,
+            message: String::new(),
+        }
+    }
+
+    fn game(&self) -> &Minesweeper
+    {
+        match self.mode
+        {
+            Mode::Solo(ref game) => game,
+            Mode::HotSeat(ref session) => session.get_game(None),
+            Mode::Flags(ref session, _) => session.get_game(None),
+        }
+    }
+
+    /// Hot-seat starts over with the same players
+    fn new_game(&mut self)
+    {
+        let (width, height, num_bombs) = (self.game().get_width(), self.game().get_height(), self.game().get_num_bombs());
+
+        let result = match self.mode
+        {
+            Mode::Solo(ref mut game) => game.resize(width, height, num_bombs),
+            Mode::HotSeat(ref mut session) =>
+            {
+                let names = session.get_seats().into_iter().map(|seat| seat.name).collect();
+                Minesweeper::new(width, height, num_bombs)
+                    .and_then(|game| HotSeatSession::with_players(game, names))
+                    .map(|new_session| *session = new_session)
+            },
+            Mode::Flags(ref mut session, _) =>
+            {
+                let names = session.get_seats().into_iter().map(|seat| seat.name).collect();
+                Minesweeper::new(width, height, num_bombs)
+                    .and_then(|game| flags_session(game, names))
+                    .map(|new_session| *session = new_session)
+            },
+        };
+
+        match result
+        {
+            Ok(_) => self.message.clear(),
+            Err(error) => self.message = error.to_string(),
+        }
+    }
+
+    fn apply(&mut self, action: Action)
+    {
+        let result = match self.mode
+        {
+            Mode::Solo(ref mut game) => game.handle_action(action).map(|_| String::new()),
+            Mode::HotSeat(ref mut session) =>
+            {
+                let turn = session.get_turn();
+                session.handle_action(turn, action).map(|result| match result.mine_hit
+                {
+                    Some(_) => format!("{} hit a mine and is out", session.get_seats()[turn.0].name),
+                    None => String::new(),
+                })
+            },
+            Mode::Flags(ref mut session, ref computer) =>
+            {
+                let turn = session.get_turn();
+                session.handle_action(turn, action).map(|_| match *computer
+                {
+                    Some(ref computer) => play_computer(session, computer),
+                    None => String::new(),
+                })
+            },
+        };
+
+        match result
+        {
+            Ok(message) => self.message = message,
+            Err(error) => self.message = error.to_string(),
+        }
+    }
+
+    fn cursor_tile(&self) -> PackedTile
+    {
+        self.game().get_tiles()[(Horizontal(self.cursor_x), Vertical(self.cursor_y))]
+    }
+
+    fn expose(&mut self)
+    {
+        self.apply(Action::Expose { x_position: self.cursor_x, y_position: self.cursor_y });
+    }
+
+    fn toggle_flag(&mut self)
+    {
+        let (x_position, y_position) = (self.cursor_x, self.cursor_y);
+
+        if self.cursor_tile().has_flag()
+        {
+            self.apply(Action::Unflag { x_position, y_position });
+        } else {
+            self.apply(Action::Flag { x_position, y_position });
+        }
+    }
+
+    /// Exposes every unflagged neighbor of a shown number once it has that many flags around it
+    fn chord(&mut self)
+    {
+        self.apply(Action::Chord { x_position: self.cursor_x, y_position: self.cursor_y });
+    }
+
+    fn move_cursor(&mut self, dx: isize, dy: isize)
+    {
+        let max_x = self.game().get_width().0.saturating_sub(1) as isize;
+        let max_y = self.game().get_height().0.saturating_sub(1) as isize;
+
+        self.cursor_x = (self.cursor_x as isize + dx).max(0).min(max_x) as usize;
+        self.cursor_y = (self.cursor_y as isize + dy).max(0).min(max_y) as usize;
+    }
+
+    /// Keeps the cursor inside the part of the field that fits in `columns` by `rows` tiles
+    fn scroll_to_cursor(&mut self, columns: usize, rows: usize)
+    {
+        if self.cursor_x < self.scroll_x
+        {
+            self.scroll_x = self.cursor_x;
+        } else if self.cursor_x >= self.scroll_x + columns {
+            self.scroll_x = self.cursor_x + 1 - columns;
+        }
+
+        if self.cursor_y < self.scroll_y
+        {
+            self.scroll_y = self.cursor_y;
+        } else if self.cursor_y >= self.scroll_y + rows {
+            self.scroll_y = self.cursor_y + 1 - rows;
+        }
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W) -> crossterm::Result<()>
+    {
+        let (terminal_columns, terminal_rows) = terminal::size()?;
+        let columns = ((terminal_columns / TILE_COLUMNS) as usize).max(1);
+        let rows = (terminal_rows.saturating_sub(STATUS_LINES) as usize).max(1);
+        self.scroll_to_cursor(columns, rows);
+
+        queue!(out, Clear(ClearType::All))?;
+
+        let tiles = self.game().get_tiles_in(&Horizontal(self.scroll_x), &Vertical(self.scroll_y), &Horizontal(columns), &Vertical(rows));
+        for (row_index, row) in tiles.iter().enumerate()
+        {
+            queue!(out, MoveTo(0, row_index as u16))?;
+
+            for (column_index, tile) in row.iter().enumerate()
+            {
+                let is_cursor = self.scroll_x + column_index == self.cursor_x && self.scroll_y + row_index == self.cursor_y;
+                if is_cursor
+                {
+                    queue!(out, SetAttribute(Attribute::Reverse))?;
+                }
+
+                let (symbol, mut color) = tile_symbol(tile);
+                if let Mode::Flags(ref session, _) = self.mode
+                {
+                    if let Some(claimed_by) = session.get_claimed_by(self.scroll_x + column_index, self.scroll_y + row_index)
+                    {
+                        color = CLAIM_COLORS[claimed_by.0 % CLAIM_COLORS.len()];
+                    }
+                }
+                queue!(out, SetForegroundColor(color), Print(symbol), ResetColor)?;
+
+                if is_cursor
+                {
+                    queue!(out, SetAttribute(Attribute::NoReverse))?;
+                }
+                queue!(out, Print(' '))?;
+            }
+        }
+
+        let summary = self.game().get_summary();
+        let state = match self.mode
+        {
+            Mode::Solo(ref game) => match game.get_state()
+            {
+                State::Won => "You won!".to_string(),
+                State::Loss => "You lost.".to_string(),
+                State::New | State::InProgress => "Playing".to_string(),
+            },
+            Mode::HotSeat(ref session) => turn_status(&session.get_seats(), session.get_turn().0, session.is_over(), &session.get_winners()),
+            Mode::Flags(ref session, _) => format!("{}  First to {} bombs",
+                turn_status(&session.get_seats(), session.get_turn().0, session.is_over(), &session.get_winners()), session.get_bombs_to_win()),
+        };
+        let status = format!("{}  Flags: {}/{}  Shown: {}/{}  {}",
+            state, summary.num_flags, summary.num_bombs, summary.num_shown, summary.width * summary.height, self.message);
+
+        queue!(out,
+            MoveTo(0, terminal_rows.saturating_sub(STATUS_LINES)), Print(status),
+            MoveTo(0, terminal_rows.saturating_sub(1)), SetForegroundColor(Color::DarkGrey), Print(KEYS_HELP), ResetColor)?;
+
+        out.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns false once the player wants to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool
+    {
+        match key
+        {
+            KeyEvent { code: KeyCode::Char('c'), modifiers } if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyEvent { code: KeyCode::Char('q'), .. } | KeyEvent { code: KeyCode::Esc, .. } => return false,
+
+            KeyEvent { code: KeyCode::Left, .. } | KeyEvent { code: KeyCode::Char('h'), .. } => self.move_cursor(-1, 0),
+            KeyEvent { code: KeyCode::Right, .. } | KeyEvent { code: KeyCode::Char('l'), .. } => self.move_cursor(1, 0),
+            KeyEvent { code: KeyCode::Up, .. } | KeyEvent { code: KeyCode::Char('k'), .. } => self.move_cursor(0, -1),
+            KeyEvent { code: KeyCode::Down, .. } | KeyEvent { code: KeyCode::Char('j'), .. } => self.move_cursor(0, 1),
+
+            KeyEvent { code: KeyCode::Char(' '), .. } | KeyEvent { code: KeyCode::Enter, .. } => self.expose(),
+            KeyEvent { code: KeyCode::Char('f'), .. } => self.toggle_flag(),
+            KeyEvent { code: KeyCode::Char('c'), .. } => self.chord(),
+            KeyEvent { code: KeyCode::Char('n'), .. } => self.new_game(),
+            _ => {},
+        };
+
+        true
+    }
+}
+
+/// Whose turn it is and everyone's score, or who won
+fn turn_status(seats: &[SeatSummary], turn: usize, is_over: bool, winners: &[PlayerId]) -> String
+{
+    let scores: Vec<String> = seats.iter()
+        .map(|seat| format!("{} {}{}", seat.name, seat.score, if seat.eliminated { " (out)" } else { "" }))
+        .collect();
+
+    let headline = if is_over
+    {
+        let winners: Vec<String> = winners.iter().map(|winner| seats[winner.0].name.clone()).collect();
+        match winners.len()
+        {
+            0 => "Nobody won.".to_string(),
+            _ => format!("{} won!", winners.join(" & ")),
+        }
+    } else {
+        format!("{}'s turn", seats[turn].name)
+    };
+
+    format!("{}  [{}]", headline, scores.join(", "))
+}
+
+/// Mirrors `createFieldSlot` in the Elm UI
+fn tile_symbol(tile: &PackedTile) -> (char, Color)
+{
+    if tile.is_bomb()
+    {
+        ('*', Color::Red)
+    } else if tile.has_flag() {
+        ('F', Color::Yellow)
+    } else if !tile.is_shown() {
+        ('.', Color::DarkGrey)
+    } else if tile.num_bombs_around() == 0 {
+        (' ', Color::Reset)
+    } else {
+        (number_symbol(tile.num_bombs_around()), number_color(tile.num_bombs_around()))
+    }
+}
+
+fn number_symbol(num_bombs_around: usize) -> char
+{
+    ::std::char::from_digit(num_bombs_around as u32, 10).unwrap_or('?')
+}
+
+fn number_color(num_bombs_around: usize) -> Color
+{
+    match num_bombs_around
+    {
+        1 => Color::Blue,
+        2 => Color::Green,
+        3 => Color::Red,
+        4 => Color::DarkBlue,
+        5 => Color::DarkRed,
+        6 => Color::Cyan,
+        7 => Color::Magenta,
+        _ => Color::Grey,
+    }
+}
+
+fn flags_session(game: Minesweeper, names: Vec<String>) -> errors::Result<FlagsSession>
+{
+    let mut session = FlagsSession::new(game);
+    for name in names
+    {
+        session.join(name)?;
+    }
+
+    Ok(session)
+}
+
+/// Lets the computer take its turns, which go on for as long as it keeps finding bombs
+fn play_computer(session: &mut FlagsSession, computer: &FlagsOpponent) -> String
+{
+    let mut found = 0;
+
+    while !session.is_over() && session.get_turn() == PlayerId(1)
+    {
+        let (x_position, y_position) = match computer.choose_move(session.get_game(None))
+        {
+            Some(position) => position,
+            None => break,
+        };
+
+        match session.handle_action(PlayerId(1), Action::Expose { x_position, y_position })
+        {
+            Ok(result) => found += result.points,
+            Err(error) => return format!("{} got stuck because {}", COMPUTER_NAME, error),
+        }
+    }
+
+    match found
+    {
+        0 => String::new(),
+        1 => format!("{} found a bomb", COMPUTER_NAME),
+        _ => format!("{} found {} bombs", COMPUTER_NAME, found),
+    }
+}
+
+/// Removes `flag` and the value after it from `args`
+fn take_value(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, String>
+{
+    match args.iter().position(|arg| arg == flag)
+    {
+        Some(index) if index + 1 < args.len() =>
+        {
+            args.remove(index);
+            Ok(Some(args.remove(index)))
+        },
+        Some(_) => Err(format!("{} needs a value", flag)),
+        None => Ok(None),
+    }
+}
+
+fn split_names(names: &str) -> Vec<String>
+{
+    names.split(',').map(|name| name.trim().to_string()).collect()
+}
+
+/// Who is playing, picked from the command line before there's a game to play on
+enum Players
+{
+    Solo,
+    HotSeat(Vec<String>),
+    Flags(Vec<String>, Option<FlagsOpponent>),
+}
+
+fn parse_players(args: &mut Vec<String>) -> Result<Players, String>
+{
+    let players = take_value(args, "--players")?;
+    let flags = take_value(args, "--flags")?;
+    let computer = match take_value(args, "--computer")?
+    {
+        Some(name) => Some(Difficulty::from_name(&name).ok_or_else(|| format!("{} isn't easy, medium or hard", name))?),
+        None => None,
+    };
+
+    match (players, flags, computer)
+    {
+        (Some(names), None, None) => Ok(Players::HotSeat(split_names(&names))),
+        (None, Some(names), computer) =>
+        {
+            let mut names = split_names(&names);
+            let computer = match (names.len(), computer)
+            {
+                (1, difficulty) =>
+                {
+                    names.push(COMPUTER_NAME.to_string());
+                    Some(FlagsOpponent::new(difficulty.unwrap_or(Difficulty::Medium)))
+                },
+                (_, None) => None,
+                (_, Some(_)) => return Err("--computer only plays against one named player".to_string()),
+            };
+
+            Ok(Players::Flags(names, computer))
+        },
+        (None, None, None) => Ok(Players::Solo),
+        (Some(_), Some(_), _) => Err("Pick either --players or --flags".to_string()),
+        (_, None, Some(_)) => Err("--computer only plays Flags".to_string()),
+    }
+}
+
+const USAGE: &str = "Usage: minesweeper-tui [--players name,name[,name,name] | --flags name[,name] [--computer easy|medium|hard]] [width height num_bombs]";
+
+fn parse_dimensions(args: Vec<String>) -> Result<(Horizontal, Vertical, usize), String>
+{
+    match args.len()
+    {
+        0 => Ok(Preset::Beginner.dimensions().unwrap()),
+        3 =>
+        {
+            let numbers: Result<Vec<usize>, _> = args.iter().map(|arg| arg.parse::<usize>()).collect();
+            match numbers
+            {
+                Ok(numbers) => Ok((Horizontal(numbers[0]), Vertical(numbers[1]), numbers[2])),
+                Err(error) => Err(format!("width, height and num_bombs must be numbers: {}", error)),
+            }
+        },
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn run<W: Write>(tui: &mut Tui, out: &mut W) -> crossterm::Result<()>
+{
+    loop
+    {
+        tui.draw(out)?;
+
+        match event::read()?
+        {
+            Event::Key(key) =>
+            {
+                if !tui.handle_key(key)
+                {
+                    return Ok(());
+                }
+            },
+            /* Redrawn at the top of the loop */
+            Event::Resize(_, _) => {},
+            Event::Mouse(_) => {},
+        }
+    }
+}
+
+fn main()
+{
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let players = match parse_players(&mut args)
+    {
+        Ok(players) => players,
+        Err(message) =>
+        {
+            eprintln!("{}", message);
+            process::exit(2);
+        },
+    };
+
+    let (width, height, num_bombs) = match parse_dimensions(args)
+    {
+        Ok(dimensions) => dimensions,
+        Err(message) =>
+        {
+            eprintln!("{}", message);
+            process::exit(2);
+        },
+    };
+
+    let game = match Minesweeper::new(width, height, num_bombs)
+    {
+        Ok(game) => game,
+        Err(error) =>
+        {
+            eprintln!("Unable to start a game of width:{} height:{} bombs:{} because {}", width.0, height.0, num_bombs, error);
+            process::exit(2);
+        },
+    };
+    let mode = match players
+    {
+        Players::Solo => Mode::Solo(game),
+        Players::HotSeat(names) => match HotSeatSession::with_players(game, names)
+        {
+            Ok(session) => Mode::HotSeat(session),
+            Err(error) =>
+            {
+                eprintln!("Unable to start a hot-seat game because {}", error);
+                process::exit(2);
+            },
+        },
+        Players::Flags(names, computer) => match flags_session(game, names)
+        {
+            Ok(session) => Mode::Flags(session, computer),
+            Err(error) =>
+            {
+                eprintln!("Unable to start a game of Flags because {}", error);
+                process::exit(2);
+            },
+        },
+    };
+    let mut tui = Tui::new(mode);
+
+    let mut stdout = io::stdout();
+    let result = terminal::enable_raw_mode()
+        .and_then(|_| execute!(stdout, EnterAlternateScreen, Hide))
+        .and_then(|_| run(&mut tui, &mut stdout));
+
+    /* Always give the terminal back, even when drawing failed */
+    let _ = execute!(stdout, Show, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+
+    if let Err(error) = result
+    {
+        eprintln!("Terminal error: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,61 @@
+/// A fixed number of bits, all starting out unset
+#[derive(Clone, Debug)]
+pub struct BitSet
+{
+    len: usize,
+    words: Vec<u64>,
+}
+
+const WORD_BITS: usize = 64;
+
+impl BitSet
+{
+    pub fn new(len: usize) -> BitSet
+    {
+        let num_words = len.div_ceil(WORD_BITS);
+
+        BitSet {
+            len,
+            words: vec![0; num_words],
+        }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+
+    /// Panics if `index` is not below `len`
+    pub fn contains(&self, index: usize) -> bool
+    {
+        assert!(index < self.len, "index:{} is outside of a BitSet of len:{}", index, self.len);
+
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    /// Returns whether the bit was newly set, panics if `index` is not below `len`
+    pub fn insert(&mut self, index: usize) -> bool
+    {
+        assert!(index < self.len, "index:{} is outside of a BitSet of len:{}", index, self.len);
+
+        let word = &mut self.words[index / WORD_BITS];
+        let bit = 1 << (index % WORD_BITS);
+        let was_set = *word & bit != 0;
+        *word |= bit;
+
+        !was_set
+    }
+
+    pub fn clear(&mut self)
+    {
+        for word in self.words.iter_mut()
+        {
+            *word = 0;
+        }
+    }
+}
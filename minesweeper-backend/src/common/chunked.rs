@@ -0,0 +1,114 @@
+use ::errors::Result;
+
+use super::{WorldHorizontal, WorldVertical};
+
+use std::collections::HashMap;
+
+
+/// World coordinates divided by the chunk size, rounding towards negative infinity
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ChunkPosition
+{
+    pub x: i64,
+    pub y: i64,
+}
+
+/// An unbounded grid made of square chunks that only exist once they're inserted
+#[derive(Debug)]
+pub struct ChunkedGrid<T>
+{
+    chunk_size: usize,
+    chunks: HashMap<ChunkPosition, Vec<T>>,
+}
+
+impl<T> ChunkedGrid<T>
+{
+    pub fn new(chunk_size: usize) -> ChunkedGrid<T>
+    {
+        ChunkedGrid {
+            chunk_size,
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn chunk_size(&self) -> usize
+    {
+        self.chunk_size
+    }
+
+    pub fn num_chunks(&self) -> usize
+    {
+        self.chunks.len()
+    }
+
+    pub fn chunk_of(&self, x: &WorldHorizontal, y: &WorldVertical) -> ChunkPosition
+    {
+        let size = self.chunk_size as i64;
+
+        ChunkPosition {
+            x: x.0.div_euclid(size),
+            y: y.0.div_euclid(size),
+        }
+    }
+
+    /// World coordinates of the top left tile of the chunk
+    pub fn origin_of(&self, chunk: &ChunkPosition) -> (WorldHorizontal, WorldVertical)
+    {
+        let size = self.chunk_size as i64;
+
+        (WorldHorizontal(chunk.x * size), WorldVertical(chunk.y * size))
+    }
+
+    fn index_in_chunk(&self, x: &WorldHorizontal, y: &WorldVertical) -> usize
+    {
+        let size = self.chunk_size as i64;
+
+        (y.0.rem_euclid(size) * size + x.0.rem_euclid(size)) as usize
+    }
+
+    pub fn has_chunk(&self, chunk: &ChunkPosition) -> bool
+    {
+        self.chunks.contains_key(chunk)
+    }
+
+    /// `items` are row by row starting from the chunk's origin and must fill the whole chunk
+    pub fn insert_chunk(&mut self, chunk: ChunkPosition, items: Vec<T>) -> Result<()>
+    {
+        if items.len() != self.chunk_size * self.chunk_size
+        {
+            bail!("Chunk x:{}, y:{} needs {} items but was given {}", chunk.x, chunk.y, self.chunk_size * self.chunk_size, items.len());
+        }
+
+        self.chunks.insert(chunk, items);
+
+        Ok(())
+    }
+
+    pub fn get(&self, x: &WorldHorizontal, y: &WorldVertical) -> Option<&T>
+    {
+        let index = self.index_in_chunk(x, y);
+
+        self.chunks.get(&self.chunk_of(x, y)).and_then(|chunk| chunk.get(index))
+    }
+
+    pub fn get_mut(&mut self, x: &WorldHorizontal, y: &WorldVertical) -> Option<&mut T>
+    {
+        let index = self.index_in_chunk(x, y);
+        let chunk = self.chunk_of(x, y);
+
+        self.chunks.get_mut(&chunk).and_then(|chunk| chunk.get_mut(index))
+    }
+
+    /// Every item in every chunk that exists, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (WorldHorizontal, WorldVertical, &T)>
+    {
+        let size = self.chunk_size as i64;
+
+        self.chunks.iter().flat_map(move |(chunk, items)| {
+            items.iter().enumerate().map(move |(index, item)| {
+                let index = index as i64;
+                (WorldHorizontal(chunk.x * size + index % size), WorldVertical(chunk.y * size + index / size), item)
+            })
+        })
+    }
+}
@@ -1,4 +1,6 @@
 pub mod vec2d;
+pub mod chunked;
+pub mod bitset;
 
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -8,4 +10,27 @@ pub struct Horizontal(pub usize);
 pub struct Vertical(pub usize);
 
 impl Horizontal {}
-impl Vertical {}
\ No newline at end of file
+impl Vertical {}
+
+/// Signed counterparts of Horizontal & Vertical for fields that extend forever in every direction
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WorldHorizontal(pub i64);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WorldVertical(pub i64);
+
+impl WorldHorizontal {}
+impl WorldVertical {}
+/// Seed for when nobody picked one. `rand` has no entropy source on wasm32-unknown-unknown so the browser provides it there
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random_seed() -> u64
+{
+    ::rand::random()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn random_seed() -> u64
+{
+    /* Math.random only has 53 bits worth of randomness */
+    (::js_sys::Math::random() * (1u64 << 53) as f64) as u64
+}
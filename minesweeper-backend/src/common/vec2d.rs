@@ -1,67 +1,416 @@
-
-use ::errors::Result;
+use ::errors::{ErrorKind, Result};
 
 use super::{Horizontal, Vertical};
 
-#[derive(Debug)]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error as DeError;
+use serde::ser::SerializeSeq;
+
+use std::ops::{Index, IndexMut};
+
+
+/// A width by height grid stored row by row in one contiguous buffer
+#[derive(Clone, PartialEq, Debug)]
 pub struct Vec2d<T>
 {
     width: Horizontal,
     height: Vertical,
-    xy_vec: Vec<Vec<T>>,
+    items: Vec<T>,
 }
 
 impl<T> Vec2d<T>
 {
-    pub fn new(width: Horizontal, height: Vertical) -> Vec2d<T>
+    /// Every slot starts as `T::default()`, fails if width * height overflows or is 0
+    pub fn new(width: Horizontal, height: Vertical) -> Result<Vec2d<T>>
+        where T: Default
+    {
+        Vec2d::from_fn(width, height, |_, _| T::default())
+    }
+
+    /// Same as `new` but every slot starts as `item_func(x, y)`
+    pub fn from_fn<F>(width: Horizontal, height: Vertical, mut item_func: F) -> Result<Vec2d<T>>
+        where F: FnMut(Horizontal, Vertical) -> T
+    {
+        let len = Vec2d::<T>::checked_len(width, height)?;
+
+        let mut items = Vec::with_capacity(len);
+        for y in 0..height.0
+        {
+            for x in 0..width.0
+            {
+                items.push(item_func(Horizontal(x), Vertical(y)));
+            }
+        }
+
+        Ok(Vec2d { width, height, items })
+    }
+
+    /// `items` are row by row and there must be exactly width * height of them, which can't be 0
+    pub fn from_vec(width: Horizontal, height: Vertical, items: Vec<T>) -> Result<Vec2d<T>>
+    {
+        let len = Vec2d::<T>::checked_len(width, height)?;
+        if items.len() != len
+        {
+            bail!("width:{} * height:{} needs {} items but was given {}", width.0, height.0, len, items.len());
+        }
+
+        Ok(Vec2d { width, height, items })
+    }
+
+    /// Every row must be the same length and there has to be at least one tile
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Vec2d<T>>
+    {
+        let height = Vertical(rows.len());
+        let width = Horizontal(rows.first().map(|row| row.len()).unwrap_or(0));
+
+        let mut items = Vec::with_capacity(width.0 * height.0);
+        for (y, row) in rows.into_iter().enumerate()
+        {
+            if row.len() != width.0
+            {
+                bail!("Row {} has {} items but the first row has {}", y, row.len(), width.0);
+            }
+            items.extend(row);
+        }
+
+        Vec2d::from_vec(width, height, items)
+    }
+
+    /// Every constructor goes through here so none of them can make a Vec2d without tiles
+    fn checked_len(width: Horizontal, height: Vertical) -> Result<usize>
+    {
+        match width.0.checked_mul(height.0)
+        {
+            Some(0) => bail!(ErrorKind::EmptyField(width.0, height.0)),
+            Some(len) => Ok(len),
+            None => bail!(ErrorKind::Overflow(width.0, height.0)),
+        }
+    }
+
+    pub fn width(&self) -> Horizontal
+    {
+        self.width
+    }
+
+    pub fn height(&self) -> Vertical
+    {
+        self.height
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.items.is_empty()
+    }
+
+    fn index_of(&self, x: &Horizontal, y: &Vertical) -> Option<usize>
+    {
+        if x.0 < self.width.0 && y.0 < self.height.0
+        {
+            Some(y.0 * self.width.0 + x.0)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, x: &Horizontal, y: &Vertical) -> Option<&T>
+    {
+        match self.index_of(x, y)
+        {
+            Some(index) => self.items.get(index),
+            None => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, x: &Horizontal, y: &Vertical) -> Option<&mut T>
+    {
+        match self.index_of(x, y)
+        {
+            Some(index) => self.items.get_mut(index),
+            None => None,
+        }
+    }
+
+    pub fn row(&self, y: &Vertical) -> Option<&[T]>
+    {
+        if y.0 < self.height.0
+        {
+            let start = y.0 * self.width.0;
+            Some(&self.items[start..start + self.width.0])
+        } else {
+            None
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]>
+    {
+        /* `chunks` doesn't allow a size of 0 but with a width of 0 there are no items to chunk anyways */
+        self.items.chunks(self.width.0.max(1))
+    }
+
+    /// Top to bottom, empty when x is outside of the Vec2d
+    pub fn column(&self, x: &Horizontal) -> impl Iterator<Item = &T>
+    {
+        let start = if x.0 < self.width.0 { x.0 } else { self.items.len() };
+
+        self.items.iter().skip(start).step_by(self.width.0.max(1))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T>
+    {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T>
+    {
+        self.items.iter_mut()
+    }
+
+    /// Every x,y row by row
+    pub fn positions(&self) -> impl Iterator<Item = (Horizontal, Vertical)>
+    {
+        let width = self.width.0.max(1);
+
+        (0..self.items.len()).map(move |index| (Horizontal(index % width), Vertical(index / width)))
+    }
+
+    pub fn enumerate(&self) -> impl Iterator<Item = (Horizontal, Vertical, &T)>
     {
+        self.positions().zip(self.items.iter()).map(|((x, y), item)| (x, y, item))
+    }
+
+    pub fn enumerate_mut(&mut self) -> impl Iterator<Item = (Horizontal, Vertical, &mut T)>
+    {
+        let width = self.width.0.max(1);
+
+        self.items.iter_mut().enumerate().map(move |(index, item)| (Horizontal(index % width), Vertical(index / width), item))
+    }
+
+    pub fn map<U, F>(&self, func: F) -> Vec2d<U>
+        where F: FnMut(&T) -> U
+    {
+        Vec2d {
+            width: self.width,
+            height: self.height,
+            items: self.items.iter().map(func).collect(),
+        }
+    }
+
+    /// The up to 8 positions around x,y that are inside the Vec2d, not including x,y itself.
+    /// The iterator doesn't borrow the Vec2d so it can be mutated while iterating
+    pub fn neighbors_of(&self, x: &Horizontal, y: &Vertical) -> Neighbors
+    {
+        Neighbors {
+            x: *x,
+            y: *y,
+            width: self.width,
+            height: self.height,
+            offset: 0,
+        }
+    }
+
+    /// Row slices of the rectangle starting at x,y, clipped to the edges of the Vec2d
+    pub fn get_rect(&self, x: &Horizontal, y: &Vertical, width: &Horizontal, height: &Vertical) -> Vec<&[T]>
+    {
+        let x_end = x.0.saturating_add(width.0).min(self.width.0);
+        let x_start = x.0.min(x_end);
+        let y_end = y.0.saturating_add(height.0).min(self.height.0);
+        let y_start = y.0.min(y_end);
+
+        (y_start..y_end)
+            .map(|row| &self.items[row * self.width.0 + x_start..row * self.width.0 + x_end])
+            .collect()
+    }
+
+    /// Same as `get_rect` but as a new Vec2d
+    pub fn sub_rect(&self, x: &Horizontal, y: &Vertical, width: &Horizontal, height: &Vertical) -> Vec2d<T>
+        where T: Clone
+    {
+        let rows = self.get_rect(x, y, width, height);
+        let width = Horizontal(rows.first().map(|row| row.len()).unwrap_or(0));
+        let height = Vertical(rows.len());
+
         Vec2d {
             width,
             height,
-            xy_vec: Vec::with_capacity(height.0),
+            items: rows.iter().flat_map(|row| row.iter().cloned()).collect(),
         }
     }
+}
 
-    pub fn get(&self, x: &Horizontal, y: &Vertical) -> Option<&T>
+/// 0x0, for a placeholder until the real size is known
+impl<T> Default for Vec2d<T>
+{
+    fn default() -> Vec2d<T>
     {
-        match self.xy_vec.get(y.0)
+        Vec2d {
+            width: Horizontal(0),
+            height: Vertical(0),
+            items: Vec::new(),
+        }
+    }
+}
+
+impl<T> Index<(Horizontal, Vertical)> for Vec2d<T>
+{
+    type Output = T;
+
+    fn index(&self, (x, y): (Horizontal, Vertical)) -> &T
+    {
+        match self.index_of(&x, &y)
         {
-            Some(row) => row.get(x.0),
-            _ => None,
+            Some(index) => &self.items[index],
+            None => panic!("x:{}, y:{} is outside of width:{}, height:{}", x.0, y.0, self.width.0, self.height.0),
         }
     }
+}
 
-    pub fn get_mut(&mut self, x: &Horizontal, y: &Vertical) -> Option<&mut T>
+impl<T> IndexMut<(Horizontal, Vertical)> for Vec2d<T>
+{
+    fn index_mut(&mut self, (x, y): (Horizontal, Vertical)) -> &mut T
+    {
+        match self.index_of(&x, &y)
+        {
+            Some(index) => &mut self.items[index],
+            None => panic!("x:{}, y:{} is outside of width:{}, height:{}", x.0, y.0, self.width.0, self.height.0),
+        }
+    }
+}
+
+/// Serialized as a list of rows so it looks the same as a `Vec<Vec<T>>`
+impl<T: Serialize> Serialize for Vec2d<T>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    {
+        let num_rows = if self.width.0 == 0 { 0 } else { self.height.0 };
+
+        let mut seq = serializer.serialize_seq(Some(num_rows))?;
+        for row in self.rows()
+        {
+            seq.serialize_element(row)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Vec2d<T>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Vec2d<T>, D::Error>
+    {
+        let rows = Vec::<Vec<T>>::deserialize(deserializer)?;
+
+        Vec2d::from_rows(rows).map_err(|error| D::Error::custom(error.to_string()))
+    }
+}
+
+/// See `Vec2d::neighbors_of`
+#[derive(Clone, Debug)]
+pub struct Neighbors
+{
+    x: Horizontal,
+    y: Vertical,
+    width: Horizontal,
+    height: Vertical,
+    /// 0..9 going row by row through the 3x3 square around x,y
+    offset: usize,
+}
+
+impl Iterator for Neighbors
+{
+    type Item = (Horizontal, Vertical);
+
+    fn next(&mut self) -> Option<(Horizontal, Vertical)>
     {
-        match self.xy_vec.get_mut(y.0)
+        while self.offset < 9
         {
-            Some(row) => row.get_mut(x.0),
-            _ => None,
+            let offset = self.offset;
+            self.offset += 1;
+
+            if offset == 4
+            {
+                continue;
+            }
+
+            /* Shifted by one so the top left neighbor doesn't need to go below 0 */
+            let (x, y) = (self.x.0 + offset % 3, self.y.0 + offset / 3);
+            if x >= 1 && y >= 1 && x - 1 < self.width.0 && y - 1 < self.height.0
+            {
+                return Some((Horizontal(x - 1), Vertical(y - 1)));
+            }
         }
+
+        None
     }
+}
 
-    pub fn fill<F>(&mut self, item_func: F) -> Result<()>
-        where F: Fn() -> T
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use ::errors::Error;
+
+    fn sorted(neighbors: Neighbors) -> Vec<(usize, usize)>
     {
-        if !self.xy_vec.is_empty()
+        let mut positions: Vec<(usize, usize)> = neighbors.map(|(x, y)| (x.0, y.0)).collect();
+        positions.sort();
+        positions
+    }
+
+    #[test]
+    fn new_refuses_sizes_that_overflow_or_are_empty()
+    {
+        match Vec2d::<u8>::new(Horizontal(usize::MAX), Vertical(2))
         {
-            self.xy_vec.clear();
+            Err(Error(ErrorKind::Overflow(width, 2), _)) => assert_eq!(width, usize::MAX),
+            other => panic!("expected an overflow, got {:?}", other),
         }
 
-        for _ in 0..self.height.0
+        for &(width, height) in &[(0, 0), (0, 5), (5, 0)]
         {
-            let mut row = Vec::with_capacity(self.width.0);
-            for _ in 0..self.width.0
+            match Vec2d::<u8>::new(Horizontal(width), Vertical(height))
             {
-                row.push(item_func());
+                Err(Error(ErrorKind::EmptyField(..), _)) => {},
+                other => panic!("expected {}x{} to be refused, got {:?}", width, height, other),
             }
-            self.xy_vec.push(row);
         }
-        Ok(())
+
+        let grid = Vec2d::<u8>::new(Horizontal(3), Vertical(2)).unwrap();
+        assert_eq!((grid.width(), grid.height(), grid.len()), (Horizontal(3), Vertical(2), 6));
     }
 
-    pub fn as_vec_vec(&self) -> &Vec<Vec<T>>
+    #[test]
+    fn every_constructor_refuses_empty_sizes()
     {
-        &self.xy_vec
+        let is_empty_field = |result: Result<Vec2d<u8>>| matches!(result, Err(Error(ErrorKind::EmptyField(..), _)));
+
+        for &(width, height) in &[(0, 0), (0, 5), (5, 0)]
+        {
+            assert!(is_empty_field(Vec2d::from_fn(Horizontal(width), Vertical(height), |_, _| 1)), "from_fn {}x{}", width, height);
+            assert!(is_empty_field(Vec2d::from_vec(Horizontal(width), Vertical(height), Vec::new())), "from_vec {}x{}", width, height);
+        }
+        assert!(is_empty_field(Vec2d::from_rows(Vec::new())));
+        assert!(is_empty_field(Vec2d::from_rows(vec![Vec::new(), Vec::new()])));
+        assert!(::serde_json::from_str::<Vec2d<u8>>("[]").is_err());
+
+        assert_eq!(Vec2d::from_rows(vec![vec![1u8, 2], vec![3, 4]]).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn neighbors_stay_inside_at_the_corners()
+    {
+        let grid = Vec2d::<u8>::new(Horizontal(4), Vertical(3)).unwrap();
+
+        assert_eq!(sorted(grid.neighbors_of(&Horizontal(0), &Vertical(0))), vec![(0, 1), (1, 0), (1, 1)]);
+        assert_eq!(sorted(grid.neighbors_of(&Horizontal(3), &Vertical(0))), vec![(2, 0), (2, 1), (3, 1)]);
+        assert_eq!(sorted(grid.neighbors_of(&Horizontal(0), &Vertical(2))), vec![(0, 1), (1, 1), (1, 2)]);
+        assert_eq!(sorted(grid.neighbors_of(&Horizontal(3), &Vertical(2))), vec![(2, 1), (2, 2), (3, 1)]);
+        assert_eq!(grid.neighbors_of(&Horizontal(1), &Vertical(1)).count(), 8);
+
+        let single = Vec2d::<u8>::new(Horizontal(1), Vertical(1)).unwrap();
+        assert_eq!(single.neighbors_of(&Horizontal(0), &Vertical(0)).count(), 0);
     }
-}
\ No newline at end of file
+}
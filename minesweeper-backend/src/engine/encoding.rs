@@ -0,0 +1,127 @@
+use ::errors::Result;
+
+use ::common::{Horizontal, Vertical};
+use ::common::vec2d::Vec2d;
+
+use super::minesweeper::Tile;
+use super::packed::PackedTile;
+
+use base64;
+
+use std::convert::TryFrom;
+
+
+/// Bumped whenever the layout of the header or of a tile byte, which is `PackedTile`'s, changes
+pub const COMPACT_VERSION: u8 = 1;
+
+/// version + width + height
+const HEADER_LEN: usize = 1 + 4 + 4;
+
+/// Packs a tile into one byte, see `PackedTile`
+pub fn encode_tile(tile: &Tile) -> u8
+{
+    PackedTile::from_tile(tile).bits()
+}
+
+pub fn decode_tile(byte: u8) -> Tile
+{
+    PackedTile::from_bits(byte).to_tile()
+}
+
+/// Base64 of a header (version, then big-endian u32 width and height) followed by one byte per tile, row by row
+pub fn encode_field(tiles: &Vec2d<PackedTile>) -> Result<String>
+{
+    let width = tiles.width().0;
+    let height = tiles.height().0;
+
+    if u32::try_from(width).is_err() || u32::try_from(height).is_err()
+    {
+        bail!("width:{} height:{} doesn't fit in the compact header", width, height);
+    }
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + width * height);
+    bytes.push(COMPACT_VERSION);
+    push_u32(&mut bytes, width as u32);
+    push_u32(&mut bytes, height as u32);
+
+    bytes.extend(tiles.iter().map(PackedTile::bits));
+
+    Ok(base64::encode(&bytes))
+}
+
+pub fn decode_field(encoded: &str) -> Result<Vec2d<PackedTile>>
+{
+    let bytes = base64::decode(encoded)?;
+
+    if bytes.len() < HEADER_LEN
+    {
+        bail!("{} bytes is too short for the compact header", bytes.len());
+    }
+    if bytes[0] != COMPACT_VERSION
+    {
+        bail!("Compact field version {} isn't supported, expected {}", bytes[0], COMPACT_VERSION);
+    }
+
+    let width = read_u32(&bytes[1..5]) as usize;
+    let height = read_u32(&bytes[5..9]) as usize;
+    let tiles = bytes[HEADER_LEN..].iter().map(|byte| PackedTile::from_bits(*byte)).collect();
+
+    Vec2d::from_vec(Horizontal(width), Vertical(height), tiles)
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32)
+{
+    bytes.extend_from_slice(&[(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]);
+}
+
+fn read_u32(bytes: &[u8]) -> u32
+{
+    (bytes[0] as u32) << 24 | (bytes[1] as u32) << 16 | (bytes[2] as u32) << 8 | bytes[3] as u32
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use ::engine::minesweeper::{Action, Minesweeper};
+
+    fn played_field() -> Vec2d<PackedTile>
+    {
+        let mut game = Minesweeper::new(Horizontal(17), Vertical(5), 12).unwrap();
+        game.set_seed(Some(3));
+        game.handle_action(Action::Expose { x_position: 8, y_position: 2 }).unwrap();
+
+        let mut tiles = game.get_tiles().clone();
+        tiles[(Horizontal(0), Vertical(0))].set_has_flag(true);
+        tiles
+    }
+
+    #[test]
+    fn a_field_comes_back_unchanged()
+    {
+        let tiles = played_field();
+
+        assert_eq!(decode_field(&encode_field(&tiles).unwrap()).unwrap(), tiles);
+        for tile in tiles.iter()
+        {
+            assert_eq!(PackedTile::from_tile(&decode_tile(encode_tile(&tile.to_tile()))), *tile);
+        }
+    }
+
+    #[test]
+    fn headers_that_dont_match_the_tiles_are_refused()
+    {
+        let mut bytes = base64::decode(&encode_field(&played_field()).unwrap()).unwrap();
+
+        /* One row too many for the tiles that follow */
+        bytes[8] += 1;
+        assert!(decode_field(&base64::encode(&bytes)).is_err());
+
+        bytes[8] -= 1;
+        bytes[0] = COMPACT_VERSION + 1;
+        assert!(decode_field(&base64::encode(&bytes)).is_err());
+
+        assert!(decode_field(&base64::encode(&bytes[..HEADER_LEN - 1])).is_err());
+        assert!(decode_field("not base64!").is_err());
+    }
+}
@@ -0,0 +1,459 @@
+use ::errors::{ErrorKind, Result};
+
+use ::common::{self, WorldHorizontal, WorldVertical};
+use ::common::chunked::{ChunkPosition, ChunkedGrid};
+
+use super::minesweeper::{self, State, Tile};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use std::collections::VecDeque;
+
+
+/// Width & height of each generated chunk
+pub const CHUNK_SIZE: usize = 32;
+
+pub const DEFAULT_DENSITY: f64 = 0.18;
+/// Below this an opening can grow forever since empty tiles would connect across the whole plane
+pub const MIN_DENSITY: f64 = 0.15;
+pub const MAX_DENSITY: f64 = 0.5;
+
+/// Coordinates go from -WORLD_LIMIT to WORLD_LIMIT each way, far enough to never be reached by playing
+/// and close enough to 0 that chunk and neighbor arithmetic can't overflow an i64
+pub const WORLD_LIMIT: i64 = 1 << 48;
+
+fn is_in_world(position: i64) -> bool
+{
+    (-WORLD_LIMIT..=WORLD_LIMIT).contains(&position)
+}
+
+fn check_in_world(x: i64, y: i64) -> Result<()>
+{
+    if !is_in_world(x) || !is_in_world(y)
+    {
+        bail!(ErrorKind::OutOfWorld(x, y, WORLD_LIMIT));
+    }
+
+    Ok(())
+}
+
+/// The viewport's corner has to be in the world and its size is limited the same as a field's viewport
+pub fn check_viewport(x: i64, y: i64, width: usize, height: usize) -> Result<()>
+{
+    check_in_world(x, y)?;
+    minesweeper::check_viewport_size(width, height)
+}
+
+impl WorldHorizontal
+{
+    /// Itself and the tiles either side of it that are still in the world
+    fn neighbors(&self) -> impl Iterator<Item = WorldHorizontal>
+    {
+        (self.0 - 1..self.0 + 2).filter(|&x| is_in_world(x)).map(WorldHorizontal)
+    }
+}
+
+impl WorldVertical
+{
+    fn neighbors(&self) -> impl Iterator<Item = WorldVertical>
+    {
+        (self.0 - 1..self.0 + 2).filter(|&y| is_in_world(y)).map(WorldVertical)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "_type")]
+pub enum EndlessAction
+{
+    Flag { x: i64, y: i64 },
+    Unflag { x: i64, y: i64 },
+    Expose { x: i64, y: i64 },
+    /// `None` picks a random seed and the default density
+    Start { seed: Option<u64>, density: Option<f64> },
+    /// See `check_viewport` for what's allowed
+    SetViewport { x: i64, y: i64, w: usize, h: usize },
+}
+
+/// Minesweeper on an unbounded plane, chunks are generated from the seed as the revealed area reaches them
+/// so the same seed always gives the same world no matter the order it's explored in
+#[derive(Debug)]
+pub struct Endless
+{
+    seed: u64,
+    density: f64,
+
+    /// The true tiles, `num_bombs_around` is only filled in once a tile is shown
+    tiles: ChunkedGrid<Tile>,
+
+    /// Safe tiles that have been shown, the player's score
+    num_cleared: usize,
+    num_flags: usize,
+    state: State,
+
+    changed_tiles: Vec<(WorldHorizontal, WorldVertical)>,
+}
+
+impl Endless
+{
+    /// The tiles around 0,0 are never bombs and are shown straight away so there's always somewhere to start
+    pub fn new(seed: Option<u64>, density: Option<f64>) -> Result<Endless>
+    {
+        let density = density.unwrap_or(DEFAULT_DENSITY);
+        /* Also refuses NaN */
+        if !(MIN_DENSITY..=MAX_DENSITY).contains(&density)
+        {
+            bail!(ErrorKind::InvalidDensity(density, MIN_DENSITY, MAX_DENSITY));
+        }
+
+        let mut endless = Endless {
+            seed: seed.unwrap_or_else(common::random_seed),
+            density,
+
+            tiles: ChunkedGrid::new(CHUNK_SIZE),
+
+            num_cleared: 0,
+            num_flags: 0,
+            state: State::InProgress,
+
+            changed_tiles: Vec::new(),
+        };
+
+        endless.expose_tile(&WorldHorizontal(0), &WorldVertical(0))?;
+
+        Ok(endless)
+    }
+
+    fn chunk_seed(&self, chunk: &ChunkPosition) -> u64
+    {
+        self.seed
+            ^ (chunk.x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (chunk.y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+    }
+
+    fn generate_chunk(&mut self, chunk: ChunkPosition) -> Result<()>
+    {
+        if self.tiles.has_chunk(&chunk)
+        {
+            return Ok(());
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.chunk_seed(&chunk));
+        let (origin_x, origin_y) = self.tiles.origin_of(&chunk);
+
+        let mut tiles = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE);
+        for y in 0..CHUNK_SIZE as i64
+        {
+            for x in 0..CHUNK_SIZE as i64
+            {
+                /* Always draw so the rest of the chunk doesn't depend on where the protected area is */
+                let is_bomb = rng.gen_bool(self.density);
+                let is_protected = (origin_x.0 + x).abs() <= 1 && (origin_y.0 + y).abs() <= 1;
+
+                tiles.push(Tile {
+                    num_bombs_around: 0,
+                    has_flag: false,
+                    is_bomb: is_bomb && !is_protected,
+                    is_shown: false,
+                    was_clicked: false,
+                });
+            }
+        }
+
+        trace!("Generated chunk x:{}, y:{}, {} chunks in total", chunk.x, chunk.y, self.tiles.num_chunks() + 1);
+        self.tiles.insert_chunk(chunk, tiles)
+    }
+
+    /// Generates the chunk holding x,y and every chunk touching it so neighbors can always be counted
+    fn generate_chunks_around(&mut self, x: &WorldHorizontal, y: &WorldVertical) -> Result<()>
+    {
+        let center = self.tiles.chunk_of(x, y);
+
+        for chunk_y in center.y - 1..center.y + 2
+        {
+            for chunk_x in center.x - 1..center.x + 2
+            {
+                self.generate_chunk(ChunkPosition { x: chunk_x, y: chunk_y })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn count_bombs_around(&self, x: &WorldHorizontal, y: &WorldVertical) -> usize
+    {
+        let mut count = 0;
+
+        for neighbor_y in y.neighbors()
+        {
+            for neighbor_x in x.neighbors()
+            {
+                if let Some(tile) = self.tiles.get(&neighbor_x, &neighbor_y)
+                {
+                    if tile.is_bomb
+                    {
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Only exposing grows the world, so flags can only go on chunks an expose has already generated
+    fn flag_tile(&mut self, x: &WorldHorizontal, y: &WorldVertical) -> Result<()>
+    {
+        match self.tiles.get_mut(x, y)
+        {
+            Some(tile) =>
+            {
+                if tile.has_flag
+                {
+                    bail!(ErrorKind::AlreadyFlagged(x.0, y.0));
+                }
+                if tile.is_shown
+                {
+                    bail!(ErrorKind::AlreadyShown(x.0, y.0));
+                }
+
+                tile.has_flag = true;
+            },
+            None => bail!(ErrorKind::Unexplored(x.0, y.0)),
+        };
+
+        self.num_flags += 1;
+        self.changed_tiles.push((*x, *y));
+
+        Ok(())
+    }
+
+    fn unflag_tile(&mut self, x: &WorldHorizontal, y: &WorldVertical) -> Result<()>
+    {
+        match self.tiles.get_mut(x, y)
+        {
+            Some(tile) =>
+            {
+                if !tile.has_flag
+                {
+                    bail!(ErrorKind::NotFlagged(x.0, y.0));
+                }
+
+                tile.has_flag = false;
+            },
+            None => bail!(ErrorKind::NotFlagged(x.0, y.0)),
+        };
+
+        self.num_flags -= 1;
+        self.changed_tiles.push((*x, *y));
+
+        Ok(())
+    }
+
+    fn expose_tile(&mut self, x: &WorldHorizontal, y: &WorldVertical) -> Result<()>
+    {
+        if let Some(tile) = self.tiles.get(x, y)
+        {
+            if tile.has_flag
+            {
+                bail!(ErrorKind::Flagged(x.0, y.0));
+            }
+            if tile.is_shown
+            {
+                bail!(ErrorKind::AlreadyShown(x.0, y.0));
+            }
+        }
+
+        let mut tiles_to_click = VecDeque::new();
+        tiles_to_click.push_back((*x, *y));
+
+        while let Some((x, y)) = tiles_to_click.pop_front()
+        {
+            self.generate_chunks_around(&x, &y)?;
+            let num_bombs_around = self.count_bombs_around(&x, &y);
+
+            let is_bomb = match self.tiles.get_mut(&x, &y)
+            {
+                Some(tile) =>
+                {
+                    if tile.is_shown || tile.has_flag
+                    {
+                        continue;
+                    }
+
+                    tile.is_shown = true;
+                    tile.was_clicked = true;
+                    tile.num_bombs_around = num_bombs_around;
+
+                    tile.is_bomb
+                },
+                None => bail!("Tile x:{}, y:{} was not generated", x.0, y.0),
+            };
+
+            self.changed_tiles.push((x, y));
+
+            if is_bomb
+            {
+                self.state = State::Loss;
+                break;
+            }
+
+            self.num_cleared += 1;
+
+            if num_bombs_around == 0
+            {
+                for neighbor_y in y.neighbors()
+                {
+                    for neighbor_x in x.neighbors()
+                    {
+                        tiles_to_click.push_back((neighbor_x, neighbor_y));
+                    }
+                }
+            }
+        }
+
+        if self.state == State::Loss
+        {
+            self.on_loss();
+        }
+
+        Ok(())
+    }
+
+    /// Shows every bomb in the chunks that have been generated
+    fn on_loss(&mut self)
+    {
+        let bombs: Vec<(WorldHorizontal, WorldVertical)> = self.tiles.iter()
+            .filter(|(_, _, tile)| tile.is_bomb && !tile.is_shown)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+
+        for (x, y) in bombs
+        {
+            if let Some(tile) = self.tiles.get_mut(&x, &y)
+            {
+                tile.is_shown = true;
+            }
+            self.changed_tiles.push((x, y));
+        }
+    }
+
+    pub fn handle_action(&mut self, action: EndlessAction) -> Result<&State>
+    {
+        self.changed_tiles.clear();
+
+        if self.state != State::InProgress
+        {
+            bail!(ErrorKind::NotAllowed(format!("{:?}", action), format!("{:?}", self.state)));
+        }
+
+        match action
+        {
+            EndlessAction::Flag { x, y } | EndlessAction::Unflag { x, y } | EndlessAction::Expose { x, y } => check_in_world(x, y)?,
+            _ => {},
+        }
+
+        match action
+        {
+            EndlessAction::Flag { x, y } => self.flag_tile(&WorldHorizontal(x), &WorldVertical(y))?,
+            EndlessAction::Unflag { x, y } => self.unflag_tile(&WorldHorizontal(x), &WorldVertical(y))?,
+            EndlessAction::Expose { x, y } => self.expose_tile(&WorldHorizontal(x), &WorldVertical(y))?,
+            action => bail!(ErrorKind::NotAllowed(format!("{:?}", action), format!("{:?}", self.state))),
+        };
+
+        Ok(&self.state)
+    }
+
+    /// What the player is allowed to see of a tile, tiles that were never generated are hidden
+    fn external_tile(&self, x: &WorldHorizontal, y: &WorldVertical) -> Tile
+    {
+        match self.tiles.get(x, y)
+        {
+            Some(tile) => Tile {
+                num_bombs_around: if tile.was_clicked { tile.num_bombs_around } else { 0 },
+                has_flag: tile.has_flag,
+                is_bomb: tile.is_shown && tile.is_bomb,
+                is_shown: tile.is_shown,
+                was_clicked: tile.was_clicked,
+            },
+            None => Tile {
+                num_bombs_around: 0,
+                has_flag: false,
+                is_bomb: false,
+                is_shown: false,
+                was_clicked: false,
+            },
+        }
+    }
+
+    /// Rows of the rectangle starting at x,y, nothing is generated just by looking at it.
+    /// Anything past the edge of the world is hidden
+    pub fn get_tiles_in(&self, x: &WorldHorizontal, y: &WorldVertical, width: usize, height: usize) -> Vec<Vec<Tile>>
+    {
+        (0..height as i64)
+            .map(|dy| {
+                (0..width as i64)
+                    .map(|dx| match (x.0.checked_add(dx), y.0.checked_add(dy))
+                    {
+                        (Some(tile_x), Some(tile_y)) => self.external_tile(&WorldHorizontal(tile_x), &WorldVertical(tile_y)),
+                        _ => Tile::default(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The tiles that changed during the last `handle_action` as x, y, tile
+    pub fn get_changed_tiles(&self) -> Vec<(i64, i64, Tile)>
+    {
+        self.changed_tiles.iter()
+            .map(|(x, y)| (x.0, y.0, self.external_tile(x, y)))
+            .collect()
+    }
+
+    pub fn get_state(&self) -> &State
+    {
+        &self.state
+    }
+
+    pub fn get_score(&self) -> usize
+    {
+        self.num_cleared
+    }
+
+    pub fn get_num_flags(&self) -> usize
+    {
+        self.num_flags
+    }
+
+    pub fn get_seed(&self) -> u64
+    {
+        self.seed
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use ::errors::Error;
+
+    #[test]
+    fn flags_only_go_where_exposing_has_reached()
+    {
+        let mut endless = Endless::new(Some(7), None).unwrap();
+        let num_chunks = endless.tiles.num_chunks();
+
+        match endless.handle_action(EndlessAction::Flag { x: 1_000_000, y: -1_000_000 })
+        {
+            Err(Error(ErrorKind::Unexplored(1_000_000, -1_000_000), _)) => {},
+            other => panic!("expected the flag to be refused, got {:?}", other),
+        }
+        assert_eq!(endless.tiles.num_chunks(), num_chunks);
+        assert_eq!(endless.get_num_flags(), 0);
+
+        endless.handle_action(EndlessAction::Flag { x: 5, y: 5 }).unwrap();
+        assert_eq!(endless.get_num_flags(), 1);
+        assert_eq!(endless.tiles.num_chunks(), num_chunks);
+    }
+}
@@ -1,61 +1,42 @@
 
-use ::errors::Result;
+use ::errors::{ErrorKind, Result};
 
-use ::common::{Horizontal, Vertical};
+use ::common::{self, Horizontal, Vertical};
 use ::common::vec2d::Vec2d;
+use ::common::bitset::BitSet;
+use ::stats::ExportFormat;
+use ::settings::SettingsUpdate;
+use super::endless::EndlessAction;
+use super::packed::PackedTile;
 
-use rand;
-use rand::Rng;
-use rand::distributions::Uniform;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 
-use std::collections::VecDeque;
+/* The clock panics on wasm32-unknown-unknown */
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
 
 impl Vertical
 {
-    fn neighbors(&self) -> Vec<Vertical>
+    /// Whether `other` is within one tile of this one, including being the same tile
+    fn is_adjacent(&self, other: &Vertical) -> bool
     {
-        let mut neighbors = Vec::with_capacity(3);
-
-        if let Some(below) = self.0.checked_sub(1)
-        {
-            neighbors.push(Vertical(below))
-        };
-
-        neighbors.push(Vertical(self.0));
-
-        if let Some(above) = self.0.checked_add(1)
-        {
-            neighbors.push(Vertical(above))
-        };
-
-        neighbors
+        other.0 + 1 >= self.0 && other.0 <= self.0 + 1
     }
 }
 
 impl Horizontal
 {
-    fn neighbors(&self) -> Vec<Horizontal>
+    /// Whether `other` is within one tile of this one, including being the same tile
+    fn is_adjacent(&self, other: &Horizontal) -> bool
     {
-        let mut neighbors = Vec::with_capacity(3);
-
-        if let Some(left) = self.0.checked_sub(1)
-        {
-            neighbors.push(Horizontal(left))
-        };
-
-        neighbors.push(Horizontal(self.0));
-
-        if let Some(right) = self.0.checked_add(1)
-        {
-            neighbors.push(Horizontal(right))
-        };
-
-        neighbors
+        other.0 + 1 >= self.0 && other.0 <= self.0 + 1
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
 pub struct Tile
 {
     pub num_bombs_around: usize,
@@ -65,31 +46,6 @@ pub struct Tile
     pub was_clicked: bool,
 }
 
-impl Tile
-{
-    fn default() -> Tile 
-    {
-        Tile {
-            num_bombs_around: 0,
-            has_flag: false,
-            is_bomb: false,
-            is_shown: false,
-            was_clicked: false,
-        }
-    }
-
-    fn copy_from(&mut self, source: &Tile)
-    {
-
-        self.num_bombs_around = source.num_bombs_around;
-        self.has_flag = source.has_flag;
-        self.is_bomb = source.is_bomb;
-        self.is_shown = source.is_shown;
-        self.was_clicked = source.was_clicked;
-    }
-}
-
-
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(tag = "_type")]
 pub enum Action
@@ -97,10 +53,95 @@ pub enum Action
     Flag { x_position: usize, y_position: usize },
     Unflag { x_position: usize, y_position: usize },
     Expose { x_position: usize, y_position: usize },
+    /// Exposes every neighbor of a shown number that isn't flagged, once it has that many flags around it
+    Chord { x_position: usize, y_position: usize },
     Start { width: usize, height: usize, num_bombs: usize },
+    RequestStats,
+    /// Writes the stats to a file in the data directory, `file_name` can't name any other directory
+    ExportStats { format: ExportFormat, file_name: String },
+    UpdateSettings { settings: SettingsUpdate },
+    /// Asks for the whole field to be sent again, for when the UI lost track of it
+    RequestField,
+    /// Only tiles inside this rectangle are sent to the UI until `ClearViewport`, at most `MAX_VIEWPORT_SIZE` each way
+    SetViewport { x: usize, y: usize, w: usize, h: usize },
+    ClearViewport,
+    /// Actions for the endless game, which is played alongside this one
+    Endless { action: EndlessAction },
+    /// Multiplayer, asks the host for a player id that every later move from this client is attributed to
+    Join { name: String },
+    /// Multiplayer, gives up the player id, the score still counts
+    Leave,
+    /// Race, starts the race for everyone who has joined so far
+    StartRace,
+    /// Co-op, where this player is pointing, shown to everyone else
+    MoveCursor { x_position: usize, y_position: usize },
+    /// Co-op, a flag this player is suggesting that everyone sees but that isn't placed yet
+    MarkPending { x_position: usize, y_position: usize },
+    UnmarkPending { x_position: usize, y_position: usize },
     Quit,
 }
 
+/// The widest and tallest a viewport may be, enough to cover a 4K screen with 16 pixel tiles.
+/// Every tile in a viewport is serialized for the UI so a size from the UI has to be bounded
+pub const MAX_VIEWPORT_SIZE: usize = 256;
+
+pub fn check_viewport_size(width: usize, height: usize) -> Result<()>
+{
+    if width > MAX_VIEWPORT_SIZE || height > MAX_VIEWPORT_SIZE
+    {
+        bail!(ErrorKind::ViewportTooBig(width, height, MAX_VIEWPORT_SIZE));
+    }
+
+    Ok(())
+}
+
+/// Which tiles are kept free of bombs when the field is populated on the first move
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FirstClickPolicy
+{
+    /// The first move may land on a bomb
+    Unprotected,
+    /// Only the tile that was first moved on is safe
+    SafeTile,
+    /// The first moved on tile and all of its neighbors are safe so the first move always opens an area
+    SafeOpening,
+}
+
+impl FirstClickPolicy
+{
+    pub fn from_name(name: &str) -> Option<FirstClickPolicy>
+    {
+        match name.to_lowercase().as_str()
+        {
+            "unprotected" => Some(FirstClickPolicy::Unprotected),
+            "safe-tile" => Some(FirstClickPolicy::SafeTile),
+            "safe-opening" => Some(FirstClickPolicy::SafeOpening),
+            _ => None,
+        }
+    }
+}
+
+/// Board-wide counts for UIs that only have part of the field
+#[derive(Serialize, Debug)]
+pub struct FieldSummary
+{
+    pub width: usize,
+    pub height: usize,
+    pub num_bombs: usize,
+    pub num_flags: usize,
+    pub num_shown: usize,
+}
+
+/// 3BV, the fewest clicks that clear the field without flags: one for every opening
+/// and one for every number that isn't on the edge of an opening
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Bbbv
+{
+    pub total: usize,
+    /// The part of `total` the clicks so far have already cleared
+    pub solved: usize,
+}
+
 #[derive(PartialEq, Eq, Debug)]
 pub enum State
 {
@@ -114,9 +155,9 @@ pub enum State
 pub struct Minesweeper 
 {
     /// Starts initially empty so that _after_ the first move it can be populated
-    internal_field: Vec2d<Tile>,
+    internal_field: Vec2d<PackedTile>,
     /// External representation of the field where `is_bomb` is always false until a bomb is clicked
-    external_field: Vec2d<Tile>,
+    external_field: Vec2d<PackedTile>,
 
     width: Horizontal,
     height: Vertical,
@@ -124,7 +165,16 @@ pub struct Minesweeper
     num_bombs: usize,
     num_flags: usize,
     num_correct_flags: usize,
+    /// Number of tiles in `external_field` that are shown, kept as a count so huge fields don't need scanning
+    num_shown: usize,
     state: State,
+
+    first_click_policy: FirstClickPolicy,
+    /// Fixes where the bombs go, `None` picks them at random every game
+    seed: Option<u64>,
+
+    /// Every tile in `external_field` that changed during the last `handle_action`, in the order they changed
+    changed_tiles: Vec<(Horizontal, Vertical)>,
 }
 
 impl Minesweeper
@@ -135,8 +185,8 @@ impl Minesweeper
         let temp_vertical = Vertical(0);
 
         let mut minesweeper = Minesweeper {
-            internal_field: Vec2d::new(temp_horizontal, temp_vertical),
-            external_field: Vec2d::new(temp_horizontal, temp_vertical), 
+            internal_field: Vec2d::default(),
+            external_field: Vec2d::default(),
 
             width: temp_horizontal,
             height: temp_vertical,
@@ -144,7 +194,13 @@ impl Minesweeper
             num_bombs: 0,
             num_flags: 0,
             num_correct_flags: 0,
+            num_shown: 0,
             state: State::New,
+
+            first_click_policy: FirstClickPolicy::SafeTile,
+            seed: None,
+
+            changed_tiles: Vec::new(),
         };
 
         minesweeper.resize(width, height, num_bombs)?;
@@ -154,100 +210,97 @@ impl Minesweeper
 
     pub fn resize(&mut self, width: Horizontal, height: Vertical, num_bombs: usize) -> Result<()>
     {
-        self.internal_field = Vec2d::new(width, height);
-        self.external_field = Vec2d::new(width, height); 
-
-        self.width = width;
-        self.height = height;
-
-        self.num_bombs = match width.0.checked_mul(height.0)
+        /* Checked before anything is changed so a bad resize leaves the current game alone */
+        let num_bombs = match width.0.checked_mul(height.0)
         {
-            None => bail!("width:{} * height:{} overflowed", width.0, height.0),
+            None => bail!(ErrorKind::Overflow(width.0, height.0)),
+            Some(0) => bail!(ErrorKind::EmptyField(width.0, height.0)),
             Some(width_height) => match num_bombs
             {
-                0 => bail!("Need at least one bomb"),
-                _ if width_height - 1 < num_bombs => bail!("{} tiles doesn't leave room for initial move + {} bombs", width_height, num_bombs),
+                0 => bail!(ErrorKind::NoBombs),
+                _ if width_height - 1 < num_bombs => bail!(ErrorKind::TooManyBombs(width_height, num_bombs)),
                 _ => num_bombs,
             }
         };
 
+        self.internal_field = Vec2d::new(width, height)?;
+        self.external_field = Vec2d::new(width, height)?;
+
+        self.width = width;
+        self.height = height;
+
+        self.num_bombs = num_bombs;
         self.num_flags = 0;
         self.num_correct_flags = 0;
+        self.num_shown = 0;
         self.state = State::New;
-
-        self.external_field.fill(|| Tile::default())?;
+        self.changed_tiles.clear();
 
         Ok(())
     }
 
     fn initialize_internal_field(&mut self, x: &Horizontal, y: &Vertical) -> Result<()>
     {
-        self.internal_field.fill(|| Tile::default())?;
+        self.internal_field = Vec2d::new(self.width, self.height)?;
 
-        /* Place bombs not in the clicked x,y position */
-        let mut bombs_placed = 0;
-        let mut horizontal_rng = rand::thread_rng();
-        let horizontal_range = Uniform::from(0..self.width.0);
-        let mut horizontal_iter = horizontal_rng.sample_iter(&horizontal_range);
+        let policy = match self.first_click_policy
+        {
+            FirstClickPolicy::SafeOpening if self.width.0 * self.height.0 - self.num_bombs < 9 =>
+            {
+                warn!("Not enough room to keep an opening around the first move, only keeping x:{}, y:{} safe", x.0, y.0);
+                FirstClickPolicy::SafeTile
+            },
+            policy => policy,
+        };
 
-        let mut vertical_rng = rand::thread_rng();
-        let vertical_range = Uniform::from(0..self.height.0);
-        let mut vertical_iter = vertical_rng.sample_iter(&vertical_range);
+        #[cfg(not(target_arch = "wasm32"))]
+        let started = Instant::now();
 
-        while bombs_placed < self.num_bombs 
-        {
-            match (horizontal_iter.next(), vertical_iter.next())
+        /* Every tile outside of the protected area around the clicked x,y position, as its row by row index */
+        let width = self.width.0;
+        let mut candidates: Vec<usize> = self.internal_field.positions()
+            .filter(|(bomb_x, bomb_y)| match policy
             {
-                (Some(bomb_x), Some(bomb_y)) =>
-                {
-                    let bomb_x = Horizontal(bomb_x);
-                    let bomb_y = Vertical(bomb_y);
+                FirstClickPolicy::Unprotected => true,
+                FirstClickPolicy::SafeTile => x != bomb_x || y != bomb_y,
+                FirstClickPolicy::SafeOpening => !(x.is_adjacent(bomb_x) && y.is_adjacent(bomb_y)),
+            })
+            .map(|(bomb_x, bomb_y)| bomb_y.0 * width + bomb_x.0)
+            .collect();
+
+        if candidates.len() < self.num_bombs
+        {
+            bail!(ErrorKind::TooManyBombs(candidates.len(), self.num_bombs));
+        }
 
-                    if x != &bomb_x || y != &bomb_y
-                    {
-                        let was_bomb_placed = match self.internal_field.get_mut(&bomb_x, &bomb_y)
-                        {
-                            None => false,
-                            Some(tile) =>
-                            {
-                                if !tile.is_bomb
-                                {
-                                    bombs_placed+= 1;
-                                    tile.is_bomb = true;
-
-                                    true
-                                }else {
-                                    false
-                                }
-                            }
-                        };
-
-                        if was_bomb_placed
-                        {
-                            self.mutate_neighbors(&bomb_x, &bomb_y, |internal, _, _| { internal.num_bombs_around+=1 });
-                        }
-                    }
-                }
-                _ => bail!("Unable to get next random x,y for bomb placement"),
-            } 
+        /* Only the first `num_bombs` candidates get shuffled, so the cost doesn't depend on the density */
+        let mut rng = StdRng::seed_from_u64(self.seed.unwrap_or_else(common::random_seed));
+        let (bomb_indices, _) = candidates.partial_shuffle(&mut rng, self.num_bombs);
+
+        for index in bomb_indices.iter()
+        {
+            let bomb_x = Horizontal(index % width);
+            let bomb_y = Vertical(index / width);
+
+            self.internal_field[(bomb_x, bomb_y)].set_is_bomb(true);
+            self.mutate_neighbors(&bomb_x, &bomb_y, |internal, _, _| {
+                let num_bombs_around = internal.num_bombs_around();
+                internal.set_num_bombs_around(num_bombs_around + 1);
+            });
         }
 
+        #[cfg(not(target_arch = "wasm32"))]
+        trace!("Placed {} bombs on a {}x{} field in {:?}", self.num_bombs, self.width.0, self.height.0, started.elapsed());
+
         Ok(())
     }
 
     fn mutate_neighbors<F>(&mut self, x: &Horizontal, y: &Vertical, mut func: F )
-        where F: FnMut(&mut Tile, &mut Tile, (&Horizontal, &Vertical))
+        where F: FnMut(&mut PackedTile, &mut PackedTile, (&Horizontal, &Vertical))
     {
-        for neighbor_h in x.neighbors().iter()
+        for (neighbor_x, neighbor_y) in self.internal_field.neighbors_of(x, y)
         {
-            for neighbor_w in y.neighbors().iter()
-            {
-                if let (Some(internal_slot), Some(external_slot))
-                    = (self.internal_field.get_mut(neighbor_h, neighbor_w), self.external_field.get_mut(neighbor_h, neighbor_w))
-                {
-                    func(internal_slot, external_slot, (neighbor_h, neighbor_w));
-                }
-            }
+            func(&mut self.internal_field[(neighbor_x, neighbor_y)], &mut self.external_field[(neighbor_x, neighbor_y)], (&neighbor_x, &neighbor_y));
         }
     }
 
@@ -257,14 +310,15 @@ impl Minesweeper
         {
             (Some(internal_tile), Some(external_tile)) =>
             {
-                internal_tile.has_flag = true;
-                external_tile.has_flag = true;
+                internal_tile.set_has_flag(true);
+                external_tile.set_has_flag(true);
+                self.changed_tiles.push((*x, *y));
 
-                self.num_flags = self.num_flags + 1;
+                self.num_flags += 1;
 
-                if internal_tile.is_bomb
+                if internal_tile.is_bomb()
                 {
-                    self.num_correct_flags = self.num_correct_flags + 1;
+                    self.num_correct_flags += 1;
                 }
 
                 if self.num_flags == self.num_correct_flags && self.num_correct_flags == self.num_bombs
@@ -272,7 +326,7 @@ impl Minesweeper
                     self.state = State::Won;
                 }
             },
-            _ => bail!("Provided tile x:{}, y:{} was not in the field", x.0, y.0),
+            _ => bail!(ErrorKind::OutOfBounds(x.0, y.0, self.width.0, self.height.0)),
         };
 
         if self.state == State::Won
@@ -289,17 +343,18 @@ impl Minesweeper
         {
             (Some(internal_tile), Some(external_tile)) =>
             {
-                internal_tile.has_flag = false;
-                external_tile.has_flag = false;
+                internal_tile.set_has_flag(false);
+                external_tile.set_has_flag(false);
+                self.changed_tiles.push((*x, *y));
 
-                self.num_flags = self.num_flags - 1;
+                self.num_flags -= 1;
 
-                if internal_tile.is_bomb
+                if internal_tile.is_bomb()
                 {
-                    self.num_correct_flags = self.num_correct_flags - 1;
+                    self.num_correct_flags -= 1;
                 }
             },
-            _ => bail!("Provided tile x:{}, y:{} was not in the field", x.0, y.0),
+            _ => bail!(ErrorKind::OutOfBounds(x.0, y.0, self.width.0, self.height.0)),
         };
 
         Ok(())
@@ -307,155 +362,261 @@ impl Minesweeper
 
     fn on_loss(&mut self)
     {
-        for y in 0..self.height.0
+        for ((x, y, external_tile), internal_tile) in self.external_field.enumerate_mut().zip(self.internal_field.iter_mut())
         {
-            for x in 0..self.width.0
+            if internal_tile.is_bomb()
             {
-                let horizontal = Horizontal(x);
-                let vertical = Vertical(y);
+                if !external_tile.is_shown()
+                {
+                    self.num_shown += 1;
+                }
 
-                match (self.internal_field.get_mut(&horizontal, &vertical), self.external_field.get_mut(&horizontal, &vertical))
+                internal_tile.set_is_shown(true);
+                if *external_tile != *internal_tile
                 {
-                    (Some(internal_tile), Some(external_tile)) =>
-                    {
-                        if internal_tile.is_bomb
-                        {
-                            internal_tile.is_shown = true;
-                            external_tile.copy_from(internal_tile);
-                        }
-                    },
-                    _ => continue,
-                };
+                    *external_tile = *internal_tile;
+                    self.changed_tiles.push((x, y));
+                }
             }
         }
     }
 
     fn on_win(&mut self)
     {
-        for y in 0..self.height.0
+        for ((x, y, external_tile), internal_tile) in self.external_field.enumerate_mut().zip(self.internal_field.iter_mut())
         {
-            for x in 0..self.width.0
+            if !internal_tile.is_bomb()
             {
-                let horizontal = Horizontal(x);
-                let vertical = Vertical(y);
+                if !external_tile.is_shown()
+                {
+                    self.num_shown += 1;
+                }
 
-                match (self.internal_field.get_mut(&horizontal, &vertical), self.external_field.get_mut(&horizontal, &vertical))
+                internal_tile.set_is_shown(true);
+                internal_tile.set_was_clicked(true);
+                if *external_tile != *internal_tile
                 {
-                    (Some(internal_tile), Some(external_tile)) =>
-                    {
-                        if !internal_tile.is_bomb
-                        {
-                            internal_tile.is_shown = true;
-                            internal_tile.was_clicked = true;
-                            external_tile.copy_from(internal_tile);
-                        }
-                    },
-                    _ => continue,
-                };
+                    *external_tile = *internal_tile;
+                    self.changed_tiles.push((x, y));
+                }
             }
         }
     }
 
     fn expose_tile(&mut self, x: &Horizontal, y: &Vertical) -> Result<()>
     {
-        let mut tiles_to_click = VecDeque::with_capacity(1);
-        tiles_to_click.push_back((x.clone(), y.clone()));
+        if self.internal_field.get(x, y).is_none()
+        {
+            bail!(ErrorKind::OutOfBounds(x.0, y.0, self.width.0, self.height.0));
+        }
 
-        loop
+        let revealed = self.reveal_from(x, y);
+        self.changed_tiles.extend(revealed);
+
+        if self.internal_field[(*x, *y)].is_bomb()
         {
-            let (x,y) = match tiles_to_click.pop_front()
-            {
-                Some(xy) => xy,
-                None => break,
-            };
+            self.state = State::Loss;
+            self.on_loss();
+        }
+
+        Ok(())
+    }
+
+    /// Clicks x,y, which must be in the field, and shows its neighbors, spreading out from every tile with no bombs around it.
+    /// Returns the tiles that changed in the order they were revealed
+    fn reveal_from(&mut self, x: &Horizontal, y: &Vertical) -> Vec<(Horizontal, Vertical)>
+    {
+        let mut visited = BitSet::new(self.internal_field.len());
+        visited.insert(y.0 * self.width.0 + x.0);
+
+        let mut revealed = Vec::new();
 
-            match (self.internal_field.get_mut(&x, &y), self.external_field.get_mut(&x, &y))
+        let clicked = &mut self.internal_field[(*x, *y)];
+        clicked.set_is_shown(true);
+        clicked.set_was_clicked(true);
+        let clicked = *clicked;
+
+        if self.reveal_tile(x, y, clicked)
+        {
+            revealed.push((*x, *y));
+        }
+
+        if clicked.is_bomb()
+        {
+            return revealed;
+        }
+
+        self.reveal_neighbors(x, y, &mut visited, &mut revealed);
+
+        /* `revealed` doubles as the queue, everything after `next` still has to spread to its neighbors */
+        let mut next = 0;
+        while next < revealed.len()
+        {
+            let (next_x, next_y) = revealed[next];
+            next += 1;
+
+            let tile = self.internal_field[(next_x, next_y)];
+            if tile.num_bombs_around() == 0 && !tile.is_bomb()
             {
-                (Some(internal_tile), Some(external_tile)) =>
-                {
-                    internal_tile.is_shown = true;
-                    internal_tile.was_clicked = true;
-                    external_tile.copy_from(&internal_tile);
+                self.reveal_neighbors(&next_x, &next_y, &mut visited, &mut revealed);
+            }
+        }
 
-                    if internal_tile.is_bomb
-                    {
-                        self.state = State::Loss;
-                        break;
-                    }
-                },
-                _ => bail!("Provided tile x:{}, y:{} was not in the field", x.0, y.0),
-            };
+        revealed
+    }
 
-            self.mutate_neighbors(&x, &y, |internal, external, (neighbor_x, neighbor_y)| { 
+    fn reveal_neighbors(&mut self, x: &Horizontal, y: &Vertical, visited: &mut BitSet, revealed: &mut Vec<(Horizontal, Vertical)>)
+    {
+        for (neighbor_x, neighbor_y) in self.internal_field.neighbors_of(x, y)
+        {
+            if !visited.insert(neighbor_y.0 * self.width.0 + neighbor_x.0)
+            {
+                continue;
+            }
 
-                internal.is_shown = true;
-                external.is_shown = true;
-                external.num_bombs_around = internal.num_bombs_around;
+            let neighbor = &mut self.internal_field[(neighbor_x, neighbor_y)];
+            neighbor.set_is_shown(true);
+            if neighbor.num_bombs_around() == 0 && !neighbor.is_bomb()
+            {
+                neighbor.set_was_clicked(true);
+            }
+            /* Only a clicked bomb is shown as one */
+            let mut shown = *neighbor;
+            shown.set_is_bomb(false);
 
-                if internal.num_bombs_around == 0 && !internal.was_clicked
-                {
-                    internal.was_clicked = true;
-                    tiles_to_click.push_back((neighbor_x.clone(), neighbor_y.clone()));
-                }
-            });
-        };
+            /* Tiles that were already revealed by an earlier move don't change and have already spread */
+            if self.reveal_tile(&neighbor_x, &neighbor_y, shown)
+            {
+                revealed.push((neighbor_x, neighbor_y));
+            }
+        }
+    }
 
-        if self.state == State::Loss
+    /// Shows `shown` to the player at x,y, returns whether that changed anything
+    fn reveal_tile(&mut self, x: &Horizontal, y: &Vertical, shown: PackedTile) -> bool
+    {
+        let external = &mut self.external_field[(*x, *y)];
+        if shown == *external
         {
-            self.on_loss();
+            return false;
         }
 
-        Ok(())
+        if !external.is_shown()
+        {
+            self.num_shown += 1;
+        }
+        *external = shown;
+
+        true
     }
 
     pub fn handle_action(&mut self, action: Action) -> Result<&State>
     {
-        match self.state
+        self.changed_tiles.clear();
+
+        self.apply_action(action)?;
+
+        Ok(&self.state)
+    }
+
+    /// Checks everything `apply_action` relies on up front so a rejected action never leaves the game half changed.
+    /// Also lets sessions check a move before deciding how to apply it
+    pub fn validate_action(&self, action: &Action) -> Result<()>
+    {
+        let (x, y) = match (&self.state, action)
         {
-            State::New => 
+            (State::New, Action::Flag { x_position, y_position }) |
+            (State::New, Action::Expose { x_position, y_position }) |
+            (State::InProgress, Action::Flag { x_position, y_position }) |
+            (State::InProgress, Action::Unflag { x_position, y_position }) |
+            (State::InProgress, Action::Expose { x_position, y_position }) |
+            (State::InProgress, Action::Chord { x_position, y_position }) => (*x_position, *y_position),
+            _ => bail!(ErrorKind::NotAllowed(format!("{:?}", action), format!("{:?}", self.state))),
+        };
+
+        let tile = match self.external_field.get(&Horizontal(x), &Vertical(y))
+        {
+            Some(tile) => tile,
+            None => bail!(ErrorKind::OutOfBounds(x, y, self.width.0, self.height.0)),
+        };
+
+        let (x, y) = (x as i64, y as i64);
+        match action
+        {
+            Action::Flag { .. } if tile.has_flag() => bail!(ErrorKind::AlreadyFlagged(x, y)),
+            /* A shown tile that wasn't clicked only shows its number as a hint and can still be flagged or clicked */
+            Action::Flag { .. } if tile.was_clicked() => bail!(ErrorKind::AlreadyShown(x, y)),
+            Action::Unflag { .. } if !tile.has_flag() => bail!(ErrorKind::NotFlagged(x, y)),
+            Action::Expose { .. } if tile.has_flag() => bail!(ErrorKind::Flagged(x, y)),
+            Action::Expose { .. } if tile.was_clicked() => bail!(ErrorKind::AlreadyShown(x, y)),
+            Action::Chord { .. } if !tile.is_shown() || tile.num_bombs_around() == 0 => bail!(ErrorKind::NothingToChord(x, y)),
+            Action::Chord { x_position, y_position } =>
             {
-                match action
+                let tiles = &self.external_field;
+                let num_flags = tiles.neighbors_of(&Horizontal(*x_position), &Vertical(*y_position))
+                    .filter(|&position| tiles[position].has_flag())
+                    .count();
+
+                if num_flags != tile.num_bombs_around()
                 {
-                    Action::Flag { x_position, y_position } => 
-                    {
-                        self.initialize_internal_field(&Horizontal(x_position), &Vertical(y_position))?;
-                        self.flag_tile(&Horizontal(x_position), &Vertical(y_position))?;
+                    bail!(ErrorKind::WrongFlagCount(x, y, tile.num_bombs_around(), num_flags));
+                }
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
 
-                        self.state = State::InProgress;
-                    },
-                    Action::Expose { x_position, y_position } =>
-                    {
-                        self.initialize_internal_field(&Horizontal(x_position), &Vertical(y_position))?;
-                        self.expose_tile(&Horizontal(x_position), &Vertical(y_position))?;
+    /// The field is only populated by the first move so that move can be protected
+    fn start_if_new(&mut self, x: &Horizontal, y: &Vertical) -> Result<()>
+    {
+        if self.state == State::New
+        {
+            self.initialize_internal_field(x, y)?;
 
-                        self.state = State::InProgress;
-                    },
-                    _ => {},
-                }
+            /* Set before the move so that winning or losing on the first move isn't overwritten */
+            self.state = State::InProgress;
+        }
+
+        Ok(())
+    }
+
+    fn apply_action(&mut self, action: Action) -> Result<()>
+    {
+        self.validate_action(&action)?;
+
+        match action
+        {
+            Action::Flag { x_position, y_position } =>
+            {
+                self.start_if_new(&Horizontal(x_position), &Vertical(y_position))?;
+                self.flag_tile(&Horizontal(x_position), &Vertical(y_position))?;
+            },
+            Action::Unflag { x_position, y_position } =>
+            {
+                self.unflag_tile(&Horizontal(x_position), &Vertical(y_position))?;
+            },
+            Action::Expose { x_position, y_position } =>
+            {
+                self.start_if_new(&Horizontal(x_position), &Vertical(y_position))?;
+                self.expose_tile(&Horizontal(x_position), &Vertical(y_position))?;
             },
-            State::InProgress =>
+            Action::Chord { x_position, y_position } =>
             {
-                match action
+                for (neighbor_x, neighbor_y) in self.get_chord_targets(&Horizontal(x_position), &Vertical(y_position))
                 {
-                    Action::Unflag { x_position, y_position } => 
-                    { 
-                        self.unflag_tile(&Horizontal(x_position), &Vertical(y_position))?;
-                    },
-                    Action::Flag { x_position, y_position } => 
-                    { 
-                        self.flag_tile(&Horizontal(x_position), &Vertical(y_position))?;
-                    },
-                    Action::Expose { x_position, y_position } =>
+                    /* An earlier neighbor's flood fill may have already clicked this one, or a bomb may have ended the game */
+                    if self.state == State::InProgress && !self.external_field[(neighbor_x, neighbor_y)].was_clicked()
                     {
-                        self.expose_tile(&Horizontal(x_position), &Vertical(y_position))?;
-                    },
-                    _ => {},
+                        self.expose_tile(&neighbor_x, &neighbor_y)?;
+                    }
                 }
             },
+            /* Everything else was already rejected by `validate_action` */
             _ => {},
         };
 
-        Ok(&self.state)
+        Ok(())
     }
 
     pub fn get_state(&self) -> &State
@@ -463,9 +624,206 @@ impl Minesweeper
         &self.state
     }
 
-    pub fn get_tiles(&self) -> &Vec<Vec<Tile>>
+    pub fn get_tiles(&self) -> &Vec2d<PackedTile>
+    {
+        &self.external_field
+    }
+
+    /// Takes effect the next time the field is populated
+    pub fn set_first_click_policy(&mut self, policy: FirstClickPolicy)
+    {
+        self.first_click_policy = policy;
+    }
+
+    pub fn get_first_click_policy(&self) -> FirstClickPolicy
+    {
+        self.first_click_policy
+    }
+
+    /// Takes effect the next time the field is populated.
+    /// The same seed, size, bombs, first click policy and first move always give the same field
+    pub fn set_seed(&mut self, seed: Option<u64>)
+    {
+        self.seed = seed;
+    }
+
+    pub fn get_seed(&self) -> Option<u64>
+    {
+        self.seed
+    }
+
+    /// The tiles that changed during the last `handle_action` as x, y, tile
+    pub fn get_changed_tiles(&self) -> Vec<(usize, usize, PackedTile)>
+    {
+        self.changed_tiles.iter()
+            .filter_map(|(x, y)| self.external_field.get(x, y).map(|tile| (x.0, y.0, *tile)))
+            .collect()
+    }
+
+    /// Rows of the external field inside the rectangle, clipped to the edges of the field
+    pub fn get_tiles_in(&self, x: &Horizontal, y: &Vertical, width: &Horizontal, height: &Vertical) -> Vec<&[PackedTile]>
+    {
+        self.external_field.get_rect(x, y, width, height)
+    }
+
+    pub fn get_summary(&self) -> FieldSummary
+    {
+        FieldSummary {
+            width: self.width.0,
+            height: self.height.0,
+            num_bombs: self.num_bombs,
+            num_flags: self.num_flags,
+            num_shown: self.num_shown,
+        }
+    }
+
+    pub fn get_width(&self) -> Horizontal
+    {
+        self.width
+    }
+
+    pub fn get_height(&self) -> Vertical
     {
-        self.external_field.as_vec_vec()
+        self.height
+    }
+
+    pub fn get_num_bombs(&self) -> usize
+    {
+        self.num_bombs
+    }
+
+    /// `None` until the first move populates the field.
+    /// Only for hosts refereeing a game, the total gives away where the openings are
+    pub fn get_bbbv(&self) -> Option<Bbbv>
+    {
+        if self.state == State::New
+        {
+            return None;
+        }
+
+        let tiles = &self.internal_field;
+        let width = self.width.0;
+        let mut visited = BitSet::new(tiles.len());
+        let mut bbbv = Bbbv { total: 0, solved: 0 };
+
+        /* Each opening takes one click however big it is, which also clears the numbers around its edge */
+        for (x, y, tile) in tiles.enumerate()
+        {
+            if tile.is_bomb() || tile.num_bombs_around() != 0 || !visited.insert(y.0 * width + x.0)
+            {
+                continue;
+            }
+
+            let mut is_solved = false;
+            let mut queue = vec![(x, y)];
+            while let Some((next_x, next_y)) = queue.pop()
+            {
+                if tiles[(next_x, next_y)].num_bombs_around() != 0
+                {
+                    continue;
+                }
+
+                is_solved = is_solved || self.external_field[(next_x, next_y)].was_clicked();
+                for (neighbor_x, neighbor_y) in tiles.neighbors_of(&next_x, &next_y)
+                {
+                    if visited.insert(neighbor_y.0 * width + neighbor_x.0)
+                    {
+                        queue.push((neighbor_x, neighbor_y));
+                    }
+                }
+            }
+
+            bbbv.total += 1;
+            if is_solved
+            {
+                bbbv.solved += 1;
+            }
+        }
+
+        /* Every number left over takes a click of its own */
+        for (x, y, tile) in tiles.enumerate()
+        {
+            if !tile.is_bomb() && !visited.contains(y.0 * width + x.0)
+            {
+                bbbv.total += 1;
+                if self.external_field[(x, y)].was_clicked()
+                {
+                    bbbv.solved += 1;
+                }
+            }
+        }
+
+        Some(bbbv)
+    }
+
+    /// The neighbors of x,y that a chord would expose, the ones that are neither flagged nor clicked
+    pub fn get_chord_targets(&self, x: &Horizontal, y: &Vertical) -> Vec<(Horizontal, Vertical)>
+    {
+        let tiles = &self.external_field;
+
+        tiles.neighbors_of(x, y)
+            .filter(|&position| !tiles[position].has_flag() && !tiles[position].was_clicked())
+            .collect()
+    }
+
+    /// Whether there's a bomb under x,y, always false before the first move populates the field.
+    /// Only for hosts refereeing a game, never send this to a player
+    pub fn is_bomb_at(&self, x: &Horizontal, y: &Vertical) -> bool
+    {
+        self.internal_field.get(x, y).map(|tile| tile.is_bomb()).unwrap_or(false)
     }
 }
 
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn first_click(seed: u64, policy: FirstClickPolicy, x: usize, y: usize) -> Minesweeper
+    {
+        let mut game = Minesweeper::new(Horizontal(30), Vertical(16), 99).unwrap();
+        game.set_seed(Some(seed));
+        game.set_first_click_policy(policy);
+        game.handle_action(Action::Expose { x_position: x, y_position: y }).unwrap();
+
+        game
+    }
+
+    fn bombs(game: &Minesweeper) -> Vec<(Horizontal, Vertical)>
+    {
+        game.internal_field.positions().filter(|(x, y)| game.is_bomb_at(x, y)).collect()
+    }
+
+    #[test]
+    fn same_seed_and_first_click_give_the_same_bombs()
+    {
+        for &policy in &[FirstClickPolicy::Unprotected, FirstClickPolicy::SafeTile, FirstClickPolicy::SafeOpening]
+        {
+            let first = first_click(42, policy, 10, 7);
+            let second = first_click(42, policy, 10, 7);
+
+            assert_eq!(bombs(&first).len(), 99);
+            assert_eq!(bombs(&first), bombs(&second));
+            assert_eq!(first.get_tiles(), second.get_tiles());
+        }
+    }
+
+    #[test]
+    fn safe_opening_keeps_the_square_around_the_first_click_free()
+    {
+        for seed in 0..50
+        {
+            for &(x, y) in &[(10, 7), (0, 0), (29, 15)]
+            {
+                let game = first_click(seed, FirstClickPolicy::SafeOpening, x, y);
+
+                assert!(!game.is_bomb_at(&Horizontal(x), &Vertical(y)), "seed {} bombed {},{}", seed, x, y);
+                for position in game.internal_field.neighbors_of(&Horizontal(x), &Vertical(y))
+                {
+                    assert!(!game.is_bomb_at(&position.0, &position.1), "seed {} bombed around {},{}", seed, x, y);
+                }
+            }
+        }
+    }
+}
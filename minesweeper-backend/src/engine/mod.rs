@@ -1 +1,5 @@
-pub mod minesweeper;
\ No newline at end of file
+pub mod minesweeper;
+pub mod preset;
+pub mod packed;
+pub mod encoding;
+pub mod endless;
\ No newline at end of file
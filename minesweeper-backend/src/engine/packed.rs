@@ -0,0 +1,131 @@
+use super::minesweeper::Tile;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+
+const NUM_BOMBS_AROUND_MASK: u8 = 0b0000_1111;
+const HAS_FLAG_BIT: u8 = 0b0001_0000;
+const IS_BOMB_BIT: u8 = 0b0010_0000;
+const IS_SHOWN_BIT: u8 = 0b0100_0000;
+const WAS_CLICKED_BIT: u8 = 0b1000_0000;
+
+/// A `Tile` squeezed into one byte, the low 4 bits are `num_bombs_around` and the high 4 bits are the flags.
+/// Serialized exactly like a `Tile` so the UI never sees the difference
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct PackedTile(u8);
+
+impl PackedTile
+{
+    pub fn from_bits(bits: u8) -> PackedTile
+    {
+        PackedTile(bits)
+    }
+
+    pub fn bits(&self) -> u8
+    {
+        self.0
+    }
+
+    /// Counts above 15 don't fit, which can't happen with only 8 neighbors
+    pub fn from_tile(tile: &Tile) -> PackedTile
+    {
+        let mut packed = PackedTile::default();
+
+        packed.set_num_bombs_around(tile.num_bombs_around);
+        packed.set_has_flag(tile.has_flag);
+        packed.set_is_bomb(tile.is_bomb);
+        packed.set_is_shown(tile.is_shown);
+        packed.set_was_clicked(tile.was_clicked);
+
+        packed
+    }
+
+    pub fn to_tile(&self) -> Tile
+    {
+        Tile {
+            num_bombs_around: self.num_bombs_around(),
+            has_flag: self.has_flag(),
+            is_bomb: self.is_bomb(),
+            is_shown: self.is_shown(),
+            was_clicked: self.was_clicked(),
+        }
+    }
+
+    fn set_bit(&mut self, bit: u8, value: bool)
+    {
+        if value
+        {
+            self.0 |= bit;
+        } else {
+            self.0 &= !bit;
+        }
+    }
+
+    pub fn num_bombs_around(&self) -> usize
+    {
+        (self.0 & NUM_BOMBS_AROUND_MASK) as usize
+    }
+
+    pub fn set_num_bombs_around(&mut self, num_bombs_around: usize)
+    {
+        debug_assert!(num_bombs_around <= NUM_BOMBS_AROUND_MASK as usize);
+
+        self.0 = (self.0 & !NUM_BOMBS_AROUND_MASK) | (num_bombs_around as u8 & NUM_BOMBS_AROUND_MASK);
+    }
+
+    pub fn has_flag(&self) -> bool
+    {
+        self.0 & HAS_FLAG_BIT != 0
+    }
+
+    pub fn set_has_flag(&mut self, has_flag: bool)
+    {
+        self.set_bit(HAS_FLAG_BIT, has_flag);
+    }
+
+    pub fn is_bomb(&self) -> bool
+    {
+        self.0 & IS_BOMB_BIT != 0
+    }
+
+    pub fn set_is_bomb(&mut self, is_bomb: bool)
+    {
+        self.set_bit(IS_BOMB_BIT, is_bomb);
+    }
+
+    pub fn is_shown(&self) -> bool
+    {
+        self.0 & IS_SHOWN_BIT != 0
+    }
+
+    pub fn set_is_shown(&mut self, is_shown: bool)
+    {
+        self.set_bit(IS_SHOWN_BIT, is_shown);
+    }
+
+    pub fn was_clicked(&self) -> bool
+    {
+        self.0 & WAS_CLICKED_BIT != 0
+    }
+
+    pub fn set_was_clicked(&mut self, was_clicked: bool)
+    {
+        self.set_bit(WAS_CLICKED_BIT, was_clicked);
+    }
+}
+
+impl Serialize for PackedTile
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    {
+        self.to_tile().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PackedTile
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<PackedTile, D::Error>
+    {
+        Tile::deserialize(deserializer).map(|tile| PackedTile::from_tile(&tile))
+    }
+}
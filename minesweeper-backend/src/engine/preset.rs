@@ -0,0 +1,43 @@
+use ::common::{Horizontal, Vertical};
+
+/// The classic difficulty levels, anything else is `Custom`
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Preset
+{
+    Beginner,
+    Intermediate,
+    Expert,
+    Custom,
+}
+
+impl Preset
+{
+    pub fn all() -> [Preset; 4]
+    {
+        [Preset::Beginner, Preset::Intermediate, Preset::Expert, Preset::Custom]
+    }
+
+    pub fn dimensions(&self) -> Option<(Horizontal, Vertical, usize)>
+    {
+        match self
+        {
+            Preset::Beginner => Some((Horizontal(9), Vertical(9), 10)),
+            Preset::Intermediate => Some((Horizontal(16), Vertical(16), 40)),
+            Preset::Expert => Some((Horizontal(30), Vertical(16), 99)),
+            Preset::Custom => None,
+        }
+    }
+
+    pub fn from_dimensions(width: Horizontal, height: Vertical, num_bombs: usize) -> Preset
+    {
+        for preset in Preset::all().iter()
+        {
+            if preset.dimensions() == Some((width, height, num_bombs))
+            {
+                return *preset;
+            }
+        }
+
+        Preset::Custom
+    }
+}
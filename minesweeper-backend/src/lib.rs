@@ -6,20 +6,31 @@ extern crate error_chain;
 
 #[macro_use]
 extern crate log;
-extern crate env_logger;
 
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
-
-extern crate web_view;
+extern crate base64;
 
 extern crate chrono;
 
 extern crate rand;
 
+#[cfg(target_arch = "wasm32")]
+extern crate wasm_bindgen;
+#[cfg(target_arch = "wasm32")]
+extern crate js_sys;
+
 pub mod engine;
 pub mod common;
+pub mod stats;
+pub mod settings;
+pub mod protocol;
+pub mod session;
+pub mod ai;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
 
 pub mod errors {
     // Create the Error, ErrorKind, ResultExt, and Result types
@@ -27,6 +38,142 @@ pub mod errors {
         foreign_links {
             /* NoneError doesn't like to be implemented. Just use `.ok_or("Nothing")?` instead of only `?` */
             // Nothing(::std::option::NoneError);
+            Io(::std::io::Error);
+            Json(::serde_json::Error);
+            Base64(::base64::DecodeError);
+        }
+
+        errors {
+            OutOfBounds(x: usize, y: usize, width: usize, height: usize) {
+                description("tile is outside of the field")
+                display("Tile x:{}, y:{} is outside of the {}x{} field", x, y, width, height)
+            }
+            Overflow(width: usize, height: usize) {
+                description("field is too big")
+                display("width:{} * height:{} overflowed", width, height)
+            }
+            EmptyField(width: usize, height: usize) {
+                description("field has no tiles")
+                display("A {}x{} field has no tiles", width, height)
+            }
+            NoBombs {
+                description("field has no bombs")
+                display("Need at least one bomb")
+            }
+            TooManyBombs(num_tiles: usize, num_bombs: usize) {
+                description("field has too many bombs")
+                display("{} tiles doesn't leave room for initial move + {} bombs", num_tiles, num_bombs)
+            }
+            NotAllowed(action: String, state: String) {
+                description("action isn't allowed right now")
+                display("{} isn't allowed while the game is {}", action, state)
+            }
+            AlreadyFlagged(x: i64, y: i64) {
+                description("tile already has a flag")
+                display("Tile x:{}, y:{} already has a flag", x, y)
+            }
+            NotFlagged(x: i64, y: i64) {
+                description("tile doesn't have a flag")
+                display("Tile x:{}, y:{} doesn't have a flag", x, y)
+            }
+            AlreadyShown(x: i64, y: i64) {
+                description("tile is already shown")
+                display("Tile x:{}, y:{} is already shown", x, y)
+            }
+            Flagged(x: i64, y: i64) {
+                description("tile has a flag")
+                display("Tile x:{}, y:{} has a flag, remove it first", x, y)
+            }
+            Unsupported(action: String) {
+                description("action isn't supported here")
+                display("{} isn't supported by this frontend", action)
+            }
+            InvalidDensity(density: f64, min: f64, max: f64) {
+                description("bomb density is out of range")
+                display("Density {} must be between {} and {}", density, min, max)
+            }
+            NothingToChord(x: i64, y: i64) {
+                description("tile can't be chorded")
+                display("Tile x:{}, y:{} isn't a shown number so there's nothing to chord", x, y)
+            }
+            WrongFlagCount(x: i64, y: i64, needed: usize, found: usize) {
+                description("tile has the wrong number of flags around it to chord")
+                display("Tile x:{}, y:{} needs {} flags around it to chord but has {}", x, y, needed, found)
+            }
+            OutOfWorld(x: i64, y: i64, limit: i64) {
+                description("tile is outside of the endless world")
+                display("Tile x:{}, y:{} is more than {} tiles from the middle of the world", x, y, limit)
+            }
+            Unexplored(x: i64, y: i64) {
+                description("tile is too far from anything exposed")
+                display("Tile x:{}, y:{} is too far from anything exposed to be flagged", x, y)
+            }
+            ViewportTooBig(width: usize, height: usize, max: usize) {
+                description("viewport is too big")
+                display("A {}x{} viewport is too big, it can be at most {} tiles each way", width, height, max)
+            }
+            InvalidFileName(file_name: String) {
+                description("not a plain file name")
+                display("{:?} must be a file name without any directories", file_name)
+            }
+            UnknownPlayer(player: usize) {
+                description("player isn't in the session")
+                display("Player {} isn't in this session", player)
+            }
+            PlayerFrozen(player: usize, remaining_ms: u64) {
+                description("player is frozen")
+                display("Player {} hit a mine and is frozen for another {}ms", player, remaining_ms)
+            }
+            NotYourTurn(player: usize, turn: usize) {
+                description("it's another player's turn")
+                display("It's player {}'s turn, not player {}'s", turn, player)
+            }
+            PlayerCount(num_players: usize, min: usize, max: usize) {
+                description("wrong number of players")
+                display("{} players can't play, it takes {} to {}", num_players, min, max)
+            }
+            PlayerEliminated(player: usize) {
+                description("player is eliminated")
+                display("Player {} hit a mine and is out of the game", player)
+            }
+        }
+    }
+
+    impl ErrorKind
+    {
+        /// Stable name of the kind of error for the UI to match on, the message is only meant for people
+        pub fn code(&self) -> &'static str
+        {
+            match self
+            {
+                ErrorKind::OutOfBounds(..) => "out_of_bounds",
+                ErrorKind::Overflow(..) => "overflow",
+                ErrorKind::EmptyField(..) => "empty_field",
+                ErrorKind::NoBombs => "no_bombs",
+                ErrorKind::TooManyBombs(..) => "too_many_bombs",
+                ErrorKind::NotAllowed(..) => "not_allowed",
+                ErrorKind::AlreadyFlagged(..) => "already_flagged",
+                ErrorKind::NotFlagged(..) => "not_flagged",
+                ErrorKind::AlreadyShown(..) => "already_shown",
+                ErrorKind::Flagged(..) => "flagged",
+                ErrorKind::Unsupported(..) => "unsupported",
+                ErrorKind::InvalidDensity(..) => "invalid_density",
+                ErrorKind::NothingToChord(..) => "nothing_to_chord",
+                ErrorKind::WrongFlagCount(..) => "wrong_flag_count",
+                ErrorKind::OutOfWorld(..) => "out_of_world",
+                ErrorKind::Unexplored(..) => "unexplored",
+                ErrorKind::ViewportTooBig(..) => "viewport_too_big",
+                ErrorKind::InvalidFileName(..) => "invalid_file_name",
+                ErrorKind::UnknownPlayer(..) => "unknown_player",
+                ErrorKind::PlayerFrozen(..) => "player_frozen",
+                ErrorKind::PlayerEliminated(..) => "player_eliminated",
+                ErrorKind::NotYourTurn(..) => "not_your_turn",
+                ErrorKind::PlayerCount(..) => "player_count",
+                ErrorKind::Io(_) => "io",
+                ErrorKind::Json(_) => "invalid_json",
+                ErrorKind::Base64(_) => "invalid_base64",
+                _ => "other",
+            }
         }
     }
 }
\ No newline at end of file
@@ -2,11 +2,15 @@ extern crate web_view;
 use web_view::*;
 
 extern crate minesweeper_backend;
-use minesweeper_backend::engine::minesweeper::{Action, Minesweeper, Tile, State};
-use minesweeper_backend::common::{Horizontal, Vertical};
+use minesweeper_backend::engine::minesweeper::{self, Action, Minesweeper, State};
+use minesweeper_backend::engine::encoding;
+use minesweeper_backend::engine::endless::{self, Endless, EndlessAction};
+use minesweeper_backend::common::{Horizontal, Vertical, WorldHorizontal, WorldVertical};
+use minesweeper_backend::stats::{Outcome, Stats};
+use minesweeper_backend::settings::Settings;
+use minesweeper_backend::errors::{Error, ErrorKind};
+use minesweeper_backend::protocol::ToUiCommand;
 
-#[macro_use]
-extern crate serde_derive;
 extern crate serde_json;
 extern crate serde;
 
@@ -15,60 +19,104 @@ extern crate log;
 extern crate env_logger;
 use env_logger::{Builder, Target};
  
+extern crate tungstenite;
+use tungstenite::{Message, WebSocket};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+
 use std::thread;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::time::Duration;
 
 
 fn main() {
-    configure_logger();
+    let loaded_settings = Settings::load(SETTINGS_FILE);
+    configure_logger(loaded_settings.as_ref().ok().and_then(|settings| settings.log_level.as_ref()));
+
+    let mut settings = match loaded_settings
+    {
+        Ok(settings) =>
+        {
+            if let Some(version) = settings.migrated_from
+            {
+                warn!("Migrated settings in {} from version {} to {}", SETTINGS_FILE, version, settings.version);
+                save_settings(&settings);
+            }
+            settings
+        },
+        Err(error) =>
+        {
+            warn!("Unable to load settings from {} because {}, resetting to defaults", SETTINGS_FILE, error);
+            let settings = Settings::default();
+            save_settings(&settings);
+            settings
+        },
+    };
+
+    let (width, height, num_bombs) = settings.game_dimensions();
+    let mut initial_game = match Minesweeper::new(width, height, num_bombs)
+    {
+        Ok(game) => game,
+        Err(error) =>
+        {
+            warn!("Saved game of width:{} height:{} bombs:{} is invalid because {}, resetting to defaults", width.0, height.0, num_bombs, error);
+            let (width, height, num_bombs) = Settings::default().game_dimensions();
+            settings.set_last_game(width, height, num_bombs);
+            save_settings(&settings);
+
+            Minesweeper::new(width, height, num_bombs).unwrap()
+        },
+    };
+    initial_game.set_first_click_policy(settings.first_click_policy);
+
+    let stats = match Stats::load(STATS_FILE)
+    {
+        Ok(stats) => stats,
+        Err(error) =>
+        {
+            warn!("Unable to load stats from {} because {}, starting fresh", STATS_FILE, error);
+            Stats::default()
+        },
+    };
+
+    let app = App {
+        game: initial_game,
+        stats,
+        settings,
+        viewport: None,
+        endless: None,
+        endless_viewport: EndlessViewport { x: -20, y: -12, w: 40, h: 25 },
+    };
 
-    let game = Arc::new(Mutex::new(Minesweeper::new(Horizontal(8), Vertical(8), 10).unwrap()));
-    let game_callback = game.clone();
-    let game_handle = game.clone();
+    match serve_port()
+    {
+        Some(port) => serve(app, port),
+        None => run_webview(app),
+    }
+}
+
+fn run_webview(app: App)
+{
+    let (window_width, window_height) = (app.settings.window_width, app.settings.window_height);
+
+    let app = Arc::new(Mutex::new(app));
+    let app_callback = app.clone();
 
     let web_view = web_view::builder()
         .title("Minesweeper")
-        .content(Content::Html(create_html()))
-        .size(800, 600)
+        .content(Content::Html(create_html(WEBVIEW_TRANSPORT_JS)))
+        .size(window_width, window_height)
         .resizable(true)
         .debug(false)
-        .user_data(game)
-        .invoke_handler( |webview, arg| {
-            trace!("Received from UI: {}", arg);
-
-            let mut game = game_callback.lock().unwrap();
-
-            match serde_json::from_str(arg)
-            {
-                Ok(Action::Start{ width, height, num_bombs }) => 
-                {
-                    match game.resize(Horizontal(width), Vertical(height), num_bombs)
-                    {
-                        Ok(_) => {},
-                        Err(error) => error!("failed to resize because {}", error),
-                    }
-                    send_to_ui(webview, &ToUiCommand::NewField {tiles: game.get_tiles()});
-                    send_to_ui(webview, &ToUiCommand::InProgress);
-                },
-                Ok(Action::Quit) => webview.terminate(),
-                Ok(action) =>
-                {
-                    match game.handle_action(action)
-                    {
-                        Ok(_) => {},
-                        Err(error) => error!("Action failed because: {}", error),
-                    }
-                    send_to_ui(webview, &ToUiCommand::NewField {tiles: game.get_tiles()});
-                    match game.get_state()
-                    {
-                        State::Won => send_to_ui(webview, &ToUiCommand::Won),
-                        State::Loss => send_to_ui(webview, &ToUiCommand::Loss),
-                        _ => send_to_ui(webview, &ToUiCommand::InProgress),
-                    };
-                }
-                Err(error) => error!("Unable to parse [{}] because {}", arg, error),
-            };
+        .user_data(())
+        .invoke_handler( move |webview, arg| {
+            app_callback.lock().unwrap().handle(webview, arg);
 
             Ok(())
         })
@@ -78,10 +126,7 @@ fn main() {
     let handle = web_view.handle();
     thread::spawn(move || {
         handle.dispatch(move |webview| {
-            let game = game_handle.lock().unwrap();
-
-            send_to_ui(webview, &ToUiCommand::NewField {tiles: game.get_tiles()});
-            send_to_ui(webview, &ToUiCommand::InProgress);
+            app.lock().unwrap().send_initial(webview);
 
             /*
                 The examples typically have the initial callback having a loop but a loop isn't needed for Minesweeper.
@@ -93,53 +138,602 @@ fn main() {
         .unwrap();
     });
 
-    let res = web_view.run().unwrap();
+    web_view.run().unwrap();
+}
 
-    println!("final state: {:?}", res);
+const DEFAULT_SERVE_PORT: u16 = 8080;
+/// How long a connection may stay silent while its request is read, so an idle socket can't hold anything up
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often a connected browser checks whether a newer tab has taken over
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
 
+/// Serves the UI on localhost and plays over a WebSocket, the newest browser tab takes over from any older one.
+/// The game carries over between tabs and quitting from the UI stops the server
+fn serve(app: App, port: u16)
+{
+    let listener = match TcpListener::bind(("127.0.0.1", port))
+    {
+        Ok(listener) => listener,
+        Err(error) =>
+        {
+            error!("Unable to listen on port {} because {}", port, error);
+            process::exit(1);
+        },
+    };
+    println!("Minesweeper is being served at http://localhost:{}/", port);
 
- 
+    let html = Arc::new(create_html(WEBSOCKET_TRANSPORT_JS));
+    let app = Arc::new(Mutex::new(app));
+    /* Bumped by every new WebSocket, the older ones see it change and close */
+    let generation = Arc::new(AtomicUsize::new(0));
+
+    for stream in listener.incoming()
+    {
+        let stream = match stream
+        {
+            Ok(stream) => stream,
+            Err(error) =>
+            {
+                warn!("Unable to accept a connection because {}", error);
+                continue;
+            },
+        };
+
+        if let Err(error) = stream.set_read_timeout(Some(REQUEST_TIMEOUT)).and_then(|_| stream.set_write_timeout(Some(REQUEST_TIMEOUT)))
+        {
+            warn!("Unable to set timeouts on a connection because {}", error);
+            continue;
+        }
+
+        let (app, html, generation) = (app.clone(), html.clone(), generation.clone());
+        thread::spawn(move || {
+            let result = if is_websocket_request(&stream)
+            {
+                play_over_websocket(&app, stream, port, &generation)
+            } else {
+                serve_page(stream, &html).map_err(|error| error.to_string())
+            };
+
+            if let Err(error) = result
+            {
+                warn!("Connection failed because {}", error);
+            }
+        });
+    }
 }
 
-#[derive(Serialize, Debug)]
-#[serde(tag = "_type")]
-pub enum ToUiCommand<'a> {
-    Won,
-    Loss,
-    InProgress,
-    NewField { tiles: &'a Vec<Vec<Tile>> },
+/// Looks at the request without consuming it so the handshake can still read it
+fn is_websocket_request(stream: &TcpStream) -> bool
+{
+    let mut buffer = [0; 4096];
+    match stream.peek(&mut buffer)
+    {
+        Ok(len) => String::from_utf8_lossy(&buffer[..len]).to_lowercase().contains("upgrade: websocket"),
+        Err(_) => false,
+    }
 }
 
-pub fn send_to_ui<'a, S, T>(webview: &mut WebView<'a, T>, data: &S)
-    where S: serde::ser::Serialize
+fn serve_page(stream: TcpStream, html: &str) -> io::Result<()>
 {
-    trace!("Serializing to send to UI");
-    match serde_json::to_string(data)
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    /* The headers aren't needed, just read past them */
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2
+    {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (status, content_type, body) = if path == "/" || path == "/index.html"
+    {
+        ("200 OK", "text/html; charset=utf-8", html)
+    } else {
+        ("404 Not Found", "text/plain; charset=utf-8", "Not found")
+    };
+    trace!("Served {} for {}", status, path);
+
+    let mut stream = reader.into_inner();
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body)?;
+    stream.flush()
+}
+
+/// Only the page this process served may connect, otherwise any site open in the browser could play or export stats.
+/// The Host is checked too since a site can point its own name at 127.0.0.1
+fn is_local_request(request: &Request, port: u16) -> bool
+{
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok());
+    let hosts = [format!("localhost:{}", port), format!("127.0.0.1:{}", port)];
+
+    let is_local_host = header("host").is_some_and(|host| hosts.iter().any(|local| local == host));
+    let is_local_origin = header("origin").is_some_and(|origin| hosts.iter().any(|local| origin == format!("http://{}", local)));
+
+    is_local_host && is_local_origin
+}
+
+/// Plays until the browser disconnects or a newer tab connects, quitting from the UI stops the whole server
+/* tungstenite picks the handshake callback's error type */
+#[allow(clippy::result_large_err)]
+fn play_over_websocket(app: &Mutex<App>, stream: TcpStream, port: u16, generation: &AtomicUsize) -> ::std::result::Result<(), String>
+{
+    let socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+        if is_local_request(request, port)
+        {
+            return Ok(response);
+        }
+
+        warn!("Refused a WebSocket from origin {:?}", request.headers().get("origin"));
+        let mut refusal = ErrorResponse::new(Some("Only the page served by this process may connect".to_string()));
+        *refusal.status_mut() = StatusCode::FORBIDDEN;
+        Err(refusal)
+    }).map_err(|error| error.to_string())?;
+
+    /* Reads wake up regularly so a replaced tab notices, tungstenite keeps any partly read message for the next read */
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)).map_err(|error| error.to_string())?;
+    let connection = generation.fetch_add(1, Ordering::SeqCst) + 1;
+    info!("Browser connected");
+
+    let mut ui = WebSocketUi { socket, quit: false };
+    app.lock().unwrap().send_initial(&mut ui);
+
+    while !ui.quit
+    {
+        if generation.load(Ordering::SeqCst) != connection
+        {
+            info!("Browser replaced by a newer tab");
+            let _ = ui.socket.close(None);
+            return Ok(());
+        }
+
+        match ui.socket.read_message()
+        {
+            Ok(Message::Text(text)) => app.lock().unwrap().handle(&mut ui, &text),
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) =>
+            {
+                info!("Browser disconnected");
+                return Ok(());
+            },
+            Err(tungstenite::Error::Io(ref error)) if error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut => {},
+            Ok(_) => {},
+            Err(error) => return Err(error.to_string()),
+        }
+    }
+
+    let _ = ui.socket.close(None);
+    info!("Quit from the browser");
+    process::exit(0);
+}
+
+struct WebSocketUi
+{
+    socket: WebSocket<TcpStream>,
+    quit: bool,
+}
+
+impl Ui for WebSocketUi
+{
+    fn send_json(&mut self, json: &str)
     {
-        Ok(json) => 
+        match self.socket.write_message(Message::Text(json.to_string()))
         {
-            match webview.eval(&format!("toFrontEnd({})", json))
+            Ok(_) => trace!("Sent to UI"),
+            Err(error) => error!("failed to send to ui because {}", error),
+        }
+    }
+
+    fn terminate(&mut self)
+    {
+        self.quit = true;
+    }
+}
+
+/// `--serve [port]` plays in a normal browser instead of the webview window
+fn serve_port() -> Option<u16>
+{
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next()
+    {
+        if arg == "--serve"
+        {
+            return Some(args.next().and_then(|port| port.parse().ok()).unwrap_or(DEFAULT_SERVE_PORT));
+        }
+    }
+
+    None
+}
+
+/// Where messages for the Elm UI go, either the webview window or a browser connected with `--serve`
+pub trait Ui
+{
+    /// `json` is one serialized `ToUiCommand`
+    fn send_json(&mut self, json: &str);
+    fn terminate(&mut self);
+}
+
+impl<'a, T> Ui for WebView<'a, T>
+{
+    fn send_json(&mut self, json: &str)
+    {
+        match self.eval(&format!("toFrontEnd({})", json))
+        {
+            Ok(_) => trace!("Sent to UI"),
+            Err(error) => error!("failed to send to ui because {}", error),
+        }
+    }
+
+    fn terminate(&mut self)
+    {
+        WebView::terminate(self);
+    }
+}
+
+/// Everything the UI can change, shared by every way of showing the UI
+pub struct App
+{
+    game: Minesweeper,
+    stats: Stats,
+    settings: Settings,
+    viewport: Option<Viewport>,
+    endless: Option<Endless>,
+    endless_viewport: EndlessViewport,
+}
+
+impl App
+{
+    /// What the UI needs as soon as it has loaded
+    pub fn send_initial<U: Ui>(&self, ui: &mut U)
+    {
+        send_field_to_ui(ui, &self.game, self.settings.compact_field_threshold, &self.viewport);
+        send_state_to_ui(ui, self.game.get_state());
+        send_to_ui(ui, &ToUiCommand::Stats {stats: self.stats.summary()});
+        send_to_ui(ui, &ToUiCommand::Settings {settings: &self.settings});
+    }
+
+    /// `arg` is one `Action` as JSON from the UI
+    pub fn handle<U: Ui>(&mut self, ui: &mut U, arg: &str)
+    {
+        trace!("Received from UI: {}", arg);
+
+        match serde_json::from_str(arg)
+        {
+            Ok(Action::Start{ width, height, num_bombs }) => 
+            {
+                /* The new game is made first so a rejected Start leaves the one being played and its stats alone */
+                match Minesweeper::new(Horizontal(width), Vertical(height), num_bombs)
+                {
+                    Ok(mut new_game) =>
+                    {
+                        new_game.set_first_click_policy(self.settings.first_click_policy);
+                        let old_game = mem::replace(&mut self.game, new_game);
+                        abandon_game_in_progress(&mut self.stats, &old_game);
+
+                        if self.settings.set_last_game(Horizontal(width), Vertical(height), num_bombs)
+                        {
+                            save_settings(&self.settings);
+                        }
+                        send_field_to_ui(ui, &self.game, self.settings.compact_field_threshold, &self.viewport);
+                        send_to_ui(ui, &ToUiCommand::InProgress);
+                    },
+                    Err(error) => send_error_to_ui(ui, "failed to resize", &error),
+                }
+            },
+            Ok(Action::RequestField) =>
             {
-                Ok(_) => trace!("Sent to UI"),
-                Err(error) => error!("failed to send to ui because {}", error),
+                send_field_to_ui(ui, &self.game, self.settings.compact_field_threshold, &self.viewport);
+                send_state_to_ui(ui, self.game.get_state());
+            },
+            Ok(Action::SetViewport{ x, y, w, h }) =>
+            {
+                match minesweeper::check_viewport_size(w, h)
+                {
+                    Ok(_) =>
+                    {
+                        self.viewport = Some(Viewport { x, y, w, h });
+                        send_field_to_ui(ui, &self.game, self.settings.compact_field_threshold, &self.viewport);
+                    },
+                    Err(error) => send_error_to_ui(ui, "failed to set the viewport", &error),
+                }
+            },
+            Ok(Action::ClearViewport) =>
+            {
+                self.viewport = None;
+                send_field_to_ui(ui, &self.game, self.settings.compact_field_threshold, &self.viewport);
+            },
+            Ok(Action::Endless{ action: EndlessAction::Start{ seed, density } }) =>
+            {
+                match Endless::new(seed, density)
+                {
+                    Ok(new_endless) =>
+                    {
+                        send_endless_field_to_ui(ui, &new_endless, &self.endless_viewport);
+                        send_state_to_ui(ui, new_endless.get_state());
+                        self.endless = Some(new_endless);
+                    },
+                    Err(error) => send_error_to_ui(ui, "failed to start endless game", &error),
+                }
+            },
+            Ok(Action::Endless{ action: EndlessAction::SetViewport{ x, y, w, h } }) =>
+            {
+                match endless::check_viewport(x, y, w, h)
+                {
+                    Ok(_) =>
+                    {
+                        self.endless_viewport = EndlessViewport { x, y, w, h };
+                        if let Some(ref endless) = self.endless
+                        {
+                            send_endless_field_to_ui(ui, endless, &self.endless_viewport);
+                        }
+                    },
+                    Err(error) => send_error_to_ui(ui, "failed to set the endless viewport", &error),
+                }
+            },
+            Ok(Action::Endless{ action }) =>
+            {
+                match self.endless
+                {
+                    Some(ref mut endless) =>
+                    {
+                        match endless.handle_action(action)
+                        {
+                            Ok(_) => {},
+                            Err(error) => send_error_to_ui(ui, "Endless action failed", &error),
+                        }
+
+                        let endless_viewport = self.endless_viewport;
+                        let mut changes = endless.get_changed_tiles();
+                        changes.retain(|&(x, y, _)| endless_viewport.contains(x, y));
+                        send_to_ui(ui, &ToUiCommand::EndlessTilesChanged {changes, score: endless.get_score()});
+                        send_state_to_ui(ui, endless.get_state());
+                    },
+                    None => send_error_to_ui(ui, "Endless action failed", &"No endless game has been started".into()),
+                }
+            },
+            Ok(Action::RequestStats) => send_to_ui(ui, &ToUiCommand::Stats {stats: self.stats.summary()}),
+            Ok(Action::ExportStats{ format, file_name }) =>
+            {
+                match self.stats.export_to(format, DATA_DIR, &file_name)
+                {
+                    Ok(path) => info!("Exported stats to {}", path.display()),
+                    Err(error) => send_error_to_ui(ui, "failed to export stats", &error),
+                }
+            },
+            Ok(Action::UpdateSettings{ settings: update }) =>
+            {
+                if self.settings.apply(update)
+                {
+                    self.game.set_first_click_policy(self.settings.first_click_policy);
+                    save_settings(&self.settings);
+                }
+                send_to_ui(ui, &ToUiCommand::Settings {settings: &self.settings});
+            },
+            Ok(action @ Action::Join{ .. }) | Ok(action @ Action::Leave) |
+            Ok(action @ Action::MoveCursor{ .. }) | Ok(action @ Action::MarkPending{ .. }) | Ok(action @ Action::UnmarkPending{ .. }) |
+            Ok(action @ Action::StartRace) =>
+            {
+                send_error_to_ui(ui, "Multiplayer action failed", &ErrorKind::Unsupported(format!("{:?}", action)).into());
+            },
+            Ok(Action::Quit) => 
+            {
+                abandon_game_in_progress(&mut self.stats, &self.game);
+                ui.terminate();
+            },
+            Ok(action) =>
+            {
+                let was_finished = is_finished(self.game.get_state());
+
+                match self.game.handle_action(action)
+                {
+                    Ok(_) => {},
+                    Err(error) => send_error_to_ui(ui, "Action failed", &error),
+                }
+
+                if !was_finished && self.game.get_state() != &State::New
+                {
+                    self.stats.start_game();
+                }
+
+                send_changes_to_ui(ui, &self.game, self.settings.compact_field_threshold, &self.viewport);
+                send_state_to_ui(ui, self.game.get_state());
+
+                if !was_finished && is_finished(self.game.get_state())
+                {
+                    let outcome = match self.game.get_state()
+                    {
+                        State::Won => Outcome::Won,
+                        _ => Outcome::Loss,
+                    };
+                    self.stats.finish_game(&self.game, outcome);
+                    save_stats(&self.stats);
+
+                    send_to_ui(ui, &ToUiCommand::Stats {stats: self.stats.summary()});
+                }
             }
-        },
+            Err(error) => send_error_to_ui(ui, &format!("Unable to parse [{}]", arg), &error.into()),
+        };
+
+    }
+}
+
+/// Stats, settings and exports are all kept in the directory Minesweeper runs from
+const DATA_DIR: &str = ".";
+const STATS_FILE: &str = "minesweeper_stats.json";
+const SETTINGS_FILE: &str = "minesweeper_settings.json";
+
+fn is_finished(state: &State) -> bool
+{
+    matches!(state, State::Won | State::Loss)
+}
+
+/// Starting over or quitting before the game is won or lost still counts against the player
+fn abandon_game_in_progress(stats: &mut Stats, game: &Minesweeper)
+{
+    if stats.is_game_in_progress() && !is_finished(game.get_state())
+    {
+        stats.finish_game(game, Outcome::Abandoned);
+        save_stats(stats);
+    }
+}
+
+fn save_settings(settings: &Settings)
+{
+    match settings.save(SETTINGS_FILE)
+    {
+        Ok(_) => trace!("Saved settings to {}", SETTINGS_FILE),
+        Err(error) => error!("failed to save settings because {}", error),
+    }
+}
+
+fn save_stats(stats: &Stats)
+{
+    match stats.save(STATS_FILE)
+    {
+        Ok(_) => trace!("Saved stats to {}", STATS_FILE),
+        Err(error) => error!("failed to save stats because {}", error),
+    }
+}
+
+/// The part of the field the UI is showing, for fields too large to send whole
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport
+{
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+impl Viewport
+{
+    fn contains(&self, x: usize, y: usize) -> bool
+    {
+        x >= self.x && x - self.x < self.w && y >= self.y && y - self.y < self.h
+    }
+}
+
+/// Same as `Viewport` but for the endless field, which has negative coordinates
+#[derive(Clone, Copy, Debug)]
+pub struct EndlessViewport
+{
+    x: i64,
+    y: i64,
+    w: usize,
+    h: usize,
+}
+
+impl EndlessViewport
+{
+    fn contains(&self, x: i64, y: i64) -> bool
+    {
+        /* Changes can be anywhere in the world so the offset may not fit */
+        let is_within = |start: i64, position: i64, length: usize| match position.checked_sub(start)
+        {
+            Some(offset) => offset >= 0 && (offset as u64) < length as u64,
+            None => false,
+        };
+
+        is_within(self.x, x, self.w) && is_within(self.y, y, self.h)
+    }
+}
+
+pub fn send_endless_field_to_ui<U: Ui>(ui: &mut U, endless: &Endless, viewport: &EndlessViewport)
+{
+    let tiles = endless.get_tiles_in(&WorldHorizontal(viewport.x), &WorldVertical(viewport.y), viewport.w, viewport.h);
+    send_to_ui(ui, &ToUiCommand::EndlessField {x: viewport.x, y: viewport.y, tiles, seed: endless.get_seed(), score: endless.get_score()});
+}
+
+/// Large fields are sent with the compact encoding since serializing every `Tile` as JSON dominates
+pub fn send_field_to_ui<U: Ui>(ui: &mut U, game: &Minesweeper, compact_threshold: usize, viewport: &Option<Viewport>)
+{
+    if let Some(viewport) = viewport
+    {
+        let tiles = game.get_tiles_in(&Horizontal(viewport.x), &Vertical(viewport.y), &Horizontal(viewport.w), &Vertical(viewport.h));
+        send_to_ui(ui, &ToUiCommand::ViewportField {x: viewport.x, y: viewport.y, tiles, summary: game.get_summary()});
+        return;
+    }
+
+    if game.get_width().0 * game.get_height().0 <= compact_threshold
+    {
+        send_to_ui(ui, &ToUiCommand::NewField {tiles: game.get_tiles()});
+        return;
+    }
+
+    match encoding::encode_field(game.get_tiles())
+    {
+        Ok(data) => send_to_ui(ui, &ToUiCommand::CompactField {data}),
+        Err(error) => error!("failed to encode field because {}", error),
+    }
+}
+
+/// A changed tile is sent as x, y and the whole tile in JSON, around 100 bytes,
+/// where the compact encoding needs 4 bytes for every 3 tiles
+const COMPACT_TILES_PER_CHANGE: usize = 75;
+
+/// Once enough tiles change on a field that is sent compactly it is cheaper to resend the whole field
+pub fn send_changes_to_ui<U: Ui>(ui: &mut U, game: &Minesweeper, compact_threshold: usize, viewport: &Option<Viewport>)
+{
+    let mut changes = game.get_changed_tiles();
+
+    if let Some(viewport) = viewport
+    {
+        changes.retain(|&(x, y, _)| viewport.contains(x, y));
+    }
+
+    let num_tiles = game.get_width().0 * game.get_height().0;
+    let is_sent_compactly = viewport.is_none() && num_tiles > compact_threshold;
+    if is_sent_compactly && changes.len().saturating_mul(COMPACT_TILES_PER_CHANGE) > num_tiles
+    {
+        send_field_to_ui(ui, game, compact_threshold, viewport);
+    } else if !changes.is_empty()
+    {
+        send_to_ui(ui, &ToUiCommand::TilesChanged {changes});
+    }
+
+    send_to_ui(ui, &ToUiCommand::Summary {summary: game.get_summary()});
+}
+
+/// Logs the error and lets the player know why their action didn't do anything
+pub fn send_error_to_ui<U: Ui>(ui: &mut U, context: &str, error: &Error)
+{
+    error!("{} because {}", context, error);
+    send_to_ui(ui, &ToUiCommand::from_error(error));
+}
+
+pub fn send_state_to_ui<U: Ui>(ui: &mut U, state: &State)
+{
+    send_to_ui(ui, &ToUiCommand::from_state(state));
+}
+
+pub fn send_to_ui<U: Ui, S>(ui: &mut U, data: &S)
+    where S: serde::ser::Serialize
+{
+    trace!("Serializing to send to UI");
+    match serde_json::to_string(data)
+    {
+        Ok(json) => ui.send_json(&json),
         Err(error) => error!("failed to serialize for ui because {}", error),
     };
 }
 
-fn configure_logger()
+fn configure_logger(settings_level: Option<&String>)
 {
     let mut builder = Builder::new();
     builder.target(Target::Stdout);
     if let Ok(level) = env::var("RUST_LOG")
     {
         builder.parse(&level);
+    } else if let Some(level) = settings_level
+    {
+        builder.parse(level);
     }
     builder.init();
 }
 
-fn create_html() -> String
+/// `transport_js` defines `sendToBackEnd(str)` for however the UI talks to this process
+fn create_html(transport_js: &str) -> String
 {
     format!(r#"
     <!DOCTYPE html>
@@ -154,6 +748,8 @@ fn create_html() -> String
         <script>
             {elmJs}
         
+            {transportJs}
+
             {portsJs}
         </script> 
         
@@ -161,19 +757,125 @@ fn create_html() -> String
     </html>
     "#,
         elmJs = ELM_JS,
+        transportJs = transport_js,
         portsJs = PORTS_JS,
     )
 }
 
-const ELM_JS: &'static str = include_str!(concat!(env!("OUT_DIR"), "/elm.js"));
-const PORTS_JS: &'static str = r#"
+const ELM_JS: &str = include_str!(concat!(env!("OUT_DIR"), "/elm.js"));
+const PORTS_JS: &str = r#"
         var app = Elm.Main.init({node: document.getElementById("view")});
 
         app.ports.toBackEnd.subscribe(function (str) {
-            window.external.invoke(str);
+            sendToBackEnd(str);
         });
 
+        /* Unpacks the header written by `engine::encoding::encode_field`, the tile bytes are unpacked by Elm */
+        function decodeCompactField(data) {
+          var raw = atob(data);
+          var readU32 = function (offset) {
+            return ((raw.charCodeAt(offset) << 24) >>> 0) + (raw.charCodeAt(offset + 1) << 16) + (raw.charCodeAt(offset + 2) << 8) + raw.charCodeAt(offset + 3);
+          };
+          var width = readU32(1);
+          var height = readU32(5);
+
+          var tiles = new Array(width * height);
+          for (var i = 0; i < tiles.length; i++) {
+            tiles[i] = raw.charCodeAt(9 + i);
+          }
+
+          return {_type: "CompactField", version: raw.charCodeAt(0), width: width, height: height, tiles: tiles};
+        }
+
         function toFrontEnd(str) {
+          if (str._type === "CompactField") {
+            str = decodeCompactField(str.data);
+          }
           app.ports.toFrontEnd.send(str);
         }
-"#;
\ No newline at end of file
+"#;
+
+const WEBVIEW_TRANSPORT_JS: &str = r#"
+        function sendToBackEnd(str) {
+          window.external.invoke(str);
+        }
+"#;
+
+/// Anything sent before the socket has opened is held back so the first request isn't lost
+const WEBSOCKET_TRANSPORT_JS: &str = r#"
+        var socket = new WebSocket("ws://" + location.host + "/ws");
+        var pending = [];
+
+        socket.onopen = function () {
+          pending.forEach(function (str) { socket.send(str); });
+          pending = [];
+        };
+        socket.onmessage = function (event) {
+          toFrontEnd(JSON.parse(event.data));
+        };
+        socket.onclose = function () {
+          document.title = "Minesweeper (disconnected)";
+        };
+
+        function sendToBackEnd(str) {
+          if (socket.readyState === WebSocket.OPEN) {
+            socket.send(str);
+          } else {
+            pending.push(str);
+          }
+        }
+"#;
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Keeps the `_type` of everything the app sends
+    #[derive(Default)]
+    struct RecordingUi
+    {
+        sent: Vec<String>,
+    }
+
+    impl Ui for RecordingUi
+    {
+        fn send_json(&mut self, json: &str)
+        {
+            let command: serde_json::Value = serde_json::from_str(json).unwrap();
+            self.sent.push(command["_type"].as_str().unwrap().to_string());
+        }
+
+        fn terminate(&mut self) {}
+    }
+
+    #[test]
+    fn a_rejected_start_leaves_the_game_and_stats_alone()
+    {
+        let mut game = Minesweeper::new(Horizontal(9), Vertical(9), 10).unwrap();
+        game.set_seed(Some(1));
+        let mut app = App {
+            game,
+            stats: Stats::default(),
+            settings: Settings::default(),
+            viewport: None,
+            endless: None,
+            endless_viewport: EndlessViewport { x: 0, y: 0, w: 1, h: 1 },
+        };
+        let mut ui = RecordingUi::default();
+
+        app.handle(&mut ui, r#"{"_type": "Expose", "x_position": 4, "y_position": 4}"#);
+        assert_eq!(app.game.get_state(), &State::InProgress);
+        assert!(app.stats.is_game_in_progress());
+        let num_shown = app.game.get_summary().num_shown;
+
+        ui.sent.clear();
+        app.handle(&mut ui, r#"{"_type": "Start", "width": 0, "height": 9, "num_bombs": 10}"#);
+
+        assert_eq!(ui.sent, vec!["Error"]);
+        assert!(app.stats.history().is_empty());
+        assert!(app.stats.is_game_in_progress());
+        assert_eq!(app.game.get_state(), &State::InProgress);
+        assert_eq!(app.game.get_summary().num_shown, num_shown);
+    }
+}
@@ -0,0 +1,76 @@
+use ::errors::Error;
+
+use ::common::vec2d::Vec2d;
+use ::engine::minesweeper::{FieldSummary, State, Tile};
+use ::engine::packed::PackedTile;
+use ::stats::Summary;
+use ::settings::Settings;
+use ::session::PlayerId;
+use ::session::competitive::PlayerSummary;
+use ::session::coop::PlayerPresence;
+use ::session::hotseat::SeatSummary;
+use ::session::race::RacerProgress;
+
+
+/// Everything the backend tells a frontend, serialized as JSON with the variant's name in `_type`.
+/// Shared by the webview app and the headless binary so bots see exactly what the UI sees
+#[derive(Serialize, Debug)]
+#[serde(tag = "_type")]
+pub enum ToUiCommand<'a> {
+    Won,
+    Loss,
+    InProgress,
+    NewField { tiles: &'a Vec2d<PackedTile> },
+    /// Only the tiles that changed since the last command, as x, y, tile
+    TilesChanged { changes: Vec<(usize, usize, PackedTile)> },
+    /// The whole field packed by `engine::encoding::encode_field`, used for large fields
+    CompactField { data: String },
+    /// Only the rows of the field inside the viewport, starting at x, y
+    ViewportField { x: usize, y: usize, tiles: Vec<&'a [PackedTile]>, summary: FieldSummary },
+    Summary { summary: FieldSummary },
+    /// The part of the endless field inside the viewport, starting at x, y
+    EndlessField { x: i64, y: i64, tiles: Vec<Vec<Tile>>, seed: u64, score: usize },
+    EndlessTilesChanged { changes: Vec<(i64, i64, Tile)>, score: usize },
+    Stats { stats: Summary },
+    Settings { settings: &'a Settings },
+    /// Why the last action was rejected, `code` is one of `ErrorKind::code`
+    Error { code: &'static str, message: String },
+    /// Multiplayer, the id the host gave this client
+    Joined { player: PlayerId },
+    /// Multiplayer, the scoreboard, sent whenever it changes
+    Players { players: Vec<PlayerSummary> },
+    /// Multiplayer, the tiles changed by one player's move and the points it scored
+    PlayerTilesChanged { player: PlayerId, changes: Vec<(usize, usize, PackedTile)>, points: usize },
+    /// Co-op, everyone's cursor and pending flags, sent whenever they change
+    Presence { players: Vec<PlayerPresence> },
+    /// Race, how far along every racer is, sent every move and every second while racing
+    RaceProgress { racers: Vec<RacerProgress> },
+    /// Hot-seat, whose turn it is and everyone's score, sent after every move
+    Turn { player: PlayerId, players: Vec<SeatSummary> },
+    /// Multiplayer, `player` exposed the bomb at x, y which is now flagged for everyone
+    MineHit { player: PlayerId, x: usize, y: usize },
+    /// Multiplayer, everyone tied for the best score, empty if nobody could win
+    SessionOver { winners: Vec<PlayerId> },
+}
+
+impl<'a> ToUiCommand<'a>
+{
+    /// A game that hasn't started yet is reported as in progress
+    pub fn from_state(state: &State) -> ToUiCommand<'a>
+    {
+        match state
+        {
+            State::Won => ToUiCommand::Won,
+            State::Loss => ToUiCommand::Loss,
+            State::New | State::InProgress => ToUiCommand::InProgress,
+        }
+    }
+
+    pub fn from_error(error: &Error) -> ToUiCommand<'a>
+    {
+        ToUiCommand::Error {
+            code: error.kind().code(),
+            message: error.to_string(),
+        }
+    }
+}
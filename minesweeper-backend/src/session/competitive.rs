@@ -0,0 +1,403 @@
+use ::errors::{ErrorKind, Result};
+
+use ::engine::minesweeper::{Action, FirstClickPolicy, Minesweeper, State};
+use ::protocol::ToUiCommand;
+use super::{bomb_hit_by, duration_ms, MoveResult, PlayerId, Session};
+
+use std::time::{Duration, Instant};
+
+
+/// What happens to a player that exposes a bomb
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(tag = "_type")]
+pub enum MinePenalty
+{
+    /// The player can't move for a while but keeps playing afterwards
+    Freeze { seconds: u64 },
+    /// The player is out for the rest of the game but keeps their score
+    Eliminate,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(tag = "_type")]
+pub enum PlayerStatus
+{
+    Playing,
+    Frozen { remaining_ms: u64 },
+    Eliminated,
+    /// Left the session, their score still counts
+    Left,
+}
+
+/// One row of the scoreboard sent to every client
+#[derive(Serialize, Clone, Debug)]
+pub struct PlayerSummary
+{
+    pub id: PlayerId,
+    pub name: String,
+    pub score: usize,
+    pub mines_hit: usize,
+    pub status: PlayerStatus,
+}
+
+#[derive(Debug)]
+struct Player
+{
+    name: String,
+    score: usize,
+    mines_hit: usize,
+    frozen_until: Option<Instant>,
+    eliminated: bool,
+    left: bool,
+}
+
+/// Every player races to open tiles on the same field, one point per tile opened.
+/// Exposing a bomb only costs the player that did it, the bomb is flagged and the game goes on for everyone else.
+/// The game is over once every safe tile is open, every bomb is flagged or nobody is left who can move
+#[derive(Debug)]
+pub struct CompetitiveSession
+{
+    game: Minesweeper,
+    /// Indexed by `PlayerId`, players that left keep their slot so ids stay unique
+    players: Vec<Player>,
+    penalty: MinePenalty,
+
+    num_safe_tiles: usize,
+    /// Safe tiles that have been clicked by anyone
+    num_cleared: usize,
+}
+
+impl CompetitiveSession
+{
+    /// The first move is always protected since losing the whole game to one player's first click isn't fair on the others
+    pub fn new(mut game: Minesweeper, penalty: MinePenalty) -> CompetitiveSession
+    {
+        if game.get_first_click_policy() == FirstClickPolicy::Unprotected
+        {
+            game.set_first_click_policy(FirstClickPolicy::SafeTile);
+        }
+
+        let num_safe_tiles = game.get_width().0 * game.get_height().0 - game.get_num_bombs();
+
+        CompetitiveSession {
+            game,
+            players: Vec::new(),
+            penalty,
+            num_safe_tiles,
+            num_cleared: 0,
+        }
+    }
+
+    fn player_mut(&mut self, player: PlayerId) -> Result<&mut Player>
+    {
+        match self.players.get_mut(player.0).filter(|found| !found.left)
+        {
+            Some(found) => Ok(found),
+            None => bail!(ErrorKind::UnknownPlayer(player.0)),
+        }
+    }
+
+    fn penalize(&mut self, player: PlayerId, now: Instant)
+    {
+        let found = &mut self.players[player.0];
+        found.mines_hit += 1;
+
+        match self.penalty
+        {
+            MinePenalty::Freeze { seconds } => found.frozen_until = Some(now + Duration::from_secs(seconds)),
+            MinePenalty::Eliminate => found.eliminated = true,
+        }
+    }
+
+    pub fn get_players(&self) -> Vec<PlayerSummary>
+    {
+        let now = Instant::now();
+
+        self.players.iter().enumerate()
+            .map(|(index, player)| {
+                let status = match player.frozen_until
+                {
+                    _ if player.left => PlayerStatus::Left,
+                    _ if player.eliminated => PlayerStatus::Eliminated,
+                    Some(until) if until > now => PlayerStatus::Frozen { remaining_ms: duration_ms(until - now) },
+                    _ => PlayerStatus::Playing,
+                };
+
+                PlayerSummary {
+                    id: PlayerId(index),
+                    name: player.name.clone(),
+                    score: player.score,
+                    mines_hit: player.mines_hit,
+                    status,
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_penalty(&self) -> MinePenalty
+    {
+        self.penalty
+    }
+}
+
+impl Session for CompetitiveSession
+{
+    fn join(&mut self, name: String) -> Result<PlayerId>
+    {
+        self.players.push(Player {
+            name,
+            score: 0,
+            mines_hit: 0,
+            frozen_until: None,
+            eliminated: false,
+            left: false,
+        });
+
+        Ok(PlayerId(self.players.len() - 1))
+    }
+
+    fn leave(&mut self, player: PlayerId) -> Result<()>
+    {
+        self.player_mut(player)?.left = true;
+
+        Ok(())
+    }
+
+    /// Only `Flag`, `Unflag`, `Expose` and `Chord` are moves, the host handles everything else.
+    /// Exposing a bomb flags it and penalizes the player instead of ending the game
+    fn handle_action(&mut self, player: PlayerId, action: Action) -> Result<MoveResult>
+    {
+        if self.is_over()
+        {
+            bail!(ErrorKind::NotAllowed(format!("{:?}", action), "over".to_string()));
+        }
+
+        let now = Instant::now();
+        {
+            let found = self.player_mut(player)?;
+            if found.eliminated
+            {
+                bail!(ErrorKind::PlayerEliminated(player.0));
+            }
+            if let Some(until) = found.frozen_until
+            {
+                if until > now
+                {
+                    bail!(ErrorKind::PlayerFrozen(player.0, duration_ms(until - now)));
+                }
+                found.frozen_until = None;
+            }
+        }
+
+        let is_expose = match action
+        {
+            Action::Expose { .. } | Action::Chord { .. } => true,
+            Action::Flag { .. } | Action::Unflag { .. } => false,
+            _ => bail!(ErrorKind::Unsupported(format!("{:?}", action))),
+        };
+
+        self.game.validate_action(&action)?;
+        let mine_hit = bomb_hit_by(&self.game, &action);
+
+        match mine_hit
+        {
+            Some((x_position, y_position)) =>
+            {
+                /* Flagging it instead of exposing it keeps the game going and warns everyone else off */
+                self.game.handle_action(Action::Flag { x_position, y_position })?;
+                self.penalize(player, now);
+            },
+            None =>
+            {
+                self.game.handle_action(action)?;
+            },
+        }
+
+        let changes = self.game.get_changed_tiles();
+        let points = if is_expose && mine_hit.is_none()
+        {
+            changes.iter().filter(|&&(_, _, tile)| tile.was_clicked()).count()
+        } else {
+            0
+        };
+        self.num_cleared += points;
+        self.players[player.0].score += points;
+
+        Ok(MoveResult { player, changes, points, mine_hit })
+    }
+
+    fn is_over(&self) -> bool
+    {
+        let anyone_can_move = self.players.iter().any(|player| !player.eliminated && !player.left);
+        let everyone_out = !self.players.is_empty() && !anyone_can_move;
+
+        *self.game.get_state() == State::Won || self.num_cleared >= self.num_safe_tiles || everyone_out
+    }
+
+    /// Everyone tied for the highest score once the game is over, eliminated players can't win
+    fn get_winners(&self) -> Vec<PlayerId>
+    {
+        if !self.is_over()
+        {
+            return Vec::new();
+        }
+
+        let contenders = || self.players.iter().enumerate().filter(|&(_, player)| !player.eliminated);
+        let best = match contenders().map(|(_, player)| player.score).max()
+        {
+            Some(best) => best,
+            None => return Vec::new(),
+        };
+
+        contenders()
+            .filter(|&(_, player)| player.score == best)
+            .map(|(index, _)| PlayerId(index))
+            .collect()
+    }
+
+    fn get_game(&self, _player: Option<PlayerId>) -> &Minesweeper
+    {
+        &self.game
+    }
+
+    fn is_shared(&self) -> bool
+    {
+        true
+    }
+
+    fn get_players_update(&self) -> ToUiCommand<'static>
+    {
+        ToUiCommand::Players { players: self.get_players() }
+    }
+
+    /// Frozen players count down
+    fn is_ticking(&self) -> bool
+    {
+        let now = Instant::now();
+
+        !self.is_over() && self.players.iter().any(|player| player.frozen_until.map(|until| until > now).unwrap_or(false))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use ::common::{Horizontal, Vertical};
+    use ::errors::Error;
+
+    fn session(penalty: MinePenalty) -> (CompetitiveSession, PlayerId, PlayerId)
+    {
+        let mut game = Minesweeper::new(Horizontal(9), Vertical(9), 10).unwrap();
+        game.set_seed(Some(42));
+        game.set_first_click_policy(FirstClickPolicy::SafeOpening);
+
+        let mut session = CompetitiveSession::new(game, penalty);
+        let first = session.join("first".to_string()).unwrap();
+        let second = session.join("second".to_string()).unwrap();
+        session.handle_action(first, Action::Expose { x_position: 4, y_position: 4 }).unwrap();
+
+        (session, first, second)
+    }
+
+    fn find_tile(session: &CompetitiveSession, bomb: bool) -> (usize, usize)
+    {
+        let game = session.get_game(None);
+
+        game.get_tiles().enumerate()
+            .find(|&(x, y, tile)| game.is_bomb_at(&x, &y) == bomb && !tile.is_shown() && !tile.has_flag())
+            .map(|(x, y, _)| (x.0, y.0))
+            .unwrap()
+    }
+
+    fn num_clicked(session: &CompetitiveSession) -> usize
+    {
+        session.get_game(None).get_tiles().iter().filter(|tile| tile.was_clicked()).count()
+    }
+
+    #[test]
+    fn players_only_score_the_tiles_they_opened()
+    {
+        let (mut session, first, second) = session(MinePenalty::Eliminate);
+        let num_opened = num_clicked(&session);
+        assert!(num_opened > 1);
+
+        let players = session.get_players();
+        assert_eq!(players[first.0].score, num_opened);
+        assert_eq!(players[second.0].score, 0);
+
+        let (x_position, y_position) = find_tile(&session, false);
+        let result = session.handle_action(second, Action::Expose { x_position, y_position }).unwrap();
+        assert!(result.points > 0);
+
+        let players = session.get_players();
+        assert_eq!(players[first.0].score, num_opened);
+        assert_eq!(players[second.0].score, result.points);
+        assert_eq!(num_clicked(&session), num_opened + result.points);
+
+        /* Flags are moves but don't score */
+        let (x_position, y_position) = find_tile(&session, false);
+        assert_eq!(session.handle_action(first, Action::Flag { x_position, y_position }).unwrap().points, 0);
+        assert_eq!(session.get_players()[first.0].score, num_opened);
+    }
+
+    #[test]
+    fn a_mine_is_flagged_and_freezes_only_the_player_that_hit_it()
+    {
+        let (mut session, first, second) = session(MinePenalty::Freeze { seconds: 60 });
+        let score = session.get_players()[first.0].score;
+
+        let (x_position, y_position) = find_tile(&session, true);
+        let result = session.handle_action(first, Action::Expose { x_position, y_position }).unwrap();
+        assert_eq!(result.mine_hit, Some((x_position, y_position)));
+        assert_eq!(result.points, 0);
+
+        let game = session.get_game(None);
+        let tile = game.get_tiles()[(Horizontal(x_position), Vertical(y_position))];
+        assert!(tile.has_flag() && !tile.is_shown());
+        assert_eq!(*game.get_state(), State::InProgress);
+        assert!(!session.is_over());
+
+        let players = session.get_players();
+        assert_eq!(players[first.0].score, score);
+        assert_eq!(players[first.0].mines_hit, 1);
+        match players[first.0].status
+        {
+            PlayerStatus::Frozen { remaining_ms } => assert!(remaining_ms > 0 && remaining_ms <= 60_000),
+            other => panic!("expected the player to be frozen, got {:?}", other),
+        }
+        assert_eq!(players[second.0].status, PlayerStatus::Playing);
+
+        let (x_position, y_position) = find_tile(&session, false);
+        match session.handle_action(first, Action::Expose { x_position, y_position })
+        {
+            Err(Error(ErrorKind::PlayerFrozen(0, _), _)) => {},
+            other => panic!("expected the frozen player to be refused, got {:?}", other),
+        }
+        session.handle_action(second, Action::Expose { x_position, y_position }).unwrap();
+    }
+
+    #[test]
+    fn a_mine_eliminates_the_player_when_that_is_the_penalty()
+    {
+        let (mut session, first, second) = session(MinePenalty::Eliminate);
+
+        let (x_position, y_position) = find_tile(&session, true);
+        session.handle_action(second, Action::Expose { x_position, y_position }).unwrap();
+        assert_eq!(session.get_players()[second.0].status, PlayerStatus::Eliminated);
+        assert!(!session.is_over());
+
+        let (x_position, y_position) = find_tile(&session, false);
+        match session.handle_action(second, Action::Expose { x_position, y_position })
+        {
+            Err(Error(ErrorKind::PlayerEliminated(1), _)) => {},
+            other => panic!("expected the eliminated player to be refused, got {:?}", other),
+        }
+
+        /* Once nobody is left who can move the game is over and only players still in can win */
+        let (x_position, y_position) = find_tile(&session, true);
+        session.handle_action(first, Action::Expose { x_position, y_position }).unwrap();
+        assert!(session.is_over());
+        assert!(session.get_winners().is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+use ::errors::{ErrorKind, Result};
+
+use ::common::{Horizontal, Vertical};
+use ::engine::minesweeper::{Action, Minesweeper, State};
+use ::protocol::ToUiCommand;
+use super::{MoveResult, PlayerId, Session};
+
+
+/// Where one player is and what they're suggesting, sent to everyone else
+#[derive(Serialize, Clone, Debug)]
+pub struct PlayerPresence
+{
+    pub id: PlayerId,
+    pub name: String,
+    /// x, y of the tile the player is pointing at, if they've pointed at one yet
+    pub cursor: Option<(usize, usize)>,
+    /// x, y of every flag the player is suggesting
+    pub pending_flags: Vec<(usize, usize)>,
+    /// Tiles this player's moves opened
+    pub num_opened: usize,
+}
+
+#[derive(Debug)]
+struct Player
+{
+    name: String,
+    cursor: Option<(usize, usize)>,
+    pending_flags: Vec<(usize, usize)>,
+    num_opened: usize,
+    left: bool,
+}
+
+/// Everyone solves the same field together and wins or loses together, any player exposing a bomb loses it for all.
+/// Cursors and pending flags are only presence, they never change the field
+#[derive(Debug)]
+pub struct CoopSession
+{
+    game: Minesweeper,
+    /// Indexed by `PlayerId`, players that left keep their slot so ids stay unique
+    players: Vec<Player>,
+}
+
+impl CoopSession
+{
+    pub fn new(game: Minesweeper) -> CoopSession
+    {
+        CoopSession {
+            game,
+            players: Vec::new(),
+        }
+    }
+
+    fn player_mut(&mut self, player: PlayerId) -> Result<&mut Player>
+    {
+        match self.players.get_mut(player.0).filter(|found| !found.left)
+        {
+            Some(found) => Ok(found),
+            None => bail!(ErrorKind::UnknownPlayer(player.0)),
+        }
+    }
+
+    fn check_bounds(&self, x: usize, y: usize) -> Result<()>
+    {
+        match self.game.get_tiles().get(&Horizontal(x), &Vertical(y))
+        {
+            Some(_) => Ok(()),
+            None => bail!(ErrorKind::OutOfBounds(x, y, self.game.get_width().0, self.game.get_height().0)),
+        }
+    }
+
+    /// A suggestion is settled once anyone flags its tile or it's shown
+    fn drop_settled_pending_flags(&mut self)
+    {
+        let tiles = self.game.get_tiles();
+
+        for player in &mut self.players
+        {
+            player.pending_flags.retain(|&(x, y)| {
+                let tile = tiles[(Horizontal(x), Vertical(y))];
+                !tile.has_flag() && !tile.is_shown()
+            });
+        }
+    }
+
+    pub fn get_presence(&self) -> Vec<PlayerPresence>
+    {
+        self.players.iter().enumerate()
+            .filter(|&(_, player)| !player.left)
+            .map(|(index, player)| PlayerPresence {
+                id: PlayerId(index),
+                name: player.name.clone(),
+                cursor: player.cursor,
+                pending_flags: player.pending_flags.clone(),
+                num_opened: player.num_opened,
+            })
+            .collect()
+    }
+}
+
+impl Session for CoopSession
+{
+    fn join(&mut self, name: String) -> Result<PlayerId>
+    {
+        self.players.push(Player {
+            name,
+            cursor: None,
+            pending_flags: Vec::new(),
+            num_opened: 0,
+            left: false,
+        });
+
+        Ok(PlayerId(self.players.len() - 1))
+    }
+
+    /// Their cursor and pending flags go with them
+    fn leave(&mut self, player: PlayerId) -> Result<()>
+    {
+        let found = self.player_mut(player)?;
+        found.left = true;
+        found.cursor = None;
+        found.pending_flags.clear();
+
+        Ok(())
+    }
+
+    fn handle_action(&mut self, player: PlayerId, action: Action) -> Result<MoveResult>
+    {
+        self.player_mut(player)?;
+
+        let mut result = MoveResult { player, changes: Vec::new(), points: 0, mine_hit: None };
+
+        match action
+        {
+            Action::MoveCursor { x_position, y_position } =>
+            {
+                self.check_bounds(x_position, y_position)?;
+                self.player_mut(player)?.cursor = Some((x_position, y_position));
+            },
+            Action::MarkPending { x_position, y_position } =>
+            {
+                self.check_bounds(x_position, y_position)?;
+
+                let tile = self.game.get_tiles()[(Horizontal(x_position), Vertical(y_position))];
+                let (x, y) = (x_position as i64, y_position as i64);
+                if tile.has_flag()
+                {
+                    bail!(ErrorKind::AlreadyFlagged(x, y));
+                }
+                if tile.is_shown()
+                {
+                    bail!(ErrorKind::AlreadyShown(x, y));
+                }
+
+                let found = self.player_mut(player)?;
+                if !found.pending_flags.contains(&(x_position, y_position))
+                {
+                    found.pending_flags.push((x_position, y_position));
+                }
+            },
+            Action::UnmarkPending { x_position, y_position } =>
+            {
+                self.player_mut(player)?.pending_flags.retain(|&pending| pending != (x_position, y_position));
+            },
+            Action::Flag { .. } | Action::Unflag { .. } | Action::Expose { .. } | Action::Chord { .. } =>
+            {
+                let is_expose = matches!(action, Action::Expose { .. } | Action::Chord { .. });
+
+                self.game.handle_action(action)?;
+                self.drop_settled_pending_flags();
+
+                result.changes = self.game.get_changed_tiles();
+                if is_expose
+                {
+                    result.points = result.changes.iter().filter(|&&(_, _, tile)| tile.was_clicked()).count();
+                    self.players[player.0].num_opened += result.points;
+                }
+            },
+            _ => bail!(ErrorKind::Unsupported(format!("{:?}", action))),
+        };
+
+        Ok(result)
+    }
+
+    fn is_over(&self) -> bool
+    {
+        match self.game.get_state()
+        {
+            State::Won | State::Loss => true,
+            State::New | State::InProgress => false,
+        }
+    }
+
+    /// Everyone still playing when the field is won, nobody when it's lost
+    fn get_winners(&self) -> Vec<PlayerId>
+    {
+        if *self.game.get_state() != State::Won
+        {
+            return Vec::new();
+        }
+
+        self.get_presence().iter().map(|presence| presence.id).collect()
+    }
+
+    fn get_game(&self, _player: Option<PlayerId>) -> &Minesweeper
+    {
+        &self.game
+    }
+
+    fn is_shared(&self) -> bool
+    {
+        true
+    }
+
+    fn get_players_update(&self) -> ToUiCommand<'static>
+    {
+        ToUiCommand::Presence { players: self.get_presence() }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use ::engine::minesweeper::FirstClickPolicy;
+    use ::errors::Error;
+
+    fn session() -> (CoopSession, PlayerId, PlayerId)
+    {
+        let mut game = Minesweeper::new(Horizontal(9), Vertical(9), 10).unwrap();
+        game.set_seed(Some(42));
+        game.set_first_click_policy(FirstClickPolicy::SafeOpening);
+
+        let mut session = CoopSession::new(game);
+        let first = session.join("first".to_string()).unwrap();
+        let second = session.join("second".to_string()).unwrap();
+        session.handle_action(first, Action::Expose { x_position: 4, y_position: 4 }).unwrap();
+
+        (session, first, second)
+    }
+
+    /// Hidden tiles that aren't bombs, so exposing one doesn't end the game
+    fn hidden_safe_tiles(session: &CoopSession) -> Vec<(usize, usize)>
+    {
+        let game = session.get_game(None);
+
+        game.get_tiles().enumerate()
+            .filter(|&(x, y, tile)| !game.is_bomb_at(&x, &y) && !tile.is_shown())
+            .map(|(x, y, _)| (x.0, y.0))
+            .collect()
+    }
+
+    fn pending_flags(session: &CoopSession, player: PlayerId) -> Vec<(usize, usize)>
+    {
+        session.get_presence().into_iter()
+            .find(|presence| presence.id == player)
+            .map(|presence| presence.pending_flags)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn pending_flags_only_go_on_hidden_unflagged_tiles()
+    {
+        let (mut session, first, _) = session();
+        let hidden = hidden_safe_tiles(&session);
+        let (x_position, y_position) = hidden[0];
+
+        let result = session.handle_action(first, Action::MarkPending { x_position, y_position }).unwrap();
+        assert!(result.changes.is_empty());
+        session.handle_action(first, Action::MarkPending { x_position, y_position }).unwrap();
+        assert_eq!(pending_flags(&session, first), vec![(x_position, y_position)]);
+
+        match session.handle_action(first, Action::MarkPending { x_position: 4, y_position: 4 })
+        {
+            Err(Error(ErrorKind::AlreadyShown(4, 4), _)) => {},
+            other => panic!("expected a shown tile to be refused, got {:?}", other),
+        }
+
+        /* Numbers around the opening are shown without being clicked, they can't be bombs either */
+        let number = session.get_game(None).get_tiles().enumerate()
+            .find(|&(_, _, tile)| tile.is_shown() && !tile.was_clicked())
+            .map(|(x, y, _)| (x.0, y.0))
+            .unwrap();
+        match session.handle_action(first, Action::MarkPending { x_position: number.0, y_position: number.1 })
+        {
+            Err(Error(ErrorKind::AlreadyShown(..), _)) => {},
+            other => panic!("expected a shown number to be refused, got {:?}", other),
+        }
+
+        let (x_position, y_position) = hidden[1];
+        session.handle_action(first, Action::Flag { x_position, y_position }).unwrap();
+        match session.handle_action(first, Action::MarkPending { x_position, y_position })
+        {
+            Err(Error(ErrorKind::AlreadyFlagged(..), _)) => {},
+            other => panic!("expected a flagged tile to be refused, got {:?}", other),
+        }
+
+        match session.handle_action(first, Action::MarkPending { x_position: 9, y_position: 0 })
+        {
+            Err(Error(ErrorKind::OutOfBounds(9, 0, 9, 9), _)) => {},
+            other => panic!("expected a tile off the field to be refused, got {:?}", other),
+        }
+        assert_eq!(pending_flags(&session, first), vec![hidden[0]]);
+    }
+
+    #[test]
+    fn flagging_or_exposing_a_tile_settles_everyones_pending_flag_on_it()
+    {
+        let (mut session, first, second) = session();
+        let hidden = hidden_safe_tiles(&session);
+        for &(x_position, y_position) in &hidden[..2]
+        {
+            session.handle_action(first, Action::MarkPending { x_position, y_position }).unwrap();
+            session.handle_action(second, Action::MarkPending { x_position, y_position }).unwrap();
+        }
+
+        /* Someone else flagging it confirms the suggestion */
+        let (x_position, y_position) = hidden[0];
+        session.handle_action(second, Action::Flag { x_position, y_position }).unwrap();
+        assert_eq!(pending_flags(&session, first), vec![hidden[1]]);
+        assert_eq!(pending_flags(&session, second), vec![hidden[1]]);
+
+        /* Exposing it shows the suggestion was wrong */
+        let (x_position, y_position) = hidden[1];
+        session.handle_action(first, Action::Expose { x_position, y_position }).unwrap();
+        assert!(pending_flags(&session, first).is_empty());
+        assert!(pending_flags(&session, second).is_empty());
+    }
+
+    #[test]
+    fn pending_flags_are_cancelled_by_unmarking_or_leaving()
+    {
+        let (mut session, first, second) = session();
+        let hidden = hidden_safe_tiles(&session);
+        let (x_position, y_position) = hidden[0];
+        session.handle_action(first, Action::MarkPending { x_position, y_position }).unwrap();
+        session.handle_action(second, Action::MarkPending { x_position, y_position }).unwrap();
+
+        /* Unmarking only takes back the player's own suggestion */
+        session.handle_action(first, Action::UnmarkPending { x_position, y_position }).unwrap();
+        assert!(pending_flags(&session, first).is_empty());
+        assert_eq!(pending_flags(&session, second), vec![(x_position, y_position)]);
+
+        session.handle_action(second, Action::MoveCursor { x_position, y_position }).unwrap();
+        session.leave(second).unwrap();
+        assert!(session.get_presence().iter().all(|presence| presence.id != second));
+        assert!(session.players[second.0].pending_flags.is_empty());
+        assert_eq!(session.players[second.0].cursor, None);
+
+        let tile = session.get_game(None).get_tiles()[(Horizontal(x_position), Vertical(y_position))];
+        assert!(!tile.has_flag() && !tile.is_shown());
+    }
+}
@@ -0,0 +1,317 @@
+use ::errors::{ErrorKind, Result};
+
+use ::common::{Horizontal, Vertical};
+use ::engine::minesweeper::{Action, FirstClickPolicy, Minesweeper, State};
+use ::protocol::ToUiCommand;
+use super::{MoveResult, PlayerId, Session};
+use super::hotseat::SeatSummary;
+
+
+pub const NUM_PLAYERS: usize = 2;
+
+#[derive(Debug)]
+struct Seat
+{
+    name: String,
+    /// x, y of every bomb this player found
+    claimed: Vec<(usize, usize)>,
+}
+
+/// Two players take turns looking for the bombs instead of avoiding them.
+/// Finding a bomb claims it and earns another turn, a safe tile passes the turn.
+/// First to claim more than half of the bombs wins
+#[derive(Debug)]
+pub struct FlagsSession
+{
+    game: Minesweeper,
+    seats: Vec<Seat>,
+    /// Index into `seats` of whoever's turn it is
+    turn: usize,
+    /// Whoever left, the other player wins straight away
+    forfeited: Option<PlayerId>,
+}
+
+impl FlagsSession
+{
+    /// The first click is kept safe, a free point for whoever goes first wouldn't be fair
+    pub fn new(mut game: Minesweeper) -> FlagsSession
+    {
+        if game.get_first_click_policy() == FirstClickPolicy::Unprotected
+        {
+            game.set_first_click_policy(FirstClickPolicy::SafeTile);
+        }
+
+        FlagsSession {
+            game,
+            seats: Vec::new(),
+            turn: 0,
+            forfeited: None,
+        }
+    }
+
+    pub fn get_turn(&self) -> PlayerId
+    {
+        PlayerId(self.turn)
+    }
+
+    /// Claimed bombs to win
+    pub fn get_bombs_to_win(&self) -> usize
+    {
+        self.game.get_num_bombs() / 2 + 1
+    }
+
+    /// `score` is the number of bombs claimed
+    pub fn get_seats(&self) -> Vec<SeatSummary>
+    {
+        self.seats.iter().enumerate()
+            .map(|(index, seat)| SeatSummary {
+                id: PlayerId(index),
+                name: seat.name.clone(),
+                score: seat.claimed.len(),
+                eliminated: false,
+            })
+            .collect()
+    }
+
+    /// Who found the bomb at x, y
+    pub fn get_claimed_by(&self, x: usize, y: usize) -> Option<PlayerId>
+    {
+        self.seats.iter().position(|seat| seat.claimed.contains(&(x, y))).map(PlayerId)
+    }
+
+    fn num_claimed(&self) -> usize
+    {
+        self.seats.iter().map(|seat| seat.claimed.len()).sum()
+    }
+}
+
+impl Session for FlagsSession
+{
+    /// Exactly two players, before the first move
+    fn join(&mut self, name: String) -> Result<PlayerId>
+    {
+        if self.seats.len() == NUM_PLAYERS
+        {
+            bail!(ErrorKind::PlayerCount(NUM_PLAYERS + 1, NUM_PLAYERS, NUM_PLAYERS));
+        }
+        if *self.game.get_state() != State::New
+        {
+            bail!(ErrorKind::NotAllowed(format!("{:?}", Action::Join { name }), "already being played".to_string()));
+        }
+
+        self.seats.push(Seat { name, claimed: Vec::new() });
+
+        Ok(PlayerId(self.seats.len() - 1))
+    }
+
+    /// Leaving hands the game to the other player
+    fn leave(&mut self, player: PlayerId) -> Result<()>
+    {
+        if player.0 >= self.seats.len()
+        {
+            bail!(ErrorKind::UnknownPlayer(player.0));
+        }
+
+        if self.forfeited.is_none()
+        {
+            self.forfeited = Some(player);
+        }
+
+        Ok(())
+    }
+
+    /// Only `Expose`, flags are how claimed bombs are shown so players can't place their own
+    fn handle_action(&mut self, player: PlayerId, action: Action) -> Result<MoveResult>
+    {
+        if self.seats.len() < NUM_PLAYERS
+        {
+            bail!(ErrorKind::PlayerCount(self.seats.len(), NUM_PLAYERS, NUM_PLAYERS));
+        }
+        if self.is_over()
+        {
+            bail!(ErrorKind::NotAllowed(format!("{:?}", action), "over".to_string()));
+        }
+        if player.0 >= self.seats.len()
+        {
+            bail!(ErrorKind::UnknownPlayer(player.0));
+        }
+        if player.0 != self.turn
+        {
+            bail!(ErrorKind::NotYourTurn(player.0, self.turn));
+        }
+
+        let (x_position, y_position) = match action
+        {
+            Action::Expose { x_position, y_position } => (x_position, y_position),
+            _ => bail!(ErrorKind::Unsupported(format!("{:?}", action))),
+        };
+
+        self.game.validate_action(&action)?;
+
+        /* The opposite of the normal rules, a bomb is a point and another go while a safe tile ends the turn */
+        let mine_hit = if self.game.is_bomb_at(&Horizontal(x_position), &Vertical(y_position))
+        {
+            self.game.handle_action(Action::Flag { x_position, y_position })?;
+            self.seats[player.0].claimed.push((x_position, y_position));
+            Some((x_position, y_position))
+        } else {
+            self.game.handle_action(action)?;
+            self.turn = (self.turn + 1) % NUM_PLAYERS;
+            None
+        };
+
+        Ok(MoveResult {
+            player,
+            changes: self.game.get_changed_tiles(),
+            points: if mine_hit.is_some() { 1 } else { 0 },
+            mine_hit,
+        })
+    }
+
+    /// Once someone has more than half of the bombs, every bomb is claimed in a tie or someone left
+    fn is_over(&self) -> bool
+    {
+        let bombs_to_win = self.get_bombs_to_win();
+
+        self.forfeited.is_some() ||
+            self.seats.iter().any(|seat| seat.claimed.len() >= bombs_to_win) ||
+            self.num_claimed() >= self.game.get_num_bombs()
+    }
+
+    fn get_winners(&self) -> Vec<PlayerId>
+    {
+        if !self.is_over()
+        {
+            return Vec::new();
+        }
+
+        let contenders = || self.seats.iter().enumerate().filter(|&(index, _)| self.forfeited != Some(PlayerId(index)));
+        let best = contenders().map(|(_, seat)| seat.claimed.len()).max().unwrap_or(0);
+        contenders()
+            .filter(|&(_, seat)| seat.claimed.len() == best)
+            .map(|(index, _)| PlayerId(index))
+            .collect()
+    }
+
+    fn get_game(&self, _player: Option<PlayerId>) -> &Minesweeper
+    {
+        &self.game
+    }
+
+    fn is_shared(&self) -> bool
+    {
+        true
+    }
+
+    fn get_players_update(&self) -> ToUiCommand<'static>
+    {
+        ToUiCommand::Turn { player: self.get_turn(), players: self.get_seats() }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use ::errors::Error;
+
+    /// Both seats taken and the first, safe, move made so the turn has passed to the second seat
+    fn session() -> (FlagsSession, PlayerId, PlayerId)
+    {
+        let mut game = Minesweeper::new(Horizontal(9), Vertical(9), 10).unwrap();
+        game.set_seed(Some(42));
+
+        let mut session = FlagsSession::new(game);
+        let first = session.join("first".to_string()).unwrap();
+        let second = session.join("second".to_string()).unwrap();
+        session.handle_action(first, Action::Expose { x_position: 4, y_position: 4 }).unwrap();
+
+        (session, first, second)
+    }
+
+    fn find_tiles(session: &FlagsSession, bomb: bool) -> Vec<(usize, usize)>
+    {
+        let game = session.get_game(None);
+
+        game.get_tiles().enumerate()
+            .filter(|&(x, y, tile)| game.is_bomb_at(&x, &y) == bomb && !tile.was_clicked() && !tile.has_flag())
+            .map(|(x, y, _)| (x.0, y.0))
+            .collect()
+    }
+
+    #[test]
+    fn finding_a_bomb_keeps_the_turn_and_a_safe_tile_passes_it()
+    {
+        let (mut session, first, second) = session();
+        assert_eq!(session.get_turn(), second);
+
+        let (x_position, y_position) = find_tiles(&session, true)[0];
+        match session.handle_action(first, Action::Expose { x_position, y_position })
+        {
+            Err(Error(ErrorKind::NotYourTurn(0, 1), _)) => {},
+            other => panic!("expected a move out of turn to be refused, got {:?}", other),
+        }
+
+        let result = session.handle_action(second, Action::Expose { x_position, y_position }).unwrap();
+        assert_eq!(result.mine_hit, Some((x_position, y_position)));
+        assert_eq!(result.points, 1);
+        assert_eq!(session.get_turn(), second);
+        assert_eq!(session.get_claimed_by(x_position, y_position), Some(second));
+        assert!(session.get_game(None).get_tiles()[(Horizontal(x_position), Vertical(y_position))].has_flag());
+        assert_eq!(*session.get_game(None).get_state(), State::InProgress);
+
+        let (x_position, y_position) = find_tiles(&session, false)[0];
+        let result = session.handle_action(second, Action::Expose { x_position, y_position }).unwrap();
+        assert_eq!(result.mine_hit, None);
+        assert_eq!(result.points, 0);
+        assert_eq!(session.get_turn(), first);
+
+        let scores: Vec<usize> = session.get_seats().iter().map(|seat| seat.score).collect();
+        assert_eq!(scores, vec![0, 1]);
+    }
+
+    #[test]
+    fn the_game_ends_once_one_side_has_more_than_half_of_the_bombs()
+    {
+        let (mut session, _, second) = session();
+        assert_eq!(session.get_bombs_to_win(), 6);
+
+        for num_claimed in 1..=6
+        {
+            assert!(!session.is_over());
+            assert!(session.get_winners().is_empty());
+
+            let (x_position, y_position) = find_tiles(&session, true)[0];
+            session.handle_action(second, Action::Expose { x_position, y_position }).unwrap();
+            assert_eq!(session.get_seats()[second.0].score, num_claimed);
+        }
+
+        assert!(session.is_over());
+        assert_eq!(session.get_winners(), vec![second]);
+
+        let (x_position, y_position) = find_tiles(&session, true)[0];
+        match session.handle_action(second, Action::Expose { x_position, y_position })
+        {
+            Err(Error(ErrorKind::NotAllowed(..), _)) => {},
+            other => panic!("expected moves after the end to be refused, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn leaving_hands_the_game_to_the_other_player()
+    {
+        let (mut session, first, second) = session();
+        match session.join("third".to_string())
+        {
+            Err(Error(ErrorKind::PlayerCount(3, 2, 2), _)) => {},
+            other => panic!("expected a third player to be refused, got {:?}", other),
+        }
+
+        let (x_position, y_position) = find_tiles(&session, true)[0];
+        session.handle_action(second, Action::Expose { x_position, y_position }).unwrap();
+        session.leave(second).unwrap();
+
+        assert!(session.is_over());
+        assert_eq!(session.get_winners(), vec![first]);
+    }
+}
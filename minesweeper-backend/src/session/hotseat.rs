@@ -0,0 +1,396 @@
+use ::errors::{ErrorKind, Result};
+
+use ::engine::minesweeper::{Action, FirstClickPolicy, Minesweeper, State};
+use ::protocol::ToUiCommand;
+use super::{bomb_hit_by, MoveResult, PlayerId, Session};
+
+
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 4;
+
+/// One player as the scoreboard shows them
+#[derive(Serialize, Clone, Debug)]
+pub struct SeatSummary
+{
+    pub id: PlayerId,
+    pub name: String,
+    /// Tiles this player opened
+    pub score: usize,
+    pub eliminated: bool,
+}
+
+#[derive(Debug)]
+struct Seat
+{
+    name: String,
+    score: usize,
+    eliminated: bool,
+}
+
+/// Players take turns on one machine, a turn is one expose or chord while flags are free.
+/// Exposing a bomb eliminates the player and flags it for the rest.
+/// Last player standing wins, otherwise whoever opened the most tiles once the field is done
+#[derive(Debug)]
+pub struct HotSeatSession
+{
+    game: Minesweeper,
+    seats: Vec<Seat>,
+    /// Index into `seats` of whoever's turn it is
+    turn: usize,
+
+    num_safe_tiles: usize,
+    num_cleared: usize,
+}
+
+impl HotSeatSession
+{
+    /// The first move is always protected, nobody should be out before having a real turn
+    pub fn new(mut game: Minesweeper) -> HotSeatSession
+    {
+        if game.get_first_click_policy() == FirstClickPolicy::Unprotected
+        {
+            game.set_first_click_policy(FirstClickPolicy::SafeTile);
+        }
+
+        let num_safe_tiles = game.get_width().0 * game.get_height().0 - game.get_num_bombs();
+
+        HotSeatSession {
+            game,
+            seats: Vec::new(),
+            turn: 0,
+            num_safe_tiles,
+            num_cleared: 0,
+        }
+    }
+
+    /// Convenience for when every name is known up front
+    pub fn with_players(game: Minesweeper, names: Vec<String>) -> Result<HotSeatSession>
+    {
+        if names.len() < MIN_PLAYERS || names.len() > MAX_PLAYERS
+        {
+            bail!(ErrorKind::PlayerCount(names.len(), MIN_PLAYERS, MAX_PLAYERS));
+        }
+
+        let mut session = HotSeatSession::new(game);
+        for name in names
+        {
+            session.join(name)?;
+        }
+
+        Ok(session)
+    }
+
+    pub fn get_turn(&self) -> PlayerId
+    {
+        PlayerId(self.turn)
+    }
+
+    pub fn get_seats(&self) -> Vec<SeatSummary>
+    {
+        self.seats.iter().enumerate()
+            .map(|(index, seat)| SeatSummary {
+                id: PlayerId(index),
+                name: seat.name.clone(),
+                score: seat.score,
+                eliminated: seat.eliminated,
+            })
+            .collect()
+    }
+
+    fn num_standing(&self) -> usize
+    {
+        self.seats.iter().filter(|seat| !seat.eliminated).count()
+    }
+
+    /// Skips anyone who's been eliminated
+    fn next_turn(&mut self)
+    {
+        if self.num_standing() == 0
+        {
+            return;
+        }
+
+        loop
+        {
+            self.turn = (self.turn + 1) % self.seats.len();
+            if !self.seats[self.turn].eliminated
+            {
+                return;
+            }
+        }
+    }
+}
+
+impl Session for HotSeatSession
+{
+    /// Only before the first move, up to `MAX_PLAYERS`
+    fn join(&mut self, name: String) -> Result<PlayerId>
+    {
+        if *self.game.get_state() != State::New
+        {
+            bail!(ErrorKind::NotAllowed(format!("{:?}", Action::Join { name }), "already being played".to_string()));
+        }
+        if self.seats.len() == MAX_PLAYERS
+        {
+            bail!(ErrorKind::PlayerCount(MAX_PLAYERS + 1, MIN_PLAYERS, MAX_PLAYERS));
+        }
+
+        self.seats.push(Seat { name, score: 0, eliminated: false });
+
+        Ok(PlayerId(self.seats.len() - 1))
+    }
+
+    /// Leaving in the middle counts as being eliminated
+    fn leave(&mut self, player: PlayerId) -> Result<()>
+    {
+        match self.seats.get_mut(player.0)
+        {
+            Some(seat) => seat.eliminated = true,
+            None => bail!(ErrorKind::UnknownPlayer(player.0)),
+        }
+
+        if self.turn == player.0
+        {
+            self.next_turn();
+        }
+
+        Ok(())
+    }
+
+    /// Flags don't end the turn, an expose or chord does
+    fn handle_action(&mut self, player: PlayerId, action: Action) -> Result<MoveResult>
+    {
+        if self.seats.len() < MIN_PLAYERS
+        {
+            bail!(ErrorKind::PlayerCount(self.seats.len(), MIN_PLAYERS, MAX_PLAYERS));
+        }
+        if self.is_over()
+        {
+            bail!(ErrorKind::NotAllowed(format!("{:?}", action), "over".to_string()));
+        }
+        match self.seats.get(player.0)
+        {
+            Some(seat) if seat.eliminated => bail!(ErrorKind::PlayerEliminated(player.0)),
+            Some(_) if player.0 != self.turn => bail!(ErrorKind::NotYourTurn(player.0, self.turn)),
+            Some(_) => {},
+            None => bail!(ErrorKind::UnknownPlayer(player.0)),
+        }
+
+        let ends_turn = match action
+        {
+            Action::Expose { .. } | Action::Chord { .. } => true,
+            Action::Flag { .. } | Action::Unflag { .. } => false,
+            _ => bail!(ErrorKind::Unsupported(format!("{:?}", action))),
+        };
+
+        self.game.validate_action(&action)?;
+        let mine_hit = bomb_hit_by(&self.game, &action);
+
+        match mine_hit
+        {
+            Some((x_position, y_position)) =>
+            {
+                self.game.handle_action(Action::Flag { x_position, y_position })?;
+                self.seats[player.0].eliminated = true;
+            },
+            None =>
+            {
+                self.game.handle_action(action)?;
+            },
+        }
+
+        let changes = self.game.get_changed_tiles();
+        let points = if ends_turn && mine_hit.is_none()
+        {
+            changes.iter().filter(|&&(_, _, tile)| tile.was_clicked()).count()
+        } else {
+            0
+        };
+        self.num_cleared += points;
+        self.seats[player.0].score += points;
+
+        if ends_turn
+        {
+            self.next_turn();
+        }
+
+        Ok(MoveResult { player, changes, points, mine_hit })
+    }
+
+    fn is_over(&self) -> bool
+    {
+        let field_done = *self.game.get_state() == State::Won || self.num_cleared >= self.num_safe_tiles;
+
+        self.seats.len() >= MIN_PLAYERS && (self.num_standing() <= 1 || field_done)
+    }
+
+    fn get_winners(&self) -> Vec<PlayerId>
+    {
+        if !self.is_over()
+        {
+            return Vec::new();
+        }
+
+        let standing = || self.seats.iter().enumerate().filter(|&(_, seat)| !seat.eliminated);
+        let best = match standing().map(|(_, seat)| seat.score).max()
+        {
+            Some(best) => best,
+            None => return Vec::new(),
+        };
+
+        standing()
+            .filter(|&(_, seat)| seat.score == best)
+            .map(|(index, _)| PlayerId(index))
+            .collect()
+    }
+
+    fn get_game(&self, _player: Option<PlayerId>) -> &Minesweeper
+    {
+        &self.game
+    }
+
+    fn is_shared(&self) -> bool
+    {
+        true
+    }
+
+    fn get_players_update(&self) -> ToUiCommand<'static>
+    {
+        ToUiCommand::Turn { player: self.get_turn(), players: self.get_seats() }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use ::common::{Horizontal, Vertical};
+    use ::errors::Error;
+
+    fn session(num_players: usize) -> HotSeatSession
+    {
+        let mut game = Minesweeper::new(Horizontal(9), Vertical(9), 10).unwrap();
+        game.set_seed(Some(42));
+        game.set_first_click_policy(FirstClickPolicy::SafeOpening);
+
+        let names = (0..num_players).map(|index| format!("player {}", index)).collect();
+        HotSeatSession::with_players(game, names).unwrap()
+    }
+
+    /// Tiles still to click, the numbers shown around an opening count as they're worth a point
+    fn find_tiles(session: &HotSeatSession, bomb: bool) -> Vec<(usize, usize)>
+    {
+        let game = session.get_game(None);
+
+        game.get_tiles().enumerate()
+            .filter(|&(x, y, tile)| game.is_bomb_at(&x, &y) == bomb && !tile.was_clicked() && !tile.has_flag())
+            .map(|(x, y, _)| (x.0, y.0))
+            .collect()
+    }
+
+    fn expose(session: &mut HotSeatSession, player: usize, bomb: bool) -> MoveResult
+    {
+        let (x_position, y_position) = find_tiles(session, bomb)[0];
+
+        session.handle_action(PlayerId(player), Action::Expose { x_position, y_position }).unwrap()
+    }
+
+    #[test]
+    fn players_take_turns_and_only_exposing_ends_one()
+    {
+        let mut session = session(3);
+        assert_eq!(session.get_turn(), PlayerId(0));
+        match session.handle_action(PlayerId(1), Action::Expose { x_position: 4, y_position: 4 })
+        {
+            Err(Error(ErrorKind::NotYourTurn(1, 0), _)) => {},
+            other => panic!("expected a move out of turn to be refused, got {:?}", other),
+        }
+
+        session.handle_action(PlayerId(0), Action::Expose { x_position: 4, y_position: 4 }).unwrap();
+        assert_eq!(session.get_turn(), PlayerId(1));
+
+        let (x_position, y_position) = find_tiles(&session, true)[0];
+        session.handle_action(PlayerId(1), Action::Flag { x_position, y_position }).unwrap();
+        session.handle_action(PlayerId(1), Action::Unflag { x_position, y_position }).unwrap();
+        assert_eq!(session.get_turn(), PlayerId(1));
+
+        let result = expose(&mut session, 1, false);
+        assert_eq!(session.get_turn(), PlayerId(2));
+        expose(&mut session, 2, false);
+        assert_eq!(session.get_turn(), PlayerId(0));
+
+        let scores: Vec<usize> = session.get_seats().iter().map(|seat| seat.score).collect();
+        assert!(scores.iter().all(|&score| score > 0));
+        assert_eq!(scores[1], result.points);
+
+        match session.get_players_update()
+        {
+            ToUiCommand::Turn { player, players } => assert_eq!((player, players.len()), (PlayerId(0), 3)),
+            _ => panic!("expected the players update to be a Turn"),
+        }
+    }
+
+    #[test]
+    fn a_mine_eliminates_the_player_and_their_turns_are_skipped()
+    {
+        let mut session = session(3);
+        expose(&mut session, 0, false);
+
+        let result = expose(&mut session, 1, true);
+        let (x, y) = result.mine_hit.unwrap();
+        assert_eq!(result.points, 0);
+        assert!(session.get_game(None).get_tiles()[(Horizontal(x), Vertical(y))].has_flag());
+        assert_eq!(*session.get_game(None).get_state(), State::InProgress);
+        assert!(session.get_seats()[1].eliminated);
+        assert!(!session.is_over());
+
+        assert_eq!(session.get_turn(), PlayerId(2));
+        expose(&mut session, 2, false);
+        assert_eq!(session.get_turn(), PlayerId(0));
+        expose(&mut session, 0, false);
+        assert_eq!(session.get_turn(), PlayerId(2));
+
+        let (x_position, y_position) = find_tiles(&session, false)[0];
+        match session.handle_action(PlayerId(1), Action::Expose { x_position, y_position })
+        {
+            Err(Error(ErrorKind::PlayerEliminated(1), _)) => {},
+            other => panic!("expected the eliminated player to be refused, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn the_last_player_standing_wins()
+    {
+        let mut session = session(3);
+        expose(&mut session, 0, false);
+        let leader = session.get_seats()[0].score;
+
+        expose(&mut session, 1, false);
+        expose(&mut session, 2, true);
+        assert!(!session.is_over());
+        expose(&mut session, 0, true);
+
+        /* The last one standing wins even if someone who's out opened more */
+        assert!(session.is_over());
+        assert!(session.get_seats()[1].score < leader);
+        assert_eq!(session.get_winners(), vec![PlayerId(1)]);
+    }
+
+    #[test]
+    fn whoever_opened_the_most_wins_a_finished_field()
+    {
+        let mut session = session(2);
+        while !session.is_over()
+        {
+            let player = session.get_turn().0;
+            expose(&mut session, player, false);
+        }
+
+        let scores: Vec<usize> = session.get_seats().iter().map(|seat| seat.score).collect();
+        assert_eq!(scores.iter().sum::<usize>(), 9 * 9 - 10);
+
+        let best = *scores.iter().max().unwrap();
+        let expected: Vec<PlayerId> = (0..2).filter(|&index| scores[index] == best).map(PlayerId).collect();
+        assert_eq!(session.get_winners(), expected);
+    }
+}
@@ -0,0 +1,83 @@
+//! Games with more than one player on the same field, layered on top of the single player engine.
+//! The host owns the session and attributes every move to the player that sent it
+
+pub mod competitive;
+pub mod coop;
+pub mod race;
+pub mod hotseat;
+pub mod flags;
+
+use ::errors::Result;
+
+use ::common::{Horizontal, Vertical};
+use ::engine::minesweeper::{Action, Minesweeper};
+use ::engine::packed::PackedTile;
+use ::protocol::ToUiCommand;
+
+use std::time::Duration;
+
+
+/// A player within one session, handed out by the host in the order players join
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PlayerId(pub usize);
+
+/// What one player's action did to the shared field
+#[derive(Debug)]
+pub struct MoveResult
+{
+    pub player: PlayerId,
+    /// Empty for actions that only change the players, like moving a cursor
+    pub changes: Vec<(usize, usize, PackedTile)>,
+    /// What the move scored, tiles opened in most modes and bombs claimed in Flags
+    pub points: usize,
+    /// The bomb the player exposed if it was flagged instead of ending the game
+    pub mine_hit: Option<(usize, usize)>,
+}
+
+/// A game several players take part in through one host, which only ever touches it from one thread
+pub trait Session
+{
+    fn join(&mut self, name: String) -> Result<PlayerId>;
+    /// The player's id is never handed out again
+    fn leave(&mut self, player: PlayerId) -> Result<()>;
+    /// Moves and anything else the players do to each other, the host handles the rest like `RequestField`
+    fn handle_action(&mut self, player: PlayerId, action: Action) -> Result<MoveResult>;
+    fn is_over(&self) -> bool;
+    /// Empty until the game is over
+    fn get_winners(&self) -> Vec<PlayerId>;
+    /// The field `player` plays on, or what a client that hasn't joined gets to watch
+    fn get_game(&self, player: Option<PlayerId>) -> &Minesweeper;
+    /// Whether everyone plays on the same field, otherwise a player's tile changes only go to that player
+    fn is_shared(&self) -> bool;
+    /// What every client is sent whenever the players change, like the scoreboard
+    fn get_players_update(&self) -> ToUiCommand<'static>;
+    /// Whether the players update changes on its own as time passes, like a clock, so the host should keep sending it
+    fn is_ticking(&self) -> bool
+    {
+        false
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64
+{
+    duration.as_secs() * 1000 + u64::from(duration.subsec_millis())
+}
+
+/// The bomb a valid move would expose, for sessions where hitting one only costs the player that did it
+fn bomb_hit_by(game: &Minesweeper, action: &Action) -> Option<(usize, usize)>
+{
+    match *action
+    {
+        Action::Expose { x_position, y_position } if game.is_bomb_at(&Horizontal(x_position), &Vertical(y_position)) =>
+        {
+            Some((x_position, y_position))
+        },
+        Action::Chord { x_position, y_position } =>
+        {
+            game.get_chord_targets(&Horizontal(x_position), &Vertical(y_position)).into_iter()
+                .find(|(x, y)| game.is_bomb_at(x, y))
+                .map(|(x, y)| (x.0, y.0))
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,394 @@
+use ::errors::{ErrorKind, Result};
+
+use ::common::{Horizontal, Vertical};
+use ::engine::minesweeper::{Action, FirstClickPolicy, Minesweeper, State};
+use ::protocol::ToUiCommand;
+use super::{duration_ms, MoveResult, PlayerId, Session};
+
+use std::time::{Duration, Instant};
+
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(tag = "_type")]
+pub enum RacerStatus
+{
+    /// Joined but the race hasn't started
+    Waiting,
+    Racing,
+    /// Exposed a bomb, their field is lost
+    Dead,
+    Finished,
+    Left,
+}
+
+/// One racer as every client sees them
+#[derive(Serialize, Clone, Debug)]
+pub struct RacerProgress
+{
+    pub id: PlayerId,
+    pub name: String,
+    /// 0 to 100, how many of the safe tiles the racer has opened
+    pub percent_cleared: f64,
+    /// Since the start, stops counting once the racer is dead or finished
+    pub elapsed_ms: u64,
+    pub status: RacerStatus,
+}
+
+#[derive(Debug)]
+struct Racer
+{
+    name: String,
+    game: Minesweeper,
+    num_cleared: usize,
+    /// How long into the race the racer died or finished
+    stopped_after: Option<Duration>,
+    left: bool,
+}
+
+/// Every racer gets their own field built from the same seed and opened at the same first move,
+/// so they all solve the exact same board. First to clear every safe tile or flag every bomb wins
+#[derive(Debug)]
+pub struct RaceSession
+{
+    seed: u64,
+    first_move: (usize, usize),
+    /// What clients that haven't joined see, never played on
+    lobby: Minesweeper,
+    /// Indexed by `PlayerId`, racers that left keep their slot so ids stay unique
+    racers: Vec<Racer>,
+    num_safe_tiles: usize,
+
+    started: Option<Instant>,
+    winner: Option<PlayerId>,
+}
+
+impl RaceSession
+{
+    pub fn new(width: Horizontal, height: Vertical, num_bombs: usize, seed: u64, first_move: (usize, usize)) -> Result<RaceSession>
+    {
+        let lobby = Minesweeper::new(width, height, num_bombs)?;
+        if first_move.0 >= width.0 || first_move.1 >= height.0
+        {
+            bail!(ErrorKind::OutOfBounds(first_move.0, first_move.1, width.0, height.0));
+        }
+
+        Ok(RaceSession {
+            seed,
+            first_move,
+            lobby,
+            racers: Vec::new(),
+            num_safe_tiles: width.0 * height.0 - num_bombs,
+            started: None,
+            winner: None,
+        })
+    }
+
+    /// The opening around the first move is always safe so nobody's race is over before it starts
+    fn new_field(&self) -> Result<Minesweeper>
+    {
+        let mut game = Minesweeper::new(self.lobby.get_width(), self.lobby.get_height(), self.lobby.get_num_bombs())?;
+        game.set_seed(Some(self.seed));
+        game.set_first_click_policy(FirstClickPolicy::SafeOpening);
+
+        Ok(game)
+    }
+
+    fn racer_mut(&mut self, player: PlayerId) -> Result<&mut Racer>
+    {
+        match self.racers.get_mut(player.0).filter(|found| !found.left)
+        {
+            Some(found) => Ok(found),
+            None => bail!(ErrorKind::UnknownPlayer(player.0)),
+        }
+    }
+
+    fn start(&mut self) -> Result<()>
+    {
+        if self.started.is_some()
+        {
+            bail!(ErrorKind::NotAllowed(format!("{:?}", Action::StartRace), "racing".to_string()));
+        }
+
+        let (x_position, y_position) = self.first_move;
+        for racer in self.racers.iter_mut().filter(|racer| !racer.left)
+        {
+            racer.game.handle_action(Action::Expose { x_position, y_position })?;
+            racer.num_cleared = racer.game.get_changed_tiles().iter().filter(|&&(_, _, tile)| tile.was_clicked()).count();
+        }
+        self.started = Some(Instant::now());
+
+        Ok(())
+    }
+
+    pub fn get_seed(&self) -> u64
+    {
+        self.seed
+    }
+
+    pub fn get_progress(&self) -> Vec<RacerProgress>
+    {
+        let elapsed = self.started.map(|started| started.elapsed()).unwrap_or_default();
+
+        self.racers.iter().enumerate()
+            .map(|(index, racer)| {
+                let status = match racer.game.get_state()
+                {
+                    _ if racer.left => RacerStatus::Left,
+                    _ if self.winner == Some(PlayerId(index)) => RacerStatus::Finished,
+                    State::Loss => RacerStatus::Dead,
+                    _ if self.started.is_none() => RacerStatus::Waiting,
+                    _ => RacerStatus::Racing,
+                };
+
+                RacerProgress {
+                    id: PlayerId(index),
+                    name: racer.name.clone(),
+                    percent_cleared: 100.0 * racer.num_cleared as f64 / self.num_safe_tiles as f64,
+                    elapsed_ms: duration_ms(racer.stopped_after.unwrap_or(elapsed)),
+                    status,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Session for RaceSession
+{
+    /// Only before the race has started
+    fn join(&mut self, name: String) -> Result<PlayerId>
+    {
+        if self.started.is_some()
+        {
+            bail!(ErrorKind::NotAllowed(format!("{:?}", Action::Join { name }), "racing".to_string()));
+        }
+
+        let game = self.new_field()?;
+        self.racers.push(Racer {
+            name,
+            game,
+            num_cleared: 0,
+            stopped_after: None,
+            left: false,
+        });
+
+        Ok(PlayerId(self.racers.len() - 1))
+    }
+
+    fn leave(&mut self, player: PlayerId) -> Result<()>
+    {
+        self.racer_mut(player)?.left = true;
+
+        Ok(())
+    }
+
+    /// `StartRace` opens every racer's field, after that only moves on the racer's own field
+    fn handle_action(&mut self, player: PlayerId, action: Action) -> Result<MoveResult>
+    {
+        self.racer_mut(player)?;
+
+        let is_expose = match action
+        {
+            Action::StartRace =>
+            {
+                self.start()?;
+                return Ok(MoveResult { player, changes: Vec::new(), points: 0, mine_hit: None });
+            },
+            Action::Expose { .. } | Action::Chord { .. } => true,
+            Action::Flag { .. } | Action::Unflag { .. } => false,
+            _ => bail!(ErrorKind::Unsupported(format!("{:?}", action))),
+        };
+
+        let started = match self.started
+        {
+            Some(started) if self.winner.is_none() => started,
+            Some(_) => bail!(ErrorKind::NotAllowed(format!("{:?}", action), "over".to_string())),
+            None => bail!(ErrorKind::NotAllowed(format!("{:?}", action), "waiting for StartRace".to_string())),
+        };
+
+        let num_safe_tiles = self.num_safe_tiles;
+        let racer = self.racer_mut(player)?;
+        racer.game.handle_action(action)?;
+
+        let changes = racer.game.get_changed_tiles();
+        let points = if is_expose && *racer.game.get_state() != State::Loss
+        {
+            changes.iter().filter(|&&(_, _, tile)| tile.was_clicked()).count()
+        } else {
+            0
+        };
+        racer.num_cleared += points;
+
+        let finished = *racer.game.get_state() == State::Won || racer.num_cleared >= num_safe_tiles;
+        if finished || *racer.game.get_state() == State::Loss
+        {
+            racer.stopped_after = Some(started.elapsed());
+        }
+        if finished
+        {
+            self.winner = Some(player);
+        }
+
+        Ok(MoveResult { player, changes, points, mine_hit: None })
+    }
+
+    /// Once someone finishes or nobody is left racing
+    fn is_over(&self) -> bool
+    {
+        let anyone_racing = self.racers.iter().any(|racer| !racer.left && *racer.game.get_state() != State::Loss);
+
+        self.winner.is_some() || (self.started.is_some() && !anyone_racing)
+    }
+
+    fn get_winners(&self) -> Vec<PlayerId>
+    {
+        self.winner.into_iter().collect()
+    }
+
+    fn get_game(&self, player: Option<PlayerId>) -> &Minesweeper
+    {
+        match player.and_then(|player| self.racers.get(player.0))
+        {
+            Some(racer) => &racer.game,
+            None => &self.lobby,
+        }
+    }
+
+    fn is_shared(&self) -> bool
+    {
+        false
+    }
+
+    fn get_players_update(&self) -> ToUiCommand<'static>
+    {
+        ToUiCommand::RaceProgress { racers: self.get_progress() }
+    }
+
+    /// The clock keeps running between moves
+    fn is_ticking(&self) -> bool
+    {
+        self.started.is_some() && !self.is_over()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use ::errors::Error;
+
+    fn race(num_racers: usize) -> (RaceSession, Vec<PlayerId>)
+    {
+        let mut session = RaceSession::new(Horizontal(9), Vertical(9), 10, 42, (4, 4)).unwrap();
+        let racers = (0..num_racers).map(|index| session.join(format!("racer {}", index)).unwrap()).collect();
+
+        (session, racers)
+    }
+
+    /// Tiles still to click, numbers shown around an opening count since a racer has to click every safe tile
+    fn find_tiles(session: &RaceSession, racer: PlayerId, bomb: bool) -> Vec<(usize, usize)>
+    {
+        let game = session.get_game(Some(racer));
+
+        game.get_tiles().enumerate()
+            .filter(|&(x, y, tile)| game.is_bomb_at(&x, &y) == bomb && !tile.was_clicked() && !tile.has_flag())
+            .map(|(x, y, _)| (x.0, y.0))
+            .collect()
+    }
+
+    fn status(session: &RaceSession, racer: PlayerId) -> RacerStatus
+    {
+        session.get_progress()[racer.0].status
+    }
+
+    #[test]
+    fn every_racer_solves_the_same_board()
+    {
+        let (mut session, racers) = race(2);
+        match session.handle_action(racers[0], Action::Expose { x_position: 0, y_position: 0 })
+        {
+            Err(Error(ErrorKind::NotAllowed(..), _)) => {},
+            other => panic!("expected moves before the start to be refused, got {:?}", other),
+        }
+
+        session.handle_action(racers[1], Action::StartRace).unwrap();
+        match session.join("late".to_string())
+        {
+            Err(Error(ErrorKind::NotAllowed(..), _)) => {},
+            other => panic!("expected joining a started race to be refused, got {:?}", other),
+        }
+
+        /* Another race from the same seed and first move is the same board too */
+        let (mut other, other_racers) = race(1);
+        other.handle_action(other_racers[0], Action::StartRace).unwrap();
+
+        let first = session.get_game(Some(racers[0]));
+        for game in &[session.get_game(Some(racers[1])), other.get_game(Some(other_racers[0]))]
+        {
+            assert_eq!(first.get_tiles(), game.get_tiles());
+            for (x, y) in first.get_tiles().positions()
+            {
+                assert_eq!(first.is_bomb_at(&x, &y), game.is_bomb_at(&x, &y));
+            }
+        }
+        assert!(first.get_tiles()[(Horizontal(4), Vertical(4))].was_clicked());
+        assert!(session.get_progress().iter().all(|progress| progress.status == RacerStatus::Racing && progress.percent_cleared > 0.0));
+
+        /* Moves only touch the racer's own field */
+        let (x_position, y_position) = find_tiles(&session, racers[0], false)[0];
+        session.handle_action(racers[0], Action::Flag { x_position, y_position }).unwrap();
+        assert!(session.get_game(Some(racers[0])).get_tiles()[(Horizontal(x_position), Vertical(y_position))].has_flag());
+        assert!(!session.get_game(Some(racers[1])).get_tiles()[(Horizontal(x_position), Vertical(y_position))].has_flag());
+    }
+
+    #[test]
+    fn the_first_racer_to_clear_the_board_wins()
+    {
+        let (mut session, racers) = race(3);
+        session.handle_action(racers[0], Action::StartRace).unwrap();
+
+        let (x_position, y_position) = find_tiles(&session, racers[2], true)[0];
+        session.handle_action(racers[2], Action::Expose { x_position, y_position }).unwrap();
+        assert_eq!(status(&session, racers[2]), RacerStatus::Dead);
+        assert!(!session.is_over());
+
+        while let Some(&(x_position, y_position)) = find_tiles(&session, racers[1], false).first()
+        {
+            session.handle_action(racers[1], Action::Expose { x_position, y_position }).unwrap();
+        }
+        assert!(session.is_over());
+        assert_eq!(session.get_winners(), vec![racers[1]]);
+        assert_eq!(status(&session, racers[1]), RacerStatus::Finished);
+        assert_eq!(status(&session, racers[0]), RacerStatus::Racing);
+        assert!(session.get_progress()[racers[1].0].percent_cleared >= 100.0);
+
+        /* The race is over for everyone else too */
+        let (x_position, y_position) = find_tiles(&session, racers[0], false)[0];
+        match session.handle_action(racers[0], Action::Expose { x_position, y_position })
+        {
+            Err(Error(ErrorKind::NotAllowed(..), _)) => {},
+            other => panic!("expected moves after the finish to be refused, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn racers_can_forfeit()
+    {
+        let (mut session, racers) = race(2);
+        session.handle_action(racers[0], Action::StartRace).unwrap();
+
+        session.leave(racers[0]).unwrap();
+        assert_eq!(status(&session, racers[0]), RacerStatus::Left);
+        assert!(!session.is_over());
+        match session.handle_action(racers[0], Action::Expose { x_position: 0, y_position: 0 })
+        {
+            Err(Error(ErrorKind::UnknownPlayer(0), _)) => {},
+            other => panic!("expected a racer that left to be refused, got {:?}", other),
+        }
+
+        /* Nobody is left racing once the last one dies, so nobody wins */
+        let (x_position, y_position) = find_tiles(&session, racers[1], true)[0];
+        session.handle_action(racers[1], Action::Expose { x_position, y_position }).unwrap();
+        assert!(session.is_over());
+        assert!(session.get_winners().is_empty());
+    }
+}
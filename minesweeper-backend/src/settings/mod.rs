@@ -0,0 +1,235 @@
+use ::errors::Result;
+
+use ::common::{Horizontal, Vertical};
+use ::engine::minesweeper::FirstClickPolicy;
+use ::engine::preset::Preset;
+
+use serde_json;
+use serde_json::Value;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::Path;
+
+
+/// Bumped whenever the layout of `Settings` changes in a way `migrate` needs to know about
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Window sizes from the file or the UI are clamped to these, the webview takes whatever it's given
+pub const MIN_WINDOW_WIDTH: i32 = 320;
+pub const MIN_WINDOW_HEIGHT: i32 = 240;
+pub const MAX_WINDOW_SIZE: i32 = 16_384;
+/// Fields up to this many tiles can be sent as JSON, more than that has to use the compact encoding
+pub const MAX_COMPACT_FIELD_THRESHOLD: usize = 100_000;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Theme
+{
+    Light,
+    Dark,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings
+{
+    pub version: u32,
+
+    /// When not `Custom` the preset's dimensions are used instead of width/height/num_bombs
+    pub preset: Preset,
+    pub width: usize,
+    pub height: usize,
+    pub num_bombs: usize,
+
+    pub window_width: i32,
+    pub window_height: i32,
+
+    pub theme: Theme,
+    pub first_click_policy: FirstClickPolicy,
+
+    /// Fields with more tiles than this are sent to the UI using the compact encoding instead of JSON
+    pub compact_field_threshold: usize,
+
+    /// Same syntax as `RUST_LOG`, which still wins when set. Only read at startup
+    pub log_level: Option<String>,
+
+    /// The version the file was migrated from when it was loaded, if it was outdated
+    #[serde(skip)]
+    pub migrated_from: Option<u32>,
+}
+
+/// A partial change to `Settings`, anything left `None` keeps its current value
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default)]
+pub struct SettingsUpdate
+{
+    pub window_width: Option<i32>,
+    pub window_height: Option<i32>,
+    pub theme: Option<Theme>,
+    pub first_click_policy: Option<FirstClickPolicy>,
+    pub log_level: Option<String>,
+}
+
+impl Default for Settings
+{
+    fn default() -> Settings
+    {
+        Settings {
+            version: SETTINGS_VERSION,
+
+            preset: Preset::Custom,
+            width: 8,
+            height: 8,
+            num_bombs: 10,
+
+            window_width: 800,
+            window_height: 600,
+
+            theme: Theme::Light,
+            first_click_policy: FirstClickPolicy::SafeTile,
+
+            compact_field_threshold: 10_000,
+
+            log_level: None,
+
+            migrated_from: None,
+        }
+    }
+}
+
+impl Settings
+{
+    /// A missing file gives the defaults, an unreadable or too new file is an error so the caller can reset it
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Settings>
+    {
+        match File::open(path.as_ref())
+        {
+            Ok(file) => Settings::migrate(serde_json::from_reader(BufReader::new(file))?),
+            Err(ref error) if error.kind() == ErrorKind::NotFound => Ok(Settings::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()>
+    {
+        let file = File::create(path.as_ref())?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+
+    fn migrate(raw: Value) -> Result<Settings>
+    {
+        /* Files written before versioning have no `version` */
+        let version = match raw.get("version")
+        {
+            None => 0,
+            Some(version) => version.as_u64().ok_or("Settings version was not a number")? as u32,
+        };
+
+        if version > SETTINGS_VERSION
+        {
+            bail!("Settings version {} is newer than the supported version {}", version, SETTINGS_VERSION);
+        }
+
+        /* Every field so far has a default so older files only need the missing fields filled in */
+        let mut settings: Settings = serde_json::from_value(raw)?;
+
+        if version < SETTINGS_VERSION
+        {
+            settings.version = SETTINGS_VERSION;
+            settings.migrated_from = Some(version);
+        }
+        settings.clamp_numbers();
+
+        Ok(settings)
+    }
+
+    pub fn game_dimensions(&self) -> (Horizontal, Vertical, usize)
+    {
+        self.preset.dimensions().unwrap_or((Horizontal(self.width), Vertical(self.height), self.num_bombs))
+    }
+
+    /// Returns whether anything changed
+    pub fn set_last_game(&mut self, width: Horizontal, height: Vertical, num_bombs: usize) -> bool
+    {
+        let preset = Preset::from_dimensions(width, height, num_bombs);
+        let changed = self.preset != preset || self.width != width.0 || self.height != height.0 || self.num_bombs != num_bombs;
+
+        self.preset = preset;
+        self.width = width.0;
+        self.height = height.0;
+        self.num_bombs = num_bombs;
+
+        changed
+    }
+
+    /// Returns whether anything changed
+    pub fn apply(&mut self, update: SettingsUpdate) -> bool
+    {
+        let before = self.clone();
+
+        if let Some(window_width) = update.window_width
+        {
+            self.window_width = window_width;
+        }
+        if let Some(window_height) = update.window_height
+        {
+            self.window_height = window_height;
+        }
+        if let Some(theme) = update.theme
+        {
+            self.theme = theme;
+        }
+        if let Some(first_click_policy) = update.first_click_policy
+        {
+            self.first_click_policy = first_click_policy;
+        }
+        if let Some(log_level) = update.log_level
+        {
+            self.log_level = Some(log_level);
+        }
+        self.clamp_numbers();
+
+        &before != self
+    }
+
+    /// The game's dimensions are checked when the game is made, everything else numeric is clamped here
+    fn clamp_numbers(&mut self)
+    {
+        let clamped = (
+            self.window_width.clamp(MIN_WINDOW_WIDTH, MAX_WINDOW_SIZE),
+            self.window_height.clamp(MIN_WINDOW_HEIGHT, MAX_WINDOW_SIZE),
+            self.compact_field_threshold.min(MAX_COMPACT_FIELD_THRESHOLD),
+        );
+
+        if clamped != (self.window_width, self.window_height, self.compact_field_threshold)
+        {
+            warn!("Clamped window:{}x{} compact_field_threshold:{} to {}x{} and {}",
+                self.window_width, self.window_height, self.compact_field_threshold, clamped.0, clamped.1, clamped.2);
+        }
+
+        self.window_width = clamped.0;
+        self.window_height = clamped.1;
+        self.compact_field_threshold = clamped.2;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn numbers_are_clamped_when_loaded_and_updated()
+    {
+        let raw = ::serde_json::json!({ "version": SETTINGS_VERSION, "window_width": 0, "window_height": 1_000_000, "compact_field_threshold": usize::MAX });
+        let settings = Settings::migrate(raw).unwrap();
+        assert_eq!((settings.window_width, settings.window_height), (MIN_WINDOW_WIDTH, MAX_WINDOW_SIZE));
+        assert_eq!(settings.compact_field_threshold, MAX_COMPACT_FIELD_THRESHOLD);
+
+        let mut settings = Settings::default();
+        assert!(settings.apply(SettingsUpdate { window_width: Some(-5), window_height: Some(700), ..SettingsUpdate::default() }));
+        assert_eq!((settings.window_width, settings.window_height), (MIN_WINDOW_WIDTH, 700));
+    }
+}
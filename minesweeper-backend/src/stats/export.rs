@@ -0,0 +1,108 @@
+use ::errors::{ErrorKind, Result};
+
+use super::store::Stats;
+
+use serde_json;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat
+{
+    Csv,
+    Json,
+}
+
+impl Stats
+{
+    pub fn export<P: AsRef<Path>>(&self, format: ExportFormat, path: P) -> Result<()>
+    {
+        let mut writer = BufWriter::new(File::create(path.as_ref())?);
+
+        match format
+        {
+            ExportFormat::Csv => self.export_csv(&mut writer)?,
+            ExportFormat::Json => self.export_json(&mut writer)?,
+        };
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Exports into `dir`, `file_name` comes from the UI so it's rejected unless it's only a file name
+    pub fn export_to<P: AsRef<Path>>(&self, format: ExportFormat, dir: P, file_name: &str) -> Result<PathBuf>
+    {
+        if !is_plain_file_name(file_name)
+        {
+            bail!(ErrorKind::InvalidFileName(file_name.to_string()));
+        }
+
+        let path = dir.as_ref().join(file_name);
+        self.export(format, &path)?;
+
+        Ok(path)
+    }
+
+    /// One row per game, none of the fields can contain a `,` so nothing needs quoting
+    pub fn export_csv<W: Write>(&self, writer: &mut W) -> Result<()>
+    {
+        writeln!(writer, "started,finished,seconds,preset,width,height,num_bombs,outcome")?;
+
+        for record in self.history().iter()
+        {
+            writeln!(writer, "{},{},{:.3},{:?},{},{},{},{:?}",
+                record.started.to_rfc3339(),
+                record.finished.to_rfc3339(),
+                record.seconds(),
+                record.preset,
+                record.width,
+                record.height,
+                record.num_bombs,
+                record.outcome,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn export_json<W: Write>(&self, writer: &mut W) -> Result<()>
+    {
+        serde_json::to_writer_pretty(writer, self.history())?;
+
+        Ok(())
+    }
+}
+
+/// A single normal path component, so joining it onto a directory can't climb out of it or replace it.
+/// Separators from any platform are refused as well as a `:` for Windows drives
+fn is_plain_file_name(file_name: &str) -> bool
+{
+    let mut components = Path::new(file_name).components();
+    match (components.next(), components.next())
+    {
+        (Some(Component::Normal(_)), None) => !file_name.contains(['/', '\\', ':', '\0']),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::is_plain_file_name;
+
+    #[test]
+    fn only_plain_file_names_are_allowed()
+    {
+        assert!(is_plain_file_name("stats.csv"));
+        assert!(is_plain_file_name("..stats.json"));
+
+        for file_name in &["", ".", "..", "../stats.csv", "/etc/passwd", "exports/stats.csv", "stats.csv/", "..\\stats.csv", "C:stats.csv"]
+        {
+            assert!(!is_plain_file_name(file_name), "{:?} should be refused", file_name);
+        }
+    }
+}
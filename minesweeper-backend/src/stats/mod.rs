@@ -0,0 +1,5 @@
+pub mod store;
+pub mod export;
+
+pub use self::store::{GameRecord, Outcome, PresetSummary, Stats, Summary};
+pub use self::export::ExportFormat;
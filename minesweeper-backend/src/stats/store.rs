@@ -0,0 +1,188 @@
+use ::errors::Result;
+
+use ::engine::minesweeper::Minesweeper;
+use ::engine::preset::Preset;
+
+use chrono::{DateTime, Utc};
+use serde_json;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::Path;
+
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome
+{
+    Won,
+    Loss,
+    /// The player started a new game or quit while this one was still in progress
+    Abandoned,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GameRecord
+{
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+
+    pub width: usize,
+    pub height: usize,
+    pub num_bombs: usize,
+    pub preset: Preset,
+
+    pub outcome: Outcome,
+}
+
+impl GameRecord
+{
+    pub fn seconds(&self) -> f64
+    {
+        self.finished.signed_duration_since(self.started).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PresetSummary
+{
+    pub preset: Preset,
+    pub played: usize,
+    pub won: usize,
+    /// Average time of the games that were won, `None` until one is won
+    pub average_seconds: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Summary
+{
+    pub played: usize,
+    pub won: usize,
+    pub lost: usize,
+    pub abandoned: usize,
+
+    pub current_streak: usize,
+    pub best_streak: usize,
+
+    pub presets: Vec<PresetSummary>,
+}
+
+/// Every finished game, persisted as JSON so it survives restarts
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct Stats
+{
+    history: Vec<GameRecord>,
+
+    /// When the game currently being played had its first move
+    #[serde(skip)]
+    current_start: Option<DateTime<Utc>>,
+}
+
+impl Stats
+{
+    /// A missing file is treated as a player who hasn't finished any games yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Stats>
+    {
+        match File::open(path.as_ref())
+        {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+            Err(ref error) if error.kind() == ErrorKind::NotFound => Ok(Stats::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()>
+    {
+        let file = File::create(path.as_ref())?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+
+    pub fn history(&self) -> &Vec<GameRecord>
+    {
+        &self.history
+    }
+
+    pub fn is_game_in_progress(&self) -> bool
+    {
+        self.current_start.is_some()
+    }
+
+    /// Marks the start of the timer, called once the first move is made
+    pub fn start_game(&mut self)
+    {
+        if self.current_start.is_none()
+        {
+            self.current_start = Some(Utc::now());
+        }
+    }
+
+    pub fn finish_game(&mut self, game: &Minesweeper, outcome: Outcome)
+    {
+        let finished = Utc::now();
+        let started = self.current_start.take().unwrap_or(finished);
+
+        self.history.push(GameRecord {
+            started,
+            finished,
+
+            width: game.get_width().0,
+            height: game.get_height().0,
+            num_bombs: game.get_num_bombs(),
+            preset: Preset::from_dimensions(game.get_width(), game.get_height(), game.get_num_bombs()),
+
+            outcome,
+        });
+    }
+
+    pub fn summary(&self) -> Summary
+    {
+        let count = |outcome: Outcome| self.history.iter().filter(|record| record.outcome == outcome).count();
+
+        /* Anything other than a win breaks a streak */
+        let mut current_streak = 0;
+        let mut best_streak = 0;
+        for record in self.history.iter()
+        {
+            if record.outcome == Outcome::Won
+            {
+                current_streak += 1;
+                best_streak = best_streak.max(current_streak);
+            } else {
+                current_streak = 0;
+            }
+        }
+
+        let presets = Preset::all().iter()
+            .map(|preset| {
+                let played: Vec<&GameRecord> = self.history.iter().filter(|record| &record.preset == preset).collect();
+                let won: Vec<&GameRecord> = played.iter().cloned().filter(|record| record.outcome == Outcome::Won).collect();
+
+                let average_seconds = match won.len()
+                {
+                    0 => None,
+                    num_won => Some(won.iter().map(|record| record.seconds()).sum::<f64>() / num_won as f64),
+                };
+
+                PresetSummary {
+                    preset: *preset,
+                    played: played.len(),
+                    won: won.len(),
+                    average_seconds,
+                }
+            })
+            .collect();
+
+        Summary {
+            played: self.history.len(),
+            won: count(Outcome::Won),
+            lost: count(Outcome::Loss),
+            abandoned: count(Outcome::Abandoned),
+
+            current_streak,
+            best_streak,
+
+            presets,
+        }
+    }
+}
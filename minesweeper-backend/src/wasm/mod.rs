@@ -0,0 +1,150 @@
+//! JavaScript wrapper so the Elm UI can play in a browser tab without any backend process, see `web/index.html`.
+//!
+//! Build with `./build-wasm.sh` from the repository root.
+//! It speaks the same `Action` and `ToUiCommand` JSON as the desktop backend, only the field game is supported.
+//! Settings only last as long as the page and stats need a file system so they stay with the desktop backend
+
+use ::errors::{Error, ErrorKind};
+
+use ::common::{Horizontal, Vertical};
+use ::engine::minesweeper::{Action, Minesweeper};
+use ::protocol::ToUiCommand;
+use ::settings::Settings;
+
+use serde;
+use serde_json;
+
+use wasm_bindgen::prelude::*;
+
+
+#[wasm_bindgen]
+pub struct Game
+{
+    game: Minesweeper,
+    settings: Settings,
+}
+
+#[wasm_bindgen]
+impl Game
+{
+    /// Starts with the same game as a desktop backend that has no settings saved yet
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<Game, JsValue>
+    {
+        let settings = Settings::default();
+        let (width, height, num_bombs) = settings.game_dimensions();
+
+        match Minesweeper::new(width, height, num_bombs)
+        {
+            Ok(mut game) =>
+            {
+                game.set_first_click_policy(settings.first_click_policy);
+                Ok(Game { game, settings })
+            },
+            Err(error) => Err(JsValue::from_str(&error.to_string())),
+        }
+    }
+
+    /// What the UI needs as soon as it has loaded, as a JSON array of commands
+    pub fn initial(&self) -> String
+    {
+        let mut commands = Vec::new();
+        push(&mut commands, &ToUiCommand::NewField {tiles: self.game.get_tiles()});
+        push(&mut commands, &ToUiCommand::from_state(self.game.get_state()));
+        push(&mut commands, &ToUiCommand::Settings {settings: &self.settings});
+
+        to_array(&commands)
+    }
+
+    /// Throws the current game away for a new one, returns the commands that show it or why it was refused as a JSON array
+    pub fn start(&mut self, width: usize, height: usize, num_bombs: usize) -> String
+    {
+        let mut commands = Vec::new();
+
+        match self.game.resize(Horizontal(width), Vertical(height), num_bombs)
+        {
+            Ok(_) =>
+            {
+                self.settings.set_last_game(Horizontal(width), Vertical(height), num_bombs);
+                push(&mut commands, &ToUiCommand::NewField {tiles: self.game.get_tiles()});
+                push(&mut commands, &ToUiCommand::InProgress);
+            },
+            /* `resize` leaves the game alone when it fails, so the UI keeps showing it */
+            Err(error) => push_error(&mut commands, &error),
+        }
+
+        to_array(&commands)
+    }
+
+    /// `json` is one `Action` from the UI, returns the commands to send back to it as a JSON array
+    pub fn action(&mut self, json: &str) -> String
+    {
+        let mut commands = Vec::new();
+
+        match serde_json::from_str(json)
+        {
+            Ok(Action::Start{ width, height, num_bombs }) => return self.start(width, height, num_bombs),
+            Ok(Action::RequestField) => return self.tiles(),
+            Ok(Action::UpdateSettings{ settings: update }) =>
+            {
+                if self.settings.apply(update)
+                {
+                    self.game.set_first_click_policy(self.settings.first_click_policy);
+                }
+                push(&mut commands, &ToUiCommand::Settings {settings: &self.settings});
+            },
+            /* A page can't close its own tab, there's nothing to save either */
+            Ok(Action::Quit) => {},
+            Ok(action @ Action::Flag{ .. }) | Ok(action @ Action::Unflag{ .. }) |
+            Ok(action @ Action::Expose{ .. }) | Ok(action @ Action::Chord{ .. }) =>
+            {
+                if let Err(error) = self.game.handle_action(action)
+                {
+                    push_error(&mut commands, &error);
+                }
+
+                let changes = self.game.get_changed_tiles();
+                if !changes.is_empty()
+                {
+                    push(&mut commands, &ToUiCommand::TilesChanged {changes});
+                }
+                push(&mut commands, &ToUiCommand::Summary {summary: self.game.get_summary()});
+                push(&mut commands, &ToUiCommand::from_state(self.game.get_state()));
+            },
+            Ok(action) => push_error(&mut commands, &ErrorKind::Unsupported(format!("{:?}", action)).into()),
+            Err(error) => push_error(&mut commands, &error.into()),
+        }
+
+        to_array(&commands)
+    }
+
+    /// The whole field and the state of the game, for when the UI has loaded or lost track of it
+    pub fn tiles(&self) -> String
+    {
+        let mut commands = Vec::new();
+        push(&mut commands, &ToUiCommand::NewField {tiles: self.game.get_tiles()});
+        push(&mut commands, &ToUiCommand::from_state(self.game.get_state()));
+
+        to_array(&commands)
+    }
+}
+
+fn push<S: serde::ser::Serialize>(commands: &mut Vec<String>, command: &S)
+{
+    match serde_json::to_string(command)
+    {
+        Ok(json) => commands.push(json),
+        Err(error) => error!("failed to serialize for ui because {}", error),
+    }
+}
+
+fn push_error(commands: &mut Vec<String>, error: &Error)
+{
+    push(commands, &ToUiCommand::from_error(error));
+}
+
+/// Every command is already JSON, so they only need joining up
+fn to_array(commands: &[String]) -> String
+{
+    format!("[{}]", commands.join(","))
+}
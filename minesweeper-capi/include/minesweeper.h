@@ -0,0 +1,165 @@
+#ifndef MINESWEEPER_H
+#define MINESWEEPER_H
+
+/* Generated by cbindgen from src/lib.rs, don't edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Numbers are part of the ABI, only ever add new ones at the end
+ */
+typedef enum MsError {
+  MS_ERROR_OK = 0,
+  MS_ERROR_NULL_POINTER = 1,
+  /**
+   * A number that doesn't stand for any `MsActionKind` or `MsFirstClickPolicy`
+   */
+  MS_ERROR_INVALID_ARGUMENT = 2,
+  /**
+   * The output buffer can't hold every tile
+   */
+  MS_ERROR_BUFFER_TOO_SMALL = 3,
+  /**
+   * A bug in the engine, the game should be thrown away
+   */
+  MS_ERROR_PANIC = 4,
+  MS_ERROR_OUT_OF_BOUNDS = 5,
+  MS_ERROR_OVERFLOW = 6,
+  MS_ERROR_EMPTY_FIELD = 7,
+  MS_ERROR_NO_BOMBS = 8,
+  MS_ERROR_TOO_MANY_BOMBS = 9,
+  MS_ERROR_NOT_ALLOWED = 10,
+  MS_ERROR_ALREADY_FLAGGED = 11,
+  MS_ERROR_NOT_FLAGGED = 12,
+  MS_ERROR_ALREADY_SHOWN = 13,
+  MS_ERROR_FLAGGED = 14,
+  MS_ERROR_NOTHING_TO_CHORD = 15,
+  MS_ERROR_WRONG_FLAG_COUNT = 16,
+  MS_ERROR_OTHER = 17,
+} MsError;
+
+typedef enum MsState {
+  MS_STATE_NEW = 0,
+  MS_STATE_IN_PROGRESS = 1,
+  MS_STATE_WON = 2,
+  MS_STATE_LOSS = 3,
+} MsState;
+
+typedef enum MsActionKind {
+  MS_ACTION_KIND_EXPOSE = 0,
+  MS_ACTION_KIND_FLAG = 1,
+  MS_ACTION_KIND_UNFLAG = 2,
+  MS_ACTION_KIND_CHORD = 3,
+} MsActionKind;
+
+typedef enum MsFirstClickPolicy {
+  MS_FIRST_CLICK_POLICY_UNPROTECTED = 0,
+  MS_FIRST_CLICK_POLICY_SAFE_TILE = 1,
+  MS_FIRST_CLICK_POLICY_SAFE_OPENING = 2,
+} MsFirstClickPolicy;
+
+/**
+ * Opaque handle to one game, only ever used through a pointer from `ms_game_new`
+ */
+typedef struct MsGame MsGame;
+
+typedef struct MsSummary {
+  size_t width;
+  size_t height;
+  size_t num_bombs;
+  size_t num_flags;
+  size_t num_shown;
+} MsSummary;
+
+/**
+ * A tile as a player sees it, `is_bomb` is only ever set once a bomb has been clicked
+ */
+typedef struct MsTile {
+  bool is_shown;
+  bool was_clicked;
+  bool has_flag;
+  bool is_bomb;
+  uint8_t num_bombs_around;
+} MsTile;
+
+/**
+ * Creates a game, `*out` has to be freed with `ms_game_free`
+ *
+ * # Safety
+ * `out` has to point at somewhere to write the handle, or be null
+ */
+enum MsError ms_game_new(size_t width, size_t height, size_t num_bombs, struct MsGame **out);
+
+/**
+ * Frees a game from `ms_game_new`, null is ignored
+ *
+ * # Safety
+ * `game` has to come from `ms_game_new` and not be freed already, or be null
+ */
+void ms_game_free(struct MsGame *game);
+
+/**
+ * Starts a new game on the same handle.
+ * `policy` is an `MsFirstClickPolicy`, the same non-zero `seed` always deals the same field for the same first move and 0 deals a random one
+ *
+ * # Safety
+ * `game` has to come from `ms_game_new` and not be freed yet, or be null
+ */
+enum MsError ms_game_start(struct MsGame *game,
+                           size_t width,
+                           size_t height,
+                           size_t num_bombs,
+                           uint32_t policy,
+                           uint64_t seed);
+
+/**
+ * `kind` is an `MsActionKind`
+ *
+ * # Safety
+ * `game` has to come from `ms_game_new` and not be freed yet, or be null
+ */
+enum MsError ms_game_apply(struct MsGame *game, uint32_t kind, size_t x, size_t y);
+
+/**
+ * # Safety
+ * `game` has to come from `ms_game_new` and not be freed yet, or be null, and `out` has to be writable or null
+ */
+enum MsError ms_game_state(const struct MsGame *game,
+                           enum MsState *out);
+
+/**
+ * # Safety
+ * `game` has to come from `ms_game_new` and not be freed yet, or be null, and `out` has to be writable or null
+ */
+enum MsError ms_game_summary(const struct MsGame *game,
+                             struct MsSummary *out);
+
+/**
+ * # Safety
+ * `game` has to come from `ms_game_new` and not be freed yet, or be null, and `out` has to be writable or null
+ */
+enum MsError ms_game_tile(const struct MsGame *game,
+                          size_t x,
+                          size_t y,
+                          struct MsTile *out);
+
+/**
+ * Copies every tile row by row into `out`, which has room for `len` tiles and needs width * height
+ *
+ * # Safety
+ * `game` has to come from `ms_game_new` and not be freed yet, or be null, and `out` has to have room for `len` tiles or be null
+ */
+enum MsError ms_game_tiles(const struct MsGame *game,
+                           struct MsTile *out,
+                           size_t len);
+
+/**
+ * A description of an `MsError` for people, the string is static and must not be freed
+ */
+const char *ms_error_message(uint32_t error);
+
+#endif  /* MINESWEEPER_H */
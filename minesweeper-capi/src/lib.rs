@@ -0,0 +1,367 @@
+//! Stable C API for the engine, declared in `include/minesweeper.h`.
+//!
+//! The header is generated from this crate with `cbindgen --config cbindgen.toml --output include/minesweeper.h`
+//! from this directory, regenerate it whenever anything here changes. `./check-capi.sh` from the repository root
+//! fails if it's out of date and runs a small C program against the library.
+//! Every function returns an `MsError` and writes results through out pointers, errors and panics never cross into C.
+//! Numbers that stand for an enum come in as plain integers so C can't hand over a value the enum doesn't have
+
+extern crate minesweeper_backend;
+
+use minesweeper_backend::errors::{Error, ErrorKind};
+
+use minesweeper_backend::common::{Horizontal, Vertical};
+use minesweeper_backend::engine::minesweeper::{Action, FirstClickPolicy, Minesweeper, State};
+use minesweeper_backend::engine::packed::PackedTile;
+
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+
+/// Numbers are part of the ABI, only ever add new ones at the end
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MsError
+{
+    Ok = 0,
+    NullPointer = 1,
+    /// A number that doesn't stand for any `MsActionKind` or `MsFirstClickPolicy`
+    InvalidArgument = 2,
+    /// The output buffer can't hold every tile
+    BufferTooSmall = 3,
+    /// A bug in the engine, the game should be thrown away
+    Panic = 4,
+    OutOfBounds = 5,
+    Overflow = 6,
+    EmptyField = 7,
+    NoBombs = 8,
+    TooManyBombs = 9,
+    NotAllowed = 10,
+    AlreadyFlagged = 11,
+    NotFlagged = 12,
+    AlreadyShown = 13,
+    Flagged = 14,
+    NothingToChord = 15,
+    WrongFlagCount = 16,
+    Other = 17,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MsState
+{
+    New = 0,
+    InProgress = 1,
+    Won = 2,
+    Loss = 3,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MsActionKind
+{
+    Expose = 0,
+    Flag = 1,
+    Unflag = 2,
+    Chord = 3,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MsFirstClickPolicy
+{
+    Unprotected = 0,
+    SafeTile = 1,
+    SafeOpening = 2,
+}
+
+/// A tile as a player sees it, `is_bomb` is only ever set once a bomb has been clicked
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MsTile
+{
+    pub is_shown: bool,
+    pub was_clicked: bool,
+    pub has_flag: bool,
+    pub is_bomb: bool,
+    pub num_bombs_around: u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MsSummary
+{
+    pub width: usize,
+    pub height: usize,
+    pub num_bombs: usize,
+    pub num_flags: usize,
+    pub num_shown: usize,
+}
+
+/// Opaque handle to one game, only ever used through a pointer from `ms_game_new`
+pub struct MsGame
+{
+    game: Minesweeper,
+}
+
+fn error_code(error: &Error) -> MsError
+{
+    match error.kind()
+    {
+        ErrorKind::OutOfBounds(..) => MsError::OutOfBounds,
+        ErrorKind::Overflow(..) => MsError::Overflow,
+        ErrorKind::EmptyField(..) => MsError::EmptyField,
+        ErrorKind::NoBombs => MsError::NoBombs,
+        ErrorKind::TooManyBombs(..) => MsError::TooManyBombs,
+        ErrorKind::NotAllowed(..) => MsError::NotAllowed,
+        ErrorKind::AlreadyFlagged(..) => MsError::AlreadyFlagged,
+        ErrorKind::NotFlagged(..) => MsError::NotFlagged,
+        ErrorKind::AlreadyShown(..) => MsError::AlreadyShown,
+        ErrorKind::Flagged(..) => MsError::Flagged,
+        ErrorKind::NothingToChord(..) => MsError::NothingToChord,
+        ErrorKind::WrongFlagCount(..) => MsError::WrongFlagCount,
+        _ => MsError::Other,
+    }
+}
+
+/// Runs `func`, turning a panic into `MsError::Panic` instead of unwinding into C
+fn guard<F: FnOnce() -> MsError>(func: F) -> MsError
+{
+    panic::catch_unwind(AssertUnwindSafe(func)).unwrap_or(MsError::Panic)
+}
+
+/// Runs `func` on the game behind `game`, which C may have left null
+fn with_game<F: FnOnce(&mut Minesweeper) -> MsError>(game: *mut MsGame, func: F) -> MsError
+{
+    guard(|| match unsafe { game.as_mut() }
+    {
+        Some(game) => func(&mut game.game),
+        None => MsError::NullPointer,
+    })
+}
+
+fn with_game_ref<F: FnOnce(&Minesweeper) -> MsError>(game: *const MsGame, func: F) -> MsError
+{
+    guard(|| match unsafe { game.as_ref() }
+    {
+        Some(game) => func(&game.game),
+        None => MsError::NullPointer,
+    })
+}
+
+/// Writes `value` through `out`, which C may have left null
+fn write<T>(out: *mut T, value: T) -> MsError
+{
+    match unsafe { out.as_mut() }
+    {
+        Some(out) =>
+        {
+            *out = value;
+            MsError::Ok
+        },
+        None => MsError::NullPointer,
+    }
+}
+
+fn to_tile(tile: &PackedTile) -> MsTile
+{
+    MsTile {
+        is_shown: tile.is_shown(),
+        was_clicked: tile.was_clicked(),
+        has_flag: tile.has_flag(),
+        is_bomb: tile.is_bomb(),
+        num_bombs_around: tile.num_bombs_around() as u8,
+    }
+}
+
+/// Creates a game, `*out` has to be freed with `ms_game_free`
+///
+/// # Safety
+/// `out` has to point at somewhere to write the handle, or be null
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_new(width: usize, height: usize, num_bombs: usize, out: *mut *mut MsGame) -> MsError
+{
+    guard(|| {
+        if out.is_null()
+        {
+            return MsError::NullPointer;
+        }
+
+        match Minesweeper::new(Horizontal(width), Vertical(height), num_bombs)
+        {
+            Ok(game) => write(out, Box::into_raw(Box::new(MsGame { game }))),
+            Err(error) => error_code(&error),
+        }
+    })
+}
+
+/// Frees a game from `ms_game_new`, null is ignored
+///
+/// # Safety
+/// `game` has to come from `ms_game_new` and not be freed already, or be null
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_free(game: *mut MsGame)
+{
+    if !game.is_null()
+    {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| Box::from_raw(game)));
+    }
+}
+
+/// Starts a new game on the same handle.
+/// `policy` is an `MsFirstClickPolicy`, the same non-zero `seed` always deals the same field for the same first move and 0 deals a random one
+///
+/// # Safety
+/// `game` has to come from `ms_game_new` and not be freed yet, or be null
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_start(game: *mut MsGame, width: usize, height: usize, num_bombs: usize, policy: u32, seed: u64) -> MsError
+{
+    with_game(game, |game| {
+        let policy = match policy
+        {
+            0 => FirstClickPolicy::Unprotected,
+            1 => FirstClickPolicy::SafeTile,
+            2 => FirstClickPolicy::SafeOpening,
+            _ => return MsError::InvalidArgument,
+        };
+
+        if let Err(error) = game.resize(Horizontal(width), Vertical(height), num_bombs)
+        {
+            return error_code(&error);
+        }
+        game.set_first_click_policy(policy);
+        game.set_seed(if seed == 0 { None } else { Some(seed) });
+
+        MsError::Ok
+    })
+}
+
+/// `kind` is an `MsActionKind`
+///
+/// # Safety
+/// `game` has to come from `ms_game_new` and not be freed yet, or be null
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_apply(game: *mut MsGame, kind: u32, x: usize, y: usize) -> MsError
+{
+    with_game(game, |game| {
+        let (x_position, y_position) = (x, y);
+        let action = match kind
+        {
+            0 => Action::Expose { x_position, y_position },
+            1 => Action::Flag { x_position, y_position },
+            2 => Action::Unflag { x_position, y_position },
+            3 => Action::Chord { x_position, y_position },
+            _ => return MsError::InvalidArgument,
+        };
+
+        match game.handle_action(action)
+        {
+            Ok(_) => MsError::Ok,
+            Err(error) => error_code(&error),
+        }
+    })
+}
+
+/// # Safety
+/// `game` has to come from `ms_game_new` and not be freed yet, or be null, and `out` has to be writable or null
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_state(game: *const MsGame, out: *mut MsState) -> MsError
+{
+    with_game_ref(game, |game| {
+        let state = match *game.get_state()
+        {
+            State::New => MsState::New,
+            State::InProgress => MsState::InProgress,
+            State::Won => MsState::Won,
+            State::Loss => MsState::Loss,
+        };
+        write(out, state)
+    })
+}
+
+/// # Safety
+/// `game` has to come from `ms_game_new` and not be freed yet, or be null, and `out` has to be writable or null
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_summary(game: *const MsGame, out: *mut MsSummary) -> MsError
+{
+    with_game_ref(game, |game| {
+        let summary = game.get_summary();
+        write(out, MsSummary {
+            width: summary.width,
+            height: summary.height,
+            num_bombs: summary.num_bombs,
+            num_flags: summary.num_flags,
+            num_shown: summary.num_shown,
+        })
+    })
+}
+
+/// # Safety
+/// `game` has to come from `ms_game_new` and not be freed yet, or be null, and `out` has to be writable or null
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_tile(game: *const MsGame, x: usize, y: usize, out: *mut MsTile) -> MsError
+{
+    with_game_ref(game, |game| match game.get_tiles().get(&Horizontal(x), &Vertical(y))
+    {
+        Some(tile) => write(out, to_tile(tile)),
+        None => MsError::OutOfBounds,
+    })
+}
+
+/// Copies every tile row by row into `out`, which has room for `len` tiles and needs width * height
+///
+/// # Safety
+/// `game` has to come from `ms_game_new` and not be freed yet, or be null, and `out` has to have room for `len` tiles or be null
+#[no_mangle]
+pub unsafe extern "C" fn ms_game_tiles(game: *const MsGame, out: *mut MsTile, len: usize) -> MsError
+{
+    with_game_ref(game, |game| {
+        let tiles = game.get_tiles();
+        if out.is_null()
+        {
+            return MsError::NullPointer;
+        }
+        if len < tiles.len()
+        {
+            return MsError::BufferTooSmall;
+        }
+
+        let out = ::std::slice::from_raw_parts_mut(out, tiles.len());
+        for (out, tile) in out.iter_mut().zip(tiles.iter())
+        {
+            *out = to_tile(tile);
+        }
+
+        MsError::Ok
+    })
+}
+
+/// Messages for every `MsError` in order, each ending in a nul for C
+const MESSAGES: [&[u8]; 18] = [
+    b"ok\0",
+    b"a pointer was null\0",
+    b"an argument is out of range\0",
+    b"the buffer is too small for every tile\0",
+    b"the engine panicked\0",
+    b"tile is outside of the field\0",
+    b"field is too big\0",
+    b"field has no tiles\0",
+    b"field has no bombs\0",
+    b"field has too many bombs\0",
+    b"action isn't allowed right now\0",
+    b"tile already has a flag\0",
+    b"tile doesn't have a flag\0",
+    b"tile is already shown\0",
+    b"tile has a flag\0",
+    b"tile can't be chorded\0",
+    b"tile has the wrong number of flags around it to chord\0",
+    b"something else went wrong\0",
+];
+
+/// A description of an `MsError` for people, the string is static and must not be freed
+#[no_mangle]
+pub extern "C" fn ms_error_message(error: u32) -> *const c_char
+{
+    let message = MESSAGES.get(error as usize).cloned().unwrap_or(b"unknown error\0");
+    message.as_ptr() as *const c_char
+}
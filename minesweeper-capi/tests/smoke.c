@@ -0,0 +1,88 @@
+/* Plays one seeded game through the C API, run by ./check-capi.sh from the repository root */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "minesweeper.h"
+
+#define CHECK(call, expected)                                                           \
+  do {                                                                                  \
+    MsError error_ = (call);                                                            \
+    if (error_ != (expected)) {                                                         \
+      fprintf(stderr, "%s:%d: %s gave %d (%s), expected %d\n", __FILE__, __LINE__, #call, \
+              (int)error_, ms_error_message(error_), (int)(expected));                  \
+      exit(1);                                                                          \
+    }                                                                                   \
+  } while (0)
+
+int main(void) {
+  MsGame *game = NULL;
+  MsState state;
+  MsSummary summary;
+  MsTile tile;
+  MsTile tiles[9 * 9];
+
+  /* Bad arguments come back as errors instead of crashing */
+  CHECK(ms_game_new(0, 9, 10, &game), MS_ERROR_EMPTY_FIELD);
+  CHECK(ms_game_new(9, 9, 81, &game), MS_ERROR_TOO_MANY_BOMBS);
+  CHECK(ms_game_new(9, 9, 10, NULL), MS_ERROR_NULL_POINTER);
+  CHECK(ms_game_state(NULL, &state), MS_ERROR_NULL_POINTER);
+
+  CHECK(ms_game_new(9, 9, 10, &game), MS_ERROR_OK);
+  CHECK(ms_game_start(game, 9, 9, 10, 3, 42), MS_ERROR_INVALID_ARGUMENT);
+  CHECK(ms_game_start(game, 9, 9, 10, MS_FIRST_CLICK_POLICY_SAFE_OPENING, 42), MS_ERROR_OK);
+  CHECK(ms_game_state(game, &state), MS_ERROR_OK);
+  if (state != MS_STATE_NEW) {
+    fprintf(stderr, "a fresh game is in state %d\n", (int)state);
+    return 1;
+  }
+
+  /* A safe opening means the first click always opens the square around it */
+  CHECK(ms_game_apply(game, 4, 4, 4), MS_ERROR_INVALID_ARGUMENT);
+  CHECK(ms_game_apply(game, MS_ACTION_KIND_EXPOSE, 9, 4), MS_ERROR_OUT_OF_BOUNDS);
+  CHECK(ms_game_apply(game, MS_ACTION_KIND_EXPOSE, 4, 4), MS_ERROR_OK);
+  CHECK(ms_game_apply(game, MS_ACTION_KIND_EXPOSE, 4, 4), MS_ERROR_ALREADY_SHOWN);
+
+  CHECK(ms_game_tile(game, 4, 4, &tile), MS_ERROR_OK);
+  if (!tile.is_shown || !tile.was_clicked || tile.is_bomb || tile.num_bombs_around != 0) {
+    fprintf(stderr, "the first click didn't open an empty tile\n");
+    return 1;
+  }
+  CHECK(ms_game_tile(game, 9, 9, &tile), MS_ERROR_OUT_OF_BOUNDS);
+
+  CHECK(ms_game_summary(game, &summary), MS_ERROR_OK);
+  if (summary.width != 9 || summary.height != 9 || summary.num_bombs != 10 || summary.num_shown < 9) {
+    fprintf(stderr, "unexpected summary %zux%zu, %zu bombs, %zu shown\n", summary.width, summary.height,
+            summary.num_bombs, summary.num_shown);
+    return 1;
+  }
+
+  CHECK(ms_game_tiles(game, tiles, 9 * 9 - 1), MS_ERROR_BUFFER_TOO_SMALL);
+  CHECK(ms_game_tiles(game, tiles, 9 * 9), MS_ERROR_OK);
+  size_t num_shown = 0;
+  for (size_t i = 0; i < 9 * 9; i++) {
+    num_shown += tiles[i].is_shown;
+  }
+  if (num_shown != summary.num_shown) {
+    fprintf(stderr, "%zu tiles are shown but the summary says %zu\n", num_shown, summary.num_shown);
+    return 1;
+  }
+
+  CHECK(ms_game_state(game, &state), MS_ERROR_OK);
+  if (state != MS_STATE_IN_PROGRESS && state != MS_STATE_WON) {
+    fprintf(stderr, "a safe first click left the game in state %d\n", (int)state);
+    return 1;
+  }
+
+  if (strcmp(ms_error_message(MS_ERROR_OK), "ok") != 0 || strcmp(ms_error_message(1000), "unknown error") != 0) {
+    fprintf(stderr, "unexpected error messages\n");
+    return 1;
+  }
+
+  ms_game_free(game);
+  ms_game_free(NULL);
+
+  printf("C API smoke test passed, %zu tiles opened\n", num_shown);
+  return 0;
+}
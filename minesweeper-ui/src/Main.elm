@@ -1,8 +1,11 @@
 port module Main exposing (main)
 
+import Array exposing (Array)
 import Browser
+import Browser.Events
+import Bitwise
 import Html exposing (Html, Attribute, label, button, div, text, input, table, td, tr)
-import Html.Attributes exposing (type_, value, placeholder)
+import Html.Attributes exposing (type_, value, placeholder, style)
 import Html.Events exposing (onInput, custom)
 
 import Json.Encode as JE
@@ -19,6 +22,12 @@ main =
 
 -- MODEL
 
+type Theme = Light
+            | Dark
+
+type Mode = Classic
+          | Endless
+
 type GameState = InProgress
                 | Won
                 | Loss
@@ -31,13 +40,51 @@ type alias Tile =
     , was_clicked: Bool
     }
 
+type alias Stats =
+    { played: Int
+    , won: Int
+    , lost: Int
+    , abandoned: Int
+    , current_streak: Int
+    , best_streak: Int
+    }
+
+type alias FieldSummary =
+    { width: Int
+    , height: Int
+    , num_bombs: Int
+    , num_flags: Int
+    , num_shown: Int
+    }
+
+-- Top left of the part of the field being shown, only moves when the field is too big to show whole
+type alias Origin =
+    { x: Int
+    , y: Int
+    }
+
+viewportWidth: Int
+viewportWidth = 40
+
+viewportHeight: Int
+viewportHeight = 25
+
 type alias Model = 
     { debug: String
     , width: Int
     , height: Int
     , num_bombs: Int
     , state: GameState
-    , field: List (List Tile)
+    , field: Array (Array Tile)
+    , stats: Maybe Stats
+    , theme: Theme
+    , summary: Maybe FieldSummary
+    , origin: Origin
+    , usingViewport: Bool
+    , mode: Mode
+    , score: Int
+    -- Why the backend rejected the last action, cleared by the player's next move
+    , lastError: Maybe String
     }
 
 init : () -> (Model, Cmd Msg)
@@ -47,7 +94,15 @@ init _ =
     , height= 10
     , num_bombs= 8
     , state= InProgress
-    , field= [[ ]]
+    , field= Array.empty
+    , stats= Nothing
+    , theme= Light
+    , summary= Nothing
+    , origin= { x= 0, y= 0 }
+    , usingViewport= False
+    , mode= Classic
+    , score= 0
+    , lastError= Nothing
     }
   , Cmd.none
   )
@@ -97,6 +152,71 @@ sendQuit =
     in
         toBackEnd str
 
+themeToString: Theme -> String
+themeToString theme =
+    case theme of
+        Light -> "Light"
+        Dark -> "Dark"
+
+sendSettingsUpdate: List (String, JE.Value) -> Cmd Msg
+sendSettingsUpdate fields =
+    let 
+        json = JE.object    [ ("_type", JE.string "UpdateSettings")
+                            , ("settings", JE.object fields)
+                            ]
+        str = JE.encode 0 json
+    in
+        toBackEnd str
+
+sendViewport: Origin -> Cmd Msg
+sendViewport origin =
+    let 
+        json = JE.object    [ ("_type", JE.string "SetViewport")
+                            , ("x", JE.int origin.x)
+                            , ("y", JE.int origin.y)
+                            , ("w", JE.int viewportWidth)
+                            , ("h", JE.int viewportHeight)
+                            ]
+        str = JE.encode 0 json
+    in
+        toBackEnd str
+
+sendClearViewport: Cmd Msg
+sendClearViewport =
+    let 
+        json = JE.object    [ ("_type", JE.string "ClearViewport")
+                            ]
+        str = JE.encode 0 json
+    in
+        toBackEnd str
+
+sendEndless: List (String, JE.Value) -> Cmd Msg
+sendEndless action =
+    let 
+        json = JE.object    [ ("_type", JE.string "Endless")
+                            , ("action", JE.object action)
+                            ]
+        str = JE.encode 0 json
+    in
+        toBackEnd str
+
+sendEndlessMove: String -> Int -> Int -> Cmd Msg
+sendEndlessMove move h w =
+    sendEndless [ ("_type", JE.string move), ("x", JE.int w), ("y", JE.int h) ]
+
+sendEndlessViewport: Origin -> Cmd Msg
+sendEndlessViewport origin =
+    sendEndless [ ("_type", JE.string "SetViewport")
+                , ("x", JE.int origin.x)
+                , ("y", JE.int origin.y)
+                , ("w", JE.int viewportWidth)
+                , ("h", JE.int viewportHeight)
+                ]
+
+requestEndless: Cmd Msg
+requestEndless =
+    sendEndless [ ("_type", JE.string "Start"), ("seed", JE.null), ("density", JE.null) ]
+
 requestNewField: Model -> Cmd Msg
 requestNewField model =
     let 
@@ -115,7 +235,19 @@ type Msg = Error String
         | UpdateHeight String
         | UpdateNumBombs String
         | UpdateState GameState
-        | UpdateField (List (List Tile))
+        | UpdateField (Array (Array Tile))
+        | UpdateTiles (List (Int, Int, Tile))
+        | UpdateViewportField Origin (Array (Array Tile)) FieldSummary
+        | UpdateSummary FieldSummary
+        | Pan Int Int
+        | UpdateEndlessField Origin (Array (Array Tile)) Int
+        | UpdateEndlessTiles (List (Int, Int, Tile)) Int
+        | RequestEndless
+        | BackendError String String
+        | UpdateStats Stats
+        | UpdateSettings Int Int Int Theme
+        | ToggleTheme
+        | WindowResized Int Int
 
         | Quit
 
@@ -139,19 +271,68 @@ update msg model =
         UpdateState newState ->
             ( {model| state = newState}, Cmd.none)
         UpdateField newField ->
-            ( {model| field = newField}, Cmd.none)
+            ( {model| field = newField, origin = { x= 0, y= 0 }}, Cmd.none)
+        UpdateTiles changes ->
+            ( {model| field = List.foldl (setTile model.origin) model.field changes}, Cmd.none)
+        UpdateViewportField origin newField newSummary ->
+            ( {model| field = newField, origin = origin, summary = Just newSummary}, Cmd.none)
+        UpdateSummary newSummary ->
+            ( {model| summary = Just newSummary}, Cmd.none)
+        UpdateEndlessField origin newField score ->
+            ( {model| mode = Endless, field = newField, origin = origin, score = score}, Cmd.none)
+        UpdateEndlessTiles changes score ->
+            ( {model| field = List.foldl (setTile model.origin) model.field changes, score = score}, Cmd.none)
+        RequestEndless ->
+            ( {model| lastError = Nothing, origin = { x= negate (viewportWidth // 2), y= negate (viewportHeight // 2) }}
+            , Cmd.batch [ sendEndlessViewport { x= negate (viewportWidth // 2), y= negate (viewportHeight // 2) }, requestEndless ]
+            )
+        Pan dx dy ->
+            case (model.mode, model.summary) of
+                (Endless, _) ->
+                    ( model, sendEndlessViewport { x= model.origin.x + dx, y= model.origin.y + dy } )
+                (Classic, Just summary) ->
+                    let
+                        origin = { x= clamp 0 (max 0 (summary.width - viewportWidth)) (model.origin.x + dx)
+                                 , y= clamp 0 (max 0 (summary.height - viewportHeight)) (model.origin.y + dy)
+                                 }
+                    in
+                        ( model, sendViewport origin )
+                (Classic, Nothing) ->
+                    ( model, Cmd.none )
+        UpdateStats newStats ->
+            ( {model| stats = Just newStats}, Cmd.none)
+        UpdateSettings width height num_bombs theme ->
+            ( {model| width = width, height = height, num_bombs = num_bombs, theme = theme}, Cmd.none)
+        ToggleTheme ->
+            let
+                newTheme = if model.theme == Light then Dark else Light
+            in
+                ( {model| theme = newTheme}, sendSettingsUpdate [ ("theme", JE.string (themeToString newTheme)) ])
+        WindowResized width height ->
+            ( model, sendSettingsUpdate [ ("window_width", JE.int width), ("window_height", JE.int height) ])
 
         Quit ->
             ( model, sendQuit )
 
         RequestNewField ->
-            ( model, requestNewField model )
+            let
+                classicModel = {model| mode = Classic, lastError = Nothing}
+            in
+                if model.width * model.height > viewportWidth * viewportHeight then
+                    ( {classicModel| usingViewport = True}, Cmd.batch [ sendViewport { x= 0, y= 0 }, requestNewField model ] )
+                else if model.usingViewport then
+                    ( {classicModel| usingViewport = False}, Cmd.batch [ sendClearViewport, requestNewField model ] )
+                else
+                    ( classicModel, requestNewField model )
         Click h w ->
-            ( model, sendClick h w)
+            ( {model| lastError = Nothing}, if model.mode == Endless then sendEndlessMove "Expose" h w else sendClick h w)
         Flag h w ->
-            ( model, sendFlag h w)
+            ( {model| lastError = Nothing}, if model.mode == Endless then sendEndlessMove "Flag" h w else sendFlag h w)
         Unflag h w ->
-            ( model, sendUnflag h w)
+            ( {model| lastError = Nothing}, if model.mode == Endless then sendEndlessMove "Unflag" h w else sendUnflag h w)
+
+        BackendError _ message ->
+            ( {model| lastError = Just message}, Cmd.none)
 
         Error _ ->
             ( model, Cmd.none)
@@ -159,6 +340,15 @@ update msg model =
 
 
 
+setTile: Origin -> (Int, Int, Tile) -> Array (Array Tile) -> Array (Array Tile)
+setTile origin (w, h, tile) field =
+    case Array.get (h - origin.y) field of
+        Just row ->
+            Array.set (h - origin.y) (Array.set (w - origin.x) tile row) field
+        Nothing ->
+            field
+
+
 -- VIEW
 
 -- NOTE: Control+Click on MacOS, also doesn't seem to work that well (it often causes a LeftClick afterwards)
@@ -194,13 +384,19 @@ createFieldSlot h w tile =
             td [] [ text " "]
 
 
-createFieldRow: Int -> (List Tile) -> Html Msg
-createFieldRow h tiles =
-    tr [] (List.indexedMap (\w -> createFieldSlot h w) tiles)
+createFieldRow: Origin -> Int -> (Array Tile) -> Html Msg
+createFieldRow origin h tiles =
+    tr [] (Array.toList (Array.indexedMap (\w -> createFieldSlot (h + origin.y) (w + origin.x)) tiles))
+
+themeStyle: Theme -> List (Attribute Msg)
+themeStyle theme =
+    case theme of
+        Light -> []
+        Dark -> [ style "background-color" "#222", style "color" "#ddd" ]
 
 view : Model -> Html Msg
 view model =
-  div []
+  div (themeStyle model.theme)
     [ div [] [ label [] [ text "Width:" ]
              , input [ type_ "number", value (String.fromInt model.width), onInput UpdateWidth ] []
              , label [] [ text "Height:" ]
@@ -208,15 +404,63 @@ view model =
              , label [] [ text "#of Bombs:" ]
              , input [ value (String.fromInt model.num_bombs), onInput UpdateNumBombs ] []
              , button [ onLeftClick RequestNewField ] [ text "Create!" ]
+             , button [ onLeftClick RequestEndless ] [ text "Endless!" ]
+             , button [ onLeftClick ToggleTheme ] [ text "Theme" ]
              , button [ onLeftClick Quit ] [ text "Quit :(" ]
              ]
     , case model.state of
         InProgress -> text ""
         Won -> text "You Won!"
         Loss -> text "You Lost."
-    , table [] (List.indexedMap createFieldRow model.field)
+    , viewError model.lastError
+    , viewPan (model.usingViewport || model.mode == Endless)
+    , if model.mode == Endless then text ("Score: " ++ String.fromInt model.score) else text ""
+    , table [] (Array.toList (Array.indexedMap (createFieldRow model.origin) model.field))
+    , viewSummary model.summary
+    , viewStats model.stats
     ]
 
+viewError: Maybe String -> Html Msg
+viewError lastError =
+    case lastError of
+        Nothing -> text ""
+        Just message -> div [ style "color" "#c33" ] [ text message ]
+
+viewPan: Bool -> Html Msg
+viewPan usingViewport =
+    if usingViewport then
+        div [] [ button [ onLeftClick (Pan (negate viewportWidth) 0) ] [ text "<" ]
+               , button [ onLeftClick (Pan 0 (negate viewportHeight)) ] [ text "^" ]
+               , button [ onLeftClick (Pan 0 viewportHeight) ] [ text "v" ]
+               , button [ onLeftClick (Pan viewportWidth 0) ] [ text ">" ]
+               ]
+    else
+        text ""
+
+viewSummary: Maybe FieldSummary -> Html Msg
+viewSummary maybeSummary =
+    case maybeSummary of
+        Nothing -> text ""
+        Just summary ->
+            div [] [ text ( "Flags: " ++ String.fromInt summary.num_flags ++ "/" ++ String.fromInt summary.num_bombs
+                         ++ " Shown: " ++ String.fromInt summary.num_shown ++ "/" ++ String.fromInt (summary.width * summary.height)
+                          )
+                   ]
+
+viewStats: Maybe Stats -> Html Msg
+viewStats maybeStats =
+    case maybeStats of
+        Nothing -> text ""
+        Just stats ->
+            div [] [ text ( "Played: " ++ String.fromInt stats.played
+                         ++ " Won: " ++ String.fromInt stats.won
+                         ++ " Lost: " ++ String.fromInt stats.lost
+                         ++ " Abandoned: " ++ String.fromInt stats.abandoned
+                         ++ " Streak: " ++ String.fromInt stats.current_streak
+                         ++ " (Best: " ++ String.fromInt stats.best_streak ++ ")"
+                          )
+                   ]
+
 -- SUBSCRIPTIONS
 
 decodeTile: JD.Decoder Tile
@@ -228,9 +472,111 @@ decodeTile =
         (JD.field "is_shown" JD.bool)
         (JD.field "was_clicked" JD.bool)
 
-decodeField: JD.Decoder (List (List Tile))
+decodeField: JD.Decoder (Array (Array Tile))
 decodeField =
-    JD.list (JD.list decodeTile)
+    JD.array (JD.array decodeTile)
+
+-- Mirrors `engine::encoding::decode_tile` in the backend
+unpackTile: Int -> Tile
+unpackTile byte =
+    { num_bombs_around = Bitwise.and byte 15
+    , has_flag = Bitwise.and byte 16 /= 0
+    , is_bomb = Bitwise.and byte 32 /= 0
+    , is_shown = Bitwise.and byte 64 /= 0
+    , was_clicked = Bitwise.and byte 128 /= 0
+    }
+
+unpackField: Int -> Int -> Array Int -> Array (Array Tile)
+unpackField width height bytes =
+    Array.initialize height (\h ->
+        Array.initialize width (\w ->
+            Array.get (h * width + w) bytes
+                |> Maybe.withDefault 0
+                |> unpackTile
+        )
+    )
+
+decodeCompactField: JD.Decoder (Array (Array Tile))
+decodeCompactField =
+    JD.field "version" JD.int
+        |> JD.andThen (\version ->
+            if version == 1 then
+                JD.map3 unpackField
+                    (JD.field "width" JD.int)
+                    (JD.field "height" JD.int)
+                    (JD.field "tiles" (JD.array JD.int))
+            else
+                JD.fail ("Unsupported compact field version: " ++ String.fromInt version)
+            )
+
+decodeSummary: JD.Decoder FieldSummary
+decodeSummary =
+    JD.map5 FieldSummary
+        (JD.field "width" JD.int)
+        (JD.field "height" JD.int)
+        (JD.field "num_bombs" JD.int)
+        (JD.field "num_flags" JD.int)
+        (JD.field "num_shown" JD.int)
+
+decodeViewportField: JD.Decoder Msg
+decodeViewportField =
+    JD.map3 UpdateViewportField
+        (JD.map2 Origin (JD.field "x" JD.int) (JD.field "y" JD.int))
+        (JD.field "tiles" decodeField)
+        (JD.field "summary" decodeSummary)
+
+decodeEndlessField: JD.Decoder Msg
+decodeEndlessField =
+    JD.map3 UpdateEndlessField
+        (JD.map2 Origin (JD.field "x" JD.int) (JD.field "y" JD.int))
+        (JD.field "tiles" decodeField)
+        (JD.field "score" JD.int)
+
+decodeEndlessTiles: JD.Decoder Msg
+decodeEndlessTiles =
+    JD.map2 UpdateEndlessTiles
+        (JD.field "changes" (JD.list decodeChange))
+        (JD.field "score" JD.int)
+
+decodeChange: JD.Decoder (Int, Int, Tile)
+decodeChange =
+    JD.map3 (\w h tile -> (w, h, tile))
+        (JD.index 0 JD.int)
+        (JD.index 1 JD.int)
+        (JD.index 2 decodeTile)
+
+decodeStats: JD.Decoder Stats
+decodeStats =
+    JD.map6 Stats
+        (JD.field "played" JD.int)
+        (JD.field "won" JD.int)
+        (JD.field "lost" JD.int)
+        (JD.field "abandoned" JD.int)
+        (JD.field "current_streak" JD.int)
+        (JD.field "best_streak" JD.int)
+
+decodeTheme: JD.Decoder Theme
+decodeTheme =
+    JD.string
+        |> JD.andThen (\theme ->
+            case theme of
+                "Dark" -> JD.succeed Dark
+                _ -> JD.succeed Light
+            )
+
+decodeSettings: JD.Decoder Msg
+decodeSettings =
+    JD.map4 UpdateSettings
+        (JD.field "width" JD.int)
+        (JD.field "height" JD.int)
+        (JD.field "num_bombs" JD.int)
+        (JD.field "theme" decodeTheme)
+
+decodeBackendError: JD.Decoder Msg
+decodeBackendError =
+    JD.map2 BackendError
+        (JD.field "code" JD.string)
+        (JD.field "message" JD.string)
 
 decodeValue : JE.Value -> Msg
 decodeValue raw =
@@ -245,6 +591,60 @@ decodeValue raw =
                             UpdateField newField
                         Err error ->
                             Error (JD.errorToString error)
+                Ok "CompactField" ->
+                    case JD.decodeValue decodeCompactField raw of
+                        Ok newField ->
+                            UpdateField newField
+                        Err error ->
+                            Error (JD.errorToString error)
+                Ok "ViewportField" ->
+                    case JD.decodeValue decodeViewportField raw of
+                        Ok updateViewportField ->
+                            updateViewportField
+                        Err error ->
+                            Error (JD.errorToString error)
+                Ok "Summary" ->
+                    case JD.decodeValue (JD.field "summary" decodeSummary) raw of
+                        Ok newSummary ->
+                            UpdateSummary newSummary
+                        Err error ->
+                            Error (JD.errorToString error)
+                Ok "EndlessField" ->
+                    case JD.decodeValue decodeEndlessField raw of
+                        Ok updateEndlessField ->
+                            updateEndlessField
+                        Err error ->
+                            Error (JD.errorToString error)
+                Ok "EndlessTilesChanged" ->
+                    case JD.decodeValue decodeEndlessTiles raw of
+                        Ok updateEndlessTiles ->
+                            updateEndlessTiles
+                        Err error ->
+                            Error (JD.errorToString error)
+                Ok "TilesChanged" ->
+                    case JD.decodeValue (JD.field "changes" (JD.list decodeChange)) raw of
+                        Ok changes ->
+                            UpdateTiles changes
+                        Err error ->
+                            Error (JD.errorToString error)
+                Ok "Stats" ->
+                    case JD.decodeValue (JD.field "stats" decodeStats) raw of
+                        Ok newStats ->
+                            UpdateStats newStats
+                        Err error ->
+                            Error (JD.errorToString error)
+                Ok "Settings" ->
+                    case JD.decodeValue (JD.field "settings" decodeSettings) raw of
+                        Ok updateSettings ->
+                            updateSettings
+                        Err error ->
+                            Error (JD.errorToString error)
+                Ok "Error" ->
+                    case JD.decodeValue decodeBackendError raw of
+                        Ok backendError ->
+                            backendError
+                        Err error ->
+                            Error (JD.errorToString error)
                 Ok "InProgress" ->
                     UpdateState InProgress
                 Ok "Won" ->
@@ -259,4 +659,7 @@ decodeValue raw =
 
 subscriptions : Model -> Sub Msg
 subscriptions model =
-    toFrontEnd (decodeValue)
\ No newline at end of file
+    Sub.batch
+        [ toFrontEnd (decodeValue)
+        , Browser.Events.onResize WindowResized
+        ]
\ No newline at end of file